@@ -2,30 +2,156 @@
 
 #![deny(clippy::all, clippy::pedantic)]
 
+use std::convert::TryFrom;
 use std::io::{self, Write};
+use std::time::{Duration, Instant};
 
-use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent};
 use crossterm::{cursor, execute, queue, style, terminal, Result};
 
-use six::{Editor, Event as Ev, Key, Modifiers};
+use six::{Editor, Event as Ev, Sign};
+
+/// The number of lines scrolled by each step of the mouse wheel.
+const SCROLL_LINES: isize = 3;
+
+/// How long to wait for input before sending an idle event, unless set by `SIX_IDLE_MS`.
+const IDLE_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// Returns how long to wait for input before sending an idle event, given the value of the
+/// `SIX_IDLE_MS` environment variable, in milliseconds.
+fn idle_timeout(var: Option<&str>) -> Duration {
+    var.and_then(|ms| ms.parse().ok()).map_or(IDLE_TIMEOUT, Duration::from_millis)
+}
+
+/// Returns the event sent when no input arrives in time, which holds the time since the last input.
+///
+/// Idle events keep coming once per timeout for as long as there is no input, so modes can tell a
+/// short pause from a long one.
+fn idle(last_input: Instant, now: Instant) -> Ev {
+    Ev::Idle(now.saturating_duration_since(last_input))
+}
+
+/// Returns the number of lines of the text area, which leaves the last row of the terminal for the
+/// status line.
+fn height() -> Result<usize> {
+    let (_cols, rows) = terminal::size()?;
+    Ok(usize::from(rows.saturating_sub(1)))
+}
+
+/// Returns the width of the column of signs, which only shows up when there are signs.
+fn sign_width(state: &Editor) -> u16 {
+    if state.signs().is_empty() {
+        0
+    } else {
+        2
+    }
+}
+
+/// Returns the width of the column of line numbers, which only shows up with `relativenumber`.
+fn number_width(state: &Editor) -> u16 {
+    if state.options().relative_number {
+        let digits = state.line_count().to_string().len();
+        u16::try_from(digits.max(3) + 1).unwrap_or(u16::MAX)
+    } else {
+        0
+    }
+}
+
+/// Returns the width of the gutter left of the text, which holds signs and line numbers.
+fn gutter_width(state: &Editor) -> u16 {
+    sign_width(state) + number_width(state)
+}
+
+/// Returns the gutter of a line, holding its sign if it has one, followed by its distance from the
+/// line of the cursor with `relativenumber`.
+fn gutter(state: &Editor, row: usize) -> String {
+    let sign = match (sign_width(state), state.signs().get(&row)) {
+        (0, _) => String::new(),
+        (_, Some(Sign(sign))) => format!("{} ", sign),
+        _ => "  ".to_owned(),
+    };
+
+    let cursor = state.cursor().row();
+    let distance = row.max(cursor) - row.min(cursor);
+
+    match usize::from(number_width(state)) {
+        0 => sign,
+        width => format!("{}{:>2$} ", sign, distance, width - 1),
+    }
+}
+
+/// Returns the lines of the buffer shown on each line of the text area, where a closed fold takes a
+/// single line.
+fn screen_rows(state: &Editor, height: usize) -> Vec<usize> {
+    let rows = state.line_count();
+
+    std::iter::successors(Some(state.top()), |&row| {
+        Some(state.folds().closed(row).map_or(row, |(_, last)| last) + 1)
+    })
+    .take_while(|&row| row < rows)
+    .take(height)
+    .collect()
+}
+
+/// Returns the text shown for a line of the buffer, which summarizes the closed fold at it if there
+/// is one.
+fn text(state: &Editor, row: usize) -> String {
+    let line = state.line(row).map(ToString::to_string).unwrap_or_default();
+
+    match state.folds().closed(row) {
+        Some((first, last)) => format!("+--{:>3} lines: {}", last - first + 1, line.trim()),
+        None => line,
+    }
+}
+
+/// Returns the status line, which shows the input of a prompt, a message, or the mode name.
+///
+/// Unless a prompt is shown, the register being recorded into follows, as in `recording @a`.
+fn status_line(state: &Editor) -> String {
+    if let Some(input) = state.input() {
+        return format!("{}: {}", state.mode(), input);
+    }
+
+    let status = state.message().unwrap_or_else(|| state.mode());
+
+    match state.recording() {
+        Some(register) => format!("{} recording @{}", status, register),
+        None => status.to_owned(),
+    }
+}
+
+/// Returns the screen column and row of the cursor, given the lines of the buffer shown on each line
+/// of the text area.
+///
+/// Within a closed fold, the cursor sits at the start of the line that summarizes it.
+fn focus(state: &Editor, screen: &[usize]) -> (u16, u16) {
+    let cursor = state.cursor();
+    let folded = state.folds().closed(cursor.row());
+
+    let col = if folded.is_some() { 0 } else { state.buffer().display_col(cursor) };
+    let col = gutter_width(state).saturating_add(u16::try_from(col).unwrap_or(u16::MAX));
+
+    let row = folded.map_or(cursor.row(), |(first, _)| first);
+    let row = screen.iter().position(|&screen| screen == row).unwrap_or_default();
+
+    (col, u16::try_from(row).unwrap_or(u16::MAX))
+}
 
 fn draw(stdout: &mut impl Write, state: &Editor) -> Result<()> {
     let (_cols, rows) = terminal::size()?;
 
     queue!(stdout, terminal::Clear(terminal::ClearType::All), cursor::MoveTo(0, 0))?;
 
-    state.buffer().content().iter().try_for_each(|row| {
-        queue!(stdout, style::Print(row.to_string()), cursor::MoveToNextLine(1))
-    })?;
-
-    queue!(stdout, style::Print(format!("{:?}", state)))?;
+    let screen = screen_rows(state, height()?);
 
-    queue!(stdout, cursor::MoveTo(0, rows))?;
-    queue!(stdout, style::Print(state.mode()))?;
+    screen.iter().try_for_each(|&row| {
+        let (gutter, text) = (gutter(state, row), text(state, row));
+        queue!(stdout, style::Print(gutter), style::Print(text), cursor::MoveToNextLine(1))
+    })?;
 
-    let col = state.cursor().col() as u16;
-    let row = state.cursor().row() as u16;
+    queue!(stdout, cursor::MoveTo(0, rows), style::Print(status_line(state)))?;
 
+    let (col, row) = focus(state, &screen);
     queue!(stdout, cursor::MoveTo(col, row))?;
 
     stdout.flush()?;
@@ -34,60 +160,201 @@ fn draw(stdout: &mut impl Write, state: &Editor) -> Result<()> {
 }
 
 fn main() -> Result<()> {
-    let mut editor = Editor::new();
+    let mut editor = match std::env::args_os().nth(1) {
+        Some(path) => Editor::open(path)?,
+        None => Editor::new(),
+    };
+
     let mut stdout = io::stdout();
 
+    // Raw mode also keeps `Ctrl-c` from raising `SIGINT`, so it reaches the editor as a key press.
     terminal::enable_raw_mode()?;
-    execute!(stdout, terminal::EnterAlternateScreen)?;
+    execute!(stdout, terminal::EnterAlternateScreen, event::EnableMouseCapture)?;
 
     draw(&mut stdout, &editor)?;
 
+    let timeout = idle_timeout(std::env::var("SIX_IDLE_MS").ok().as_deref());
+    let mut last_input = Instant::now();
+
     loop {
+        if !event::poll(timeout)? {
+            editor.advance(&[idle(last_input, Instant::now())]);
+            draw(&mut stdout, &editor)?;
+            continue;
+        }
+
+        last_input = Instant::now();
+
         match event::read()? {
-            Event::Key(KeyEvent { code: KeyCode::Char('d'), modifiers: KeyModifiers::CONTROL }) => {
+            // `Ctrl-q` quits, since the editor has no use for it, unlike `Ctrl-d`.
+            Event::Key(KeyEvent { code: KeyCode::Char('q'), modifiers: KeyModifiers::CONTROL }) => {
                 break;
             },
 
-            Event::Key(KeyEvent { code, modifiers }) => {
-                let code = match code {
-                    KeyCode::Esc => Key::Esc,
+            Event::Key(key) => {
+                if let Ok(event) = Ev::try_from(key) {
+                    editor.advance(&[event]);
+                }
+            },
+
+            Event::Mouse(MouseEvent::Down(MouseButton::Left, col, row, _))
+            | Event::Mouse(MouseEvent::Drag(MouseButton::Left, col, row, _)) => {
+                let col = col.saturating_sub(gutter_width(&editor));
+                let screen = screen_rows(&editor, height()?);
 
-                    KeyCode::Char(ch) => Key::Char(ch),
+                if let Some(&row) = screen.get(usize::from(row)) {
+                    editor.click(row, col.into());
+                }
+            },
 
-                    KeyCode::Left => Key::Left,
-                    KeyCode::Up => Key::Up,
-                    KeyCode::Down => Key::Down,
-                    KeyCode::Right => Key::Right,
+            Event::Mouse(MouseEvent::ScrollUp(..)) => editor.scroll(-SCROLL_LINES, height()?),
+            Event::Mouse(MouseEvent::ScrollDown(..)) => editor.scroll(SCROLL_LINES, height()?),
 
-                    KeyCode::Backspace => Key::Backspace,
-                    KeyCode::Delete => Key::Delete,
+            _ => continue,
+        }
 
-                    KeyCode::Home => Key::Home,
-                    KeyCode::End => Key::End,
+        editor.follow_cursor(height()?);
+        draw(&mut stdout, &editor)?;
+    }
 
-                    KeyCode::Enter => Key::Char('\n'),
+    terminal::disable_raw_mode()?;
+    execute!(stdout, event::DisableMouseCapture, terminal::LeaveAlternateScreen)
+}
 
-                    _ => Key::Char('\0'),
-                };
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
 
-                let modifiers = match modifiers {
-                    KeyModifiers::NONE => Modifiers::NONE,
-                    KeyModifiers::CONTROL => Modifiers::CTRL,
+    use six::{Editor, Event, Sign};
 
-                    KeyModifiers::SHIFT => Modifiers::NONE,
+    use super::{
+        focus, gutter, gutter_width, idle, idle_timeout, screen_rows, status_line, text,
+        IDLE_TIMEOUT,
+    };
 
-                    _ => todo!(),
-                };
+    /// Returns the lines of a text area of `height` lines, as drawn.
+    fn render(editor: &Editor, height: usize) -> Vec<String> {
+        let screen = screen_rows(editor, height);
+        screen.iter().map(|&row| format!("{}{}", gutter(editor, row), text(editor, row))).collect()
+    }
 
-                editor.advance(&[Ev::Key(code, modifiers)])
-            },
+    #[test]
+    fn test_gutter() {
+        let mut editor = Editor::with_text("foo\nbar");
 
-            _ => continue,
-        }
+        assert_eq!(gutter_width(&editor), 0);
+        assert_eq!(gutter(&editor, 0), "");
 
-        draw(&mut stdout, &editor)?;
+        editor.set_sign(1, Sign('>'));
+
+        assert_eq!(gutter_width(&editor), 2);
+        assert_eq!(gutter(&editor, 0), "  ");
+        assert_eq!(gutter(&editor, 1), "> ");
+
+        editor.feed(":set rnu<CR>j");
+
+        assert_eq!(gutter_width(&editor), 6);
+        assert_eq!(gutter(&editor, 0), "    1 ");
+        assert_eq!(gutter(&editor, 1), ">   0 ");
     }
 
-    terminal::disable_raw_mode()?;
-    execute!(stdout, terminal::LeaveAlternateScreen)
+    #[test]
+    fn test_fold() {
+        let mut editor = Editor::with_text("a\n  b\nc\nd\ne");
+        editor.feed("jzfj");
+
+        assert_eq!(screen_rows(&editor, 9), vec![0, 1, 3, 4]);
+        assert_eq!(screen_rows(&editor, 2), vec![0, 1]);
+
+        assert_eq!(text(&editor, 0), "a");
+        assert_eq!(text(&editor, 1), "+--  2 lines: b");
+        assert_eq!(text(&editor, 2), "+--  2 lines: c");
+
+        editor.feed("zo");
+        assert_eq!(screen_rows(&editor, 9), vec![0, 1, 2, 3, 4]);
+        assert_eq!(text(&editor, 1), "  b");
+    }
+
+    #[test]
+    fn test_render() {
+        let mut editor = Editor::with_text("foo\nbar baz\nqux");
+
+        editor.set_sign(1, Sign('>'));
+        editor.feed("jw");
+
+        assert_eq!(render(&editor, 9), ["  foo", "> bar baz", "  qux"]);
+        assert_eq!(focus(&editor, &screen_rows(&editor, 9)), (6, 1));
+        assert_eq!(status_line(&editor), "Normal");
+
+        editor.feed(":se");
+        assert_eq!(status_line(&editor), "Command: se");
+
+        editor.feed("<Esc>:nope<CR>");
+        assert_eq!(status_line(&editor), "Not an editor command: nope");
+    }
+
+    #[test]
+    fn test_render_recording() {
+        let mut editor = Editor::with_text("foo bar");
+
+        editor.feed("qa");
+        assert_eq!(status_line(&editor), "Normal recording @a");
+
+        editor.feed("i");
+        assert_eq!(status_line(&editor), "Insert recording @a");
+
+        editor.feed("<Esc>:se");
+        assert_eq!(status_line(&editor), "Command: se");
+
+        editor.feed("<Esc>q");
+        assert_eq!(status_line(&editor), "Normal");
+    }
+
+    #[test]
+    fn test_render_scrolled() {
+        let text = (0..20).map(|row| format!("line {}", row)).collect::<Vec<_>>().join("\n");
+        let mut editor = Editor::with_text(&text);
+
+        editor.feed("14jw");
+        editor.follow_cursor(5);
+
+        assert_eq!(render(&editor, 5), ["line 10", "line 11", "line 12", "line 13", "line 14"]);
+        assert_eq!(focus(&editor, &screen_rows(&editor, 5)), (5, 4));
+
+        editor.feed("5k");
+        editor.follow_cursor(5);
+
+        assert_eq!(render(&editor, 5)[0], "line 9");
+        assert_eq!(focus(&editor, &screen_rows(&editor, 5)), (5, 0));
+    }
+
+    #[test]
+    fn test_render_tabs() {
+        let mut editor = Editor::with_text("\tfoo\tbar");
+
+        editor.feed("ww");
+        assert_eq!(focus(&editor, &screen_rows(&editor, 9)), (16, 0));
+    }
+
+    #[test]
+    fn test_render_fold() {
+        let mut editor = Editor::with_text("a\n  b\nc\nd");
+        editor.feed("jzfjj");
+
+        assert_eq!(render(&editor, 9), ["a", "+--  2 lines: b", "d"]);
+        assert_eq!(focus(&editor, &screen_rows(&editor, 9)), (0, 2));
+    }
+
+    #[test]
+    fn test_idle() {
+        assert_eq!(idle_timeout(None), IDLE_TIMEOUT);
+        assert_eq!(idle_timeout(Some("40")), Duration::from_millis(40));
+        assert_eq!(idle_timeout(Some("soon")), IDLE_TIMEOUT);
+
+        let last_input = Instant::now();
+        let later = last_input + Duration::from_millis(600);
+
+        assert_eq!(idle(last_input, later), Event::Idle(Duration::from_millis(600)));
+        assert_eq!(idle(later, last_input), Event::Idle(Duration::from_secs(0)));
+    }
 }
@@ -1,31 +1,56 @@
-use std::ops::RangeBounds;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::{Bound, Range, RangeBounds};
+use std::path::{Path, PathBuf};
 
-use crate::cursor::{Cells, Paragraphs};
+use itertools::Itertools;
+use unicode_width::UnicodeWidthChar;
+
+use crate::cursor::{Cells, CharIndices, Metric, Paragraphs};
+use crate::undo::{Change, Undo};
 use crate::Cursor;
 
 pub type Content = Vec<Row>;
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Hash)]
 pub struct Row(Vec<char>);
 
 impl Row {
     /// Returns the number of characters in the row.
+    #[must_use]
     pub fn len(&self) -> usize {
         self.0.len()
     }
 
+    /// Returns whether the row has no characters.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
     /// Returns the character at the specified position.
+    #[must_use]
     pub fn get(&self, at: usize) -> Option<char> {
         self.0.get(at).copied()
     }
 
-    /// Fills the row with the specified character until it meets the given length.
-    pub fn pad(&mut self, chars: usize, padding: char) {
-        self.0.resize(self.0.len().max(chars), padding)
+    /// Returns an iterator over the characters of the row.
+    #[must_use]
+    pub fn chars(&self) -> impl DoubleEndedIterator<Item = char> + '_ {
+        self.0.iter().copied()
     }
 
-    pub fn to_string(&self) -> String {
-        self.0.iter().collect::<String>()
+    /// Returns the position of the first non-whitespace character of the row, or its length if
+    /// there is none.
+    #[must_use]
+    pub fn first_non_blank(&self) -> usize {
+        self.0.iter().position(|ch| !ch.is_whitespace()).unwrap_or_else(|| self.len())
+    }
+
+    /// Fills the row with the specified character until it meets the given length.
+    pub fn pad(&mut self, chars: usize, padding: char) {
+        self.0.resize(self.0.len().max(chars), padding);
     }
 
     /// Inserts a character at the specified position.
@@ -37,18 +62,245 @@ impl Row {
             self.pad(position, ' ');
         }
 
-        self.0.insert(position, ch)
+        self.0.insert(position, ch);
+    }
+}
+
+impl From<&str> for Row {
+    fn from(text: &str) -> Self {
+        Self(text.chars().collect())
+    }
+}
+
+impl fmt::Display for Row {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|&ch| fmt::Write::write_char(f, ch))
     }
 }
 
+/// The sequence of characters that ends lines in a file.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LineEnding {
+    /// A line feed, as in Unix.
+    Unix,
+
+    /// A carriage return followed by a line feed, as in DOS.
+    Dos,
+}
+
+impl LineEnding {
+    /// Returns the characters that end a line.
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Unix => "\n",
+            Self::Dos => "\r\n",
+        }
+    }
+
+    /// Returns the dominant line ending of a text, if it has any line breaks.
+    #[must_use]
+    pub fn detect(text: &str) -> Option<Self> {
+        let lf = text.matches('\n').count();
+        let crlf = text.matches("\r\n").count();
+
+        match lf {
+            0 => None,
+            _ if crlf * 2 > lf => Some(Self::Dos),
+            _ => Some(Self::Unix),
+        }
+    }
+}
+
+impl Default for LineEnding {
+    /// Returns the native line ending of the platform.
+    fn default() -> Self {
+        if cfg!(windows) {
+            Self::Dos
+        } else {
+            Self::Unix
+        }
+    }
+}
+
+/// A change made to the text of a buffer, as the replacement of the text between two positions.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Edit {
+    /// The start of the replaced text.
+    pub start: Cursor,
+
+    /// The end of the replaced text, excluded.
+    pub end: Cursor,
+
+    /// The end of the text that replaced it, excluded.
+    pub new_end: Cursor,
+}
+
+impl Edit {
+    /// Returns where a position stored before the edit ends up after it, as for marks and folds.
+    ///
+    /// Positions before the replaced text stay in place, and the ones after it move along with the
+    /// text that follows. Positions within the replaced text collapse to its start.
+    #[must_use]
+    pub fn adjust_cursor(&self, cursor: Cursor) -> Cursor {
+        if cursor < self.start {
+            cursor
+        } else if cursor < self.end {
+            self.start
+        } else if cursor.row() == self.end.row() {
+            let col = cursor.col() - self.end.col() + self.new_end.col();
+            Cursor::new(self.new_end.row(), col)
+        } else {
+            Cursor::new(cursor.row() - self.end.row() + self.new_end.row(), cursor.col())
+        }
+    }
+
+    /// Returns where a range stored before the edit ends up after it, adjusting both of its ends.
+    #[must_use]
+    pub fn adjust_range(&self, start: Cursor, end: Cursor) -> (Cursor, Cursor) {
+        (self.adjust_cursor(start), self.adjust_cursor(end))
+    }
+
+    /// Returns whether the edit removes a whole line along with a line break, given the start and
+    /// the end of the line before the edit.
+    #[must_use]
+    pub fn removes_line(&self, start: Cursor, end: Cursor) -> bool {
+        self.start.row() != self.end.row() && self.start <= start && end <= self.end
+    }
+}
+
+/// Returns whether a character is part of a keyword by default, which is the case for
+/// alphanumerics and `_`.
+fn is_keyword(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
 /// The mutable buffer of an editor.
-#[derive(Debug, Default)]
+///
+/// A buffer always holds at least one, possibly empty, row.
+#[derive(Derivative)]
+#[derivative(Debug)]
+#[allow(clippy::struct_excessive_bools)] // Each flag is an independent option.
 pub struct Buffer {
     /// The text content.
     content: Content,
 
     /// The cursor position.
     cursor: Cursor,
+
+    /// Classifies characters as part of keywords or not, as in Vim's `iskeyword`.
+    #[derivative(Debug = "ignore")]
+    keyword: fn(char) -> bool,
+
+    /// The line ending used when writing the buffer out.
+    ending: LineEnding,
+
+    /// Whether the buffer is written out ending with exactly one line ending, as in Vim's
+    /// `fixendofline`.
+    fix_end_of_line: bool,
+
+    /// Whether Tab inserts spaces instead of a tab character, as in Vim's `expandtab`.
+    expand_tab: bool,
+
+    /// The number of screen columns a tab character spans, as in Vim's `tabstop`.
+    tab_stop: usize,
+
+    /// The number of screen columns of each level of indentation, as in Vim's `shiftwidth`.
+    shift_width: usize,
+
+    /// The number of screen columns Tab and Backspace span while inserting, as in Vim's
+    /// `softtabstop`. Zero turns it off.
+    soft_tab_stop: usize,
+
+    /// The maximum number of screen columns of lines formatted with `gq`, as in Vim's `textwidth`.
+    text_width: usize,
+
+    /// The text that starts line comments toggled with `gc`, which is followed by a space.
+    comment: String,
+
+    /// Whether typing an opening bracket or quote in insert mode also inserts the closing one.
+    auto_pairs: bool,
+
+    /// Whether the text can be changed, as in Vim's `modifiable`.
+    modifiable: bool,
+
+    /// The content hash of the associated file when it was last read or written, if known.
+    file_hash: Option<u64>,
+
+    /// The file associated with the buffer, if any.
+    path: Option<PathBuf>,
+
+    /// Whether changes made to the text are recorded, to be taken later.
+    record_edits: bool,
+
+    /// The changes made to the text since they were last taken, if they are recorded.
+    edits: Vec<Edit>,
+
+    /// The states the text went through, for undo and redo.
+    undo: Undo,
+
+    /// The changes made to the text since the current undo state was saved.
+    changes: Vec<Change>,
+
+    /// The number of undo groups open, during which changes are not saved as undo states.
+    undo_groups: usize,
+
+    /// Whether a change was refused since it was last checked, as the buffer is not modifiable.
+    refused: bool,
+}
+
+impl Default for Buffer {
+    fn default() -> Self {
+        Self::from("")
+    }
+}
+
+impl From<&str> for Buffer {
+    /// Creates a buffer holding the specified text, with the cursor at the origin.
+    ///
+    /// The line ending of the buffer is the dominant one in the text, and is stripped from every
+    /// line.
+    fn from(text: &str) -> Self {
+        let ending = LineEnding::detect(text).unwrap_or_default();
+
+        let content = text
+            .split('\n')
+            .map(|line| match ending {
+                LineEnding::Dos => Row::from(line.strip_suffix('\r').unwrap_or(line)),
+                LineEnding::Unix => Row::from(line),
+            })
+            .collect::<Content>();
+
+        Self {
+            content,
+            ending,
+            cursor: Cursor::origin(),
+            keyword: is_keyword,
+            fix_end_of_line: true,
+            expand_tab: false,
+            tab_stop: 8,
+            shift_width: 8,
+            soft_tab_stop: 0,
+            text_width: 79,
+            comment: "//".to_owned(),
+            auto_pairs: false,
+            modifiable: true,
+            file_hash: None,
+            path: None,
+            record_edits: false,
+            edits: Vec::new(),
+            undo: Undo::default(),
+            changes: Vec::new(),
+            undo_groups: 0,
+            refused: false,
+        }
+    }
+}
+
+impl fmt::Display for Buffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.content.iter().format("\n"))
+    }
 }
 
 impl Buffer {
@@ -64,68 +316,1121 @@ impl Buffer {
         std::mem::replace(&mut self.cursor, cursor)
     }
 
-    /// Returns a reference to the buffer's content.
-    pub fn content(&self) -> &Content {
-        &self.content
+    /// Returns whether a character is part of a keyword, for the purposes of word motions.
+    #[must_use]
+    pub fn is_keyword(&self, ch: char) -> bool {
+        (self.keyword)(ch)
     }
 
-    /// Converts the buffer contents to a string.
-    #[inline]
+    /// Returns the function that decides which characters are part of keywords.
+    #[must_use]
+    pub fn keyword(&self) -> fn(char) -> bool {
+        self.keyword
+    }
+
+    /// Sets the function that decides which characters are part of keywords.
+    pub fn set_keyword(&mut self, keyword: fn(char) -> bool) {
+        self.keyword = keyword;
+    }
+
+    /// Returns the line ending used when writing the buffer out.
+    #[must_use]
+    pub fn line_ending(&self) -> LineEnding {
+        self.ending
+    }
+
+    /// Sets the line ending used when writing the buffer out.
+    pub fn set_line_ending(&mut self, ending: LineEnding) {
+        self.ending = ending;
+    }
+
+    /// Returns whether the buffer is written out ending with exactly one line ending.
+    #[must_use]
+    pub fn fix_end_of_line(&self) -> bool {
+        self.fix_end_of_line
+    }
+
+    /// Sets whether the buffer is written out ending with exactly one line ending.
+    pub fn set_fix_end_of_line(&mut self, fix: bool) {
+        self.fix_end_of_line = fix;
+    }
+
+    /// Returns whether Tab inserts spaces instead of a tab character.
+    #[must_use]
+    pub fn expand_tab(&self) -> bool {
+        self.expand_tab
+    }
+
+    /// Sets whether Tab inserts spaces instead of a tab character.
+    pub fn set_expand_tab(&mut self, expand: bool) {
+        self.expand_tab = expand;
+    }
+
+    /// Returns the number of screen columns a tab character spans.
+    #[must_use]
+    pub fn tab_stop(&self) -> usize {
+        self.tab_stop
+    }
+
+    /// Sets the number of screen columns a tab character spans.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` is zero.
+    pub fn set_tab_stop(&mut self, width: usize) {
+        assert!(width > 0, "the tab stop must be positive");
+        self.tab_stop = width;
+    }
+
+    /// Returns the number of screen columns of each level of indentation.
+    #[must_use]
+    pub fn shift_width(&self) -> usize {
+        self.shift_width
+    }
+
+    /// Sets the number of screen columns of each level of indentation.
+    pub fn set_shift_width(&mut self, width: usize) {
+        self.shift_width = width;
+    }
+
+    /// Returns the number of screen columns Tab and Backspace span while inserting, or zero if
+    /// they work on single characters.
+    #[must_use]
+    pub fn soft_tab_stop(&self) -> usize {
+        self.soft_tab_stop
+    }
+
+    /// Sets the number of screen columns Tab and Backspace span while inserting.
+    pub fn set_soft_tab_stop(&mut self, width: usize) {
+        self.soft_tab_stop = width;
+    }
+
+    /// Returns the maximum number of screen columns of lines formatted with `gq`.
+    #[must_use]
+    pub fn text_width(&self) -> usize {
+        self.text_width
+    }
+
+    /// Sets the maximum number of screen columns of lines formatted with `gq`.
+    pub fn set_text_width(&mut self, width: usize) {
+        self.text_width = width;
+    }
+
+    /// Returns the text that starts line comments toggled with `gc`.
+    #[must_use]
+    pub fn comment(&self) -> &str {
+        &self.comment
+    }
+
+    /// Sets the text that starts line comments toggled with `gc`, as in `#`.
+    pub fn set_comment(&mut self, comment: impl Into<String>) {
+        self.comment = comment.into();
+    }
+
+    /// Returns whether typing an opening bracket or quote in insert mode also inserts the closing
+    /// one.
     #[must_use]
-    pub fn to_string(&self) -> String {
-        unimplemented!()
+    pub fn auto_pairs(&self) -> bool {
+        self.auto_pairs
+    }
+
+    /// Sets whether typing an opening bracket or quote in insert mode also inserts the closing one.
+    pub fn set_auto_pairs(&mut self, auto_pairs: bool) {
+        self.auto_pairs = auto_pairs;
+    }
+
+    /// Returns whether the text can be changed.
+    #[must_use]
+    pub fn modifiable(&self) -> bool {
+        self.modifiable
+    }
+
+    /// Sets whether the text can be changed. Edits to a buffer that is not modifiable are ignored.
+    pub fn set_modifiable(&mut self, modifiable: bool) {
+        self.modifiable = modifiable;
+    }
+
+    /// Returns whether a change was refused since the last call, as the buffer is not modifiable,
+    /// so that it can be reported.
+    pub fn take_refused(&mut self) -> bool {
+        std::mem::take(&mut self.refused)
+    }
+
+    /// Returns whether changes made to the text are recorded, to be taken with
+    /// [`Buffer::take_edits`].
+    #[must_use]
+    pub fn record_edits(&self) -> bool {
+        self.record_edits
+    }
+
+    /// Sets whether changes made to the text are recorded. Changes recorded so far are dropped
+    /// when recording stops.
+    ///
+    /// Buffers don't record their changes unless told to, as the changes pile up until taken.
+    pub fn set_record_edits(&mut self, record: bool) {
+        self.record_edits = record;
+
+        if !record {
+            self.edits.clear();
+        }
+    }
+
+    /// Records a change made to the text, if changes are recorded.
+    fn push_edit(&mut self, edit: Edit) {
+        if self.record_edits {
+            self.edits.push(edit);
+        }
+    }
+
+    /// Returns whether changes are refused, as the buffer is not modifiable, remembering it if so.
+    fn refuses_changes(&mut self) -> bool {
+        self.refused |= !self.modifiable;
+        !self.modifiable
+    }
+
+    /// Returns the screen column of a position, with tab characters reaching up to the next
+    /// multiple of the tab stop.
+    #[must_use]
+    pub fn display_col(&self, cursor: Cursor) -> usize {
+        let row = match self.content.get(cursor.row()) {
+            Some(row) => row,
+            None => return 0,
+        };
+
+        row.0.iter().take(cursor.col()).fold(0, |col, &ch| self.next_display_col(col, ch))
+    }
+
+    /// Returns the screen column right after a character displayed at the specified one.
+    fn next_display_col(&self, col: usize, ch: char) -> usize {
+        match ch {
+            '\t' => col + self.tab_stop - col % self.tab_stop,
+            ch => col + ch.width().unwrap_or(0),
+        }
+    }
+
+    /// Returns the position of the character displayed at a screen column of a row, as when
+    /// clicking on it.
+    ///
+    /// Rows past the end of the buffer are moved to the last row, and columns past the end of a line
+    /// to its last character.
+    #[must_use]
+    pub fn cursor_at(&self, row: usize, col: usize) -> Cursor {
+        let row = row.min(self.content.len() - 1);
+
+        let mut end = 0;
+        let chars = self.content[row].0.iter().take_while(|&&ch| {
+            end = self.next_display_col(end, ch);
+            end <= col
+        });
+
+        Cursor::new(row, chars.count()).clamp_to_line(self)
+    }
+
+    /// Returns the offset of a position from the start of the text, in bytes of UTF-8, as in tools
+    /// such as ctags, or `None` if the position is outside of the text.
+    ///
+    /// Offsets are into the text as written out, where each line break takes the bytes of the
+    /// buffer's line ending.
+    #[must_use]
+    pub fn cursor_to_byte(&self, cursor: Cursor) -> Option<usize> {
+        self.offset_of(cursor, char::len_utf8)
+    }
+
+    /// Returns the position at an offset in bytes of UTF-8 from the start of the text, or `None`
+    /// if the offset is past the end of the text or within a character or line ending.
+    #[must_use]
+    pub fn byte_to_cursor(&self, offset: usize) -> Option<Cursor> {
+        self.position_of(offset, char::len_utf8)
+    }
+
+    /// Returns the offset of a position from the start of the text, in code units of UTF-16, or
+    /// `None` if the position is outside of the text.
+    #[must_use]
+    pub fn cursor_to_utf16(&self, cursor: Cursor) -> Option<usize> {
+        self.offset_of(cursor, char::len_utf16)
+    }
+
+    /// Returns the position at an offset in code units of UTF-16 from the start of the text, or
+    /// `None` if the offset is past the end of the text or within a character.
+    #[must_use]
+    pub fn utf16_to_cursor(&self, offset: usize) -> Option<Cursor> {
+        self.position_of(offset, char::len_utf16)
+    }
+
+    /// Returns a position as a line and a column in code units of UTF-16, as in the positions of the
+    /// Language Server Protocol, or `None` if the position is outside of the text.
+    #[must_use]
+    pub fn cursor_to_utf16_position(&self, cursor: Cursor) -> Option<(usize, usize)> {
+        let line = self.content.get(cursor.row())?;
+        let head = line.0.get(..cursor.col())?;
+
+        Some((cursor.row(), head.iter().copied().map(char::len_utf16).sum()))
+    }
+
+    /// Returns the position at a line and a column in code units of UTF-16, as in the positions of
+    /// the Language Server Protocol.
+    ///
+    /// Columns past the end of the line stand for its end, as the protocol requires. Returns `None`
+    /// if the line doesn't exist or the column falls within a character.
+    #[must_use]
+    pub fn utf16_position_to_cursor(&self, row: usize, col: usize) -> Option<Cursor> {
+        let line = self.content.get(row)?;
+
+        let mut units = 0;
+        for (idx, ch) in line.chars().enumerate() {
+            if units >= col {
+                return Some(Cursor::new(row, idx)).filter(|_| units == col);
+            }
+
+            units += ch.len_utf16();
+        }
+
+        Some(Cursor::new(row, line.len())).filter(|_| units <= col)
+    }
+
+    /// Returns the offset of a position, where each character takes the units given by `len` and
+    /// each line break takes one for each character of the line ending.
+    fn offset_of(&self, cursor: Cursor, len: fn(char) -> usize) -> Option<usize> {
+        let line = self.content.get(cursor.row())?;
+        let head = line.0.get(..cursor.col())?;
+
+        let ending = self.ending.as_str().len();
+        let units = |row: &Row| row.chars().map(len).sum::<usize>() + ending;
+        let rows = self.content[..cursor.row()].iter().map(units).sum::<usize>();
+
+        Some(rows + head.iter().copied().map(len).sum::<usize>())
+    }
+
+    /// Returns the position at an offset, where each character takes the units given by `len` and
+    /// each line break takes one for each character of the line ending.
+    fn position_of(&self, mut offset: usize, len: fn(char) -> usize) -> Option<Cursor> {
+        let ending = self.ending.as_str().len();
+
+        for (row, line) in self.content.iter().enumerate() {
+            for (col, ch) in line.chars().enumerate() {
+                if offset == 0 {
+                    return Some(Cursor::new(row, col));
+                }
+
+                offset = offset.checked_sub(len(ch))?;
+            }
+
+            if offset == 0 {
+                return Some(Cursor::new(row, line.len()));
+            }
+
+            offset = offset.checked_sub(ending)?;
+        }
+
+        None
+    }
+
+    /// Returns the file associated with the buffer, if any.
+    #[must_use]
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Associates the buffer with a file.
+    pub fn set_path(&mut self, path: impl Into<PathBuf>) {
+        self.path = Some(path.into());
+    }
+
+    /// Returns the content hash of the associated file when it was last read or written, if known.
+    #[must_use]
+    pub fn file_hash(&self) -> Option<u64> {
+        self.file_hash
+    }
+
+    /// Sets the content hash of the associated file, as read or written.
+    pub fn set_file_hash(&mut self, hash: u64) {
+        self.file_hash = Some(hash);
+    }
+
+    /// Returns whether the text differs from the associated file when it was last read or written,
+    /// or from an empty buffer if there is no such file.
+    #[must_use]
+    pub fn is_modified(&self) -> bool {
+        let saved = self.file_hash.unwrap_or_else(|| Self::default().content_hash());
+        saved != self.content_hash()
+    }
+
+    /// Returns a hash of the text, which changes whenever the text does.
+    ///
+    /// The hash is fast to compute but not cryptographic, and it is only meant to be compared
+    /// with others from the same build. Line endings are not part of it.
+    #[must_use]
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.content.hash(&mut hasher);
+
+        hasher.finish()
+    }
+
+    /// Converts the buffer contents to a string, ending lines with the buffer's line ending.
+    ///
+    /// If the buffer fixes its end of line, trailing empty lines are dropped and the text always
+    /// ends with a single line ending, unless it is empty.
+    #[must_use]
+    pub fn to_string_with_endings(&self) -> String {
+        let ending = self.ending.as_str();
+
+        if self.fix_end_of_line {
+            let len = self.content.iter().rposition(|row| !row.is_empty()).map_or(0, |row| row + 1);
+
+            self.content[..len].iter().map(|row| format!("{}{}", row, ending)).collect()
+        } else {
+            self.content.iter().join(ending)
+        }
+    }
+
+    /// Returns a reference to the buffer's content.
+    #[must_use]
+    pub fn content(&self) -> &Content {
+        &self.content
     }
 
     /// Returns the character at the specified position.
+    #[must_use]
     pub fn get(&self, at: Cursor) -> Option<char> {
         self.line(at.row()).and_then(|row| row.get(at.col()))
     }
 
     /// Returns a reference to the specified line, if it exists.
+    #[must_use]
     pub fn line(&self, idx: usize) -> Option<&Row> {
         self.content.get(idx)
     }
 
+    /// Returns whether a line is empty or only holds whitespace, where missing lines are not blank.
+    #[must_use]
+    pub fn is_blank_line(&self, row: usize) -> bool {
+        self.line(row).map_or(false, |line| line.chars().all(char::is_whitespace))
+    }
+
+    /// Returns the position just past the last character of the buffer.
+    #[must_use]
+    pub fn eof(&self) -> Cursor {
+        let row = self.content.len() - 1;
+        Cursor::new(row, self.content[row].len())
+    }
+
+    /// Moves a position to the nearest one that exists in the buffer.
+    ///
+    /// Positions past the end of a line are moved to the end of that line.
+    #[must_use]
+    pub fn clamp(&self, at: Cursor) -> Cursor {
+        let row = at.row().min(self.content.len() - 1);
+        Cursor::new(row, at.col().min(self.content[row].len()))
+    }
+
     /// Inserts a character at the specified cursor position.
+    ///
+    /// A line break splits the row in two. Positions past the end of a line are padded with
+    /// spaces beforehand. Does nothing if the buffer is not modifiable.
     pub fn insert(&mut self, ch: char, at: Cursor) {
-        self.content[at.row()].insert(at.col(), ch);
+        if self.refuses_changes() {
+            return;
+        }
+
+        let new_end = if ch == '\n' {
+            Cursor::new(at.row() + 1, 0)
+        } else {
+            Cursor::new(at.row(), at.col() + 1)
+        };
+
+        self.push_edit(Edit { start: at, end: at, new_end });
+
+        // Positions past the end of the line are padded with spaces, which go along with the
+        // character.
+        let len = self.content[at.row()].len();
+        let mut inserted = " ".repeat(at.col().saturating_sub(len));
+        inserted.push(ch);
+
+        let start = Cursor::new(at.row(), at.col().min(len));
+        self.changes.push(Change { start, removed: String::new(), inserted });
+
+        let row = &mut self.content[at.row()];
+
+        if ch == '\n' {
+            row.pad(at.col(), ' ');
+
+            let tail = row.0.split_off(at.col());
+            self.content.insert(at.row() + 1, Row(tail));
+        } else {
+            row.insert(at.col(), ch);
+        }
+    }
+
+    /// Inserts a character at the cursor, moving the cursor past it.
+    ///
+    /// Does nothing if the buffer is not modifiable.
+    pub fn append(&mut self, ch: char) {
+        if self.refuses_changes() {
+            return;
+        }
+
+        let cursor = self.cursor;
+        self.insert(ch, cursor);
+
+        self.cursor = if ch == '\n' {
+            Cursor::new(cursor.row() + 1, 0)
+        } else {
+            Cursor::new(cursor.row(), cursor.col() + 1)
+        };
+    }
+
+    /// Shifts the indentation of a row by one `shiftwidth` to the right or to the left, as in `>>`
+    /// and `<<`, keeping the cursor on the same character.
+    ///
+    /// The new indentation is made of tabs and spaces, or only spaces with `expandtab`. Does nothing
+    /// if the buffer is not modifiable.
+    pub fn shift(&mut self, row: usize, right: bool) {
+        if self.refuses_changes() {
+            return;
+        }
+
+        let (tab_stop, expand_tab) = (self.tab_stop, self.expand_tab);
+        let shift_width = if self.shift_width == 0 { tab_stop } else { self.shift_width };
+
+        let line = match self.content.get_mut(row) {
+            Some(line) => &mut line.0,
+            None => return,
+        };
+
+        let old = line.iter().take_while(|&&ch| ch == ' ' || ch == '\t').count();
+        let width = line[..old].iter().fold(0, |col, &ch| match ch {
+            '\t' => col + tab_stop - col % tab_stop,
+            _ => col + 1,
+        });
+
+        let width = if right { width + shift_width } else { width.saturating_sub(shift_width) };
+
+        let indent = if expand_tab {
+            " ".repeat(width)
+        } else {
+            "\t".repeat(width / tab_stop) + &" ".repeat(width % tab_stop)
+        };
+
+        let new = indent.chars().count();
+        let removed = line.splice(..old, indent.chars()).collect();
+
+        self.push_edit(Edit {
+            start: Cursor::new(row, 0),
+            end: Cursor::new(row, old),
+            new_end: Cursor::new(row, new),
+        });
+        self.changes.push(Change { start: Cursor::new(row, 0), removed, inserted: indent });
+
+        if self.cursor.row() == row {
+            let col = self.cursor.col();
+            let col = if col >= old { col - old + new } else { col.min(new) };
+
+            self.cursor = Cursor::new(row, col);
+        }
+    }
+
+    /// Returns the text in a range.
+    #[must_use]
+    pub fn slice(&self, range: impl RangeBounds<Cursor>) -> String {
+        let (start, end) = self.resolve(range);
+
+        (start.row()..=end.row())
+            .map(|row| {
+                let line = &self.content[row].0;
+
+                let from = if row == start.row() { start.col() } else { 0 };
+                let to = if row == end.row() { end.col() } else { line.len() };
+
+                line[from..to].iter().collect::<String>()
+            })
+            .join("\n")
     }
 
     /// Replaces the text in a range.
     ///
-    /// The length of the range can differ from the replacement's.
-    pub fn edit(&mut self, text: &str, range: impl RangeBounds<Cursor>) {
-        unimplemented!()
+    /// The length of the range can differ from the replacement's. Returns the position just past
+    /// the inserted text.
+    ///
+    /// If the buffer is not modifiable, leaves the text as is and returns the start of the range.
+    pub fn edit(&mut self, text: &str, range: impl RangeBounds<Cursor>) -> Cursor {
+        let (start, end) = self.resolve(range);
+
+        if self.refuses_changes() {
+            return start;
+        }
+
+        let removed = self.slice(start..end);
+        let mut tail = self.content[end.row()].0.split_off(end.col());
+        self.content[start.row()].0.truncate(start.col());
+
+        let mut rows = text.split('\n').map(Row::from).collect::<Vec<_>>();
+
+        let head = std::mem::take(&mut self.content[start.row()]);
+        rows[0].0.splice(0..0, head.0);
+
+        let last = rows.len() - 1;
+        let position = Cursor::new(start.row() + last, rows[last].len());
+        rows[last].0.append(&mut tail);
+
+        self.content.splice(start.row()..=end.row(), rows);
+        self.push_edit(Edit { start, end, new_end: position });
+        self.changes.push(Change { start, removed, inserted: text.to_owned() });
+
+        position
+    }
+
+    /// Joins the rows in a range into the first of them, as in `J`, and returns the position where
+    /// the last two were joined, or `None` if there was nothing to join.
+    ///
+    /// With an empty separator, the rows are joined as they are, as in `gJ`. Otherwise, the
+    /// separator replaces the indentation of each joined row, but is left out after an empty row or
+    /// blanks, and before an empty row or a `)`.
+    pub fn join_lines(&mut self, rows: Range<usize>, separator: &str) -> Option<Cursor> {
+        let row = rows.start;
+        let mut joined = None;
+
+        for _ in row + 1..rows.end.min(self.content.len()) {
+            let (line, next) = match (self.line(row), self.line(row + 1)) {
+                (Some(line), Some(next)) => (line, next),
+                _ => break,
+            };
+
+            let start = Cursor::new(row, line.len());
+
+            let (indent, separator) = if separator.is_empty() {
+                (0, "")
+            } else {
+                let indent = next.chars().take_while(|ch| ch.is_whitespace()).count();
+
+                let after_blank = line.chars().last().map_or(true, char::is_whitespace);
+                let before_blank = next.get(indent).map_or(true, |ch| ch == ')');
+
+                (indent, if after_blank || before_blank { "" } else { separator })
+            };
+
+            self.edit(separator, start..Cursor::new(row + 1, indent));
+            joined = Some(start);
+        }
+
+        joined
+    }
+
+    /// Saves the text as a new undo state if it changed since the current one was saved, which the
+    /// editor does once each command is complete.
+    ///
+    /// Does nothing while an undo group is open.
+    pub fn save_undo_state(&mut self) {
+        if self.undo_groups == 0 {
+            self.save_changes();
+        }
+    }
+
+    /// Returns whether the text changed since the current undo state was saved, so that a new one
+    /// is pending.
+    #[must_use]
+    pub fn undo_pending(&self) -> bool {
+        !self.changes.is_empty()
+    }
+
+    /// Opens an undo group, so that the changes made until it is closed are undone at once.
+    ///
+    /// Groups opened within another one are joined with it.
+    pub fn begin_undo_group(&mut self) {
+        self.undo_groups += 1;
+    }
+
+    /// Closes an undo group, saving the changes made since the outermost one was opened as a
+    /// single undo state once it is closed.
+    pub fn end_undo_group(&mut self) {
+        self.undo_groups = self.undo_groups.saturating_sub(1);
+        self.save_undo_state();
+    }
+
+    /// Saves the text as a new undo state if it changed since the current one was saved.
+    fn save_changes(&mut self) {
+        if !self.changes.is_empty() {
+            self.undo.save(std::mem::take(&mut self.changes));
+        }
+    }
+
+    /// Undoes the changes of the current undo state, as in `u`, returning whether there were any.
+    ///
+    /// Changes not saved as an undo state yet are saved beforehand, even within an undo group. Does
+    /// nothing if the buffer is not modifiable.
+    pub fn undo(&mut self) -> bool {
+        self.travel(Undo::undo)
+    }
+
+    /// Redoes the changes undone last, as in `Ctrl-r`, returning whether there were any.
+    ///
+    /// Does nothing if the buffer is not modifiable.
+    pub fn redo(&mut self) -> bool {
+        self.travel(Undo::redo)
+    }
+
+    /// Moves to the undo state saved before the current one, across branches of the undo tree, as
+    /// in `g-`, returning whether there was one.
+    ///
+    /// Does nothing if the buffer is not modifiable.
+    pub fn earlier(&mut self) -> bool {
+        self.travel(Undo::earlier)
+    }
+
+    /// Moves to the undo state saved after the current one, across branches of the undo tree, as in
+    /// `g+`, returning whether there was one.
+    ///
+    /// Does nothing if the buffer is not modifiable.
+    pub fn later(&mut self) -> bool {
+        self.travel(Undo::later)
+    }
+
+    /// Moves through the undo history and makes the changes that bring the text to the state
+    /// reached, if any.
+    ///
+    /// The changes go through [`Self::edit`], so that positions kept elsewhere are adjusted, and
+    /// the cursor goes to the start of the topmost one.
+    fn travel(&mut self, step: fn(&mut Undo) -> Option<Vec<Change>>) -> bool {
+        if self.refuses_changes() {
+            return false;
+        }
+
+        self.save_changes();
+
+        let changes = match step(&mut self.undo) {
+            Some(changes) => changes,
+            None => return false,
+        };
+
+        for change in &changes {
+            self.edit(&change.inserted, change.start..change.end());
+        }
+
+        self.changes.clear();
+
+        if let Some(start) = changes.iter().map(|change| change.start).min() {
+            self.set_cursor(start.clamp_to_line(self));
+        }
+
+        true
+    }
+
+    /// Returns the changes made to the text since they were last taken, in the order they were
+    /// made, without taking them, or none if they are not recorded.
+    #[must_use]
+    pub fn edits(&self) -> &[Edit] {
+        &self.edits
+    }
+
+    /// Returns the changes made to the text since the last call, in the order they were made, or
+    /// none if they are not recorded.
+    ///
+    /// Positions stored outside of the buffer, such as folds, should be adjusted by each of them.
+    pub fn take_edits(&mut self) -> Vec<Edit> {
+        std::mem::take(&mut self.edits)
+    }
+
+    /// Resolves a range into a pair of positions delimiting it, as in `start..end`.
+    ///
+    /// Both positions are clamped into the buffer, and the end never precedes the start.
+    fn resolve(&self, range: impl RangeBounds<Cursor>) -> (Cursor, Cursor) {
+        let after = |at: Cursor| self.cells(self.clamp(at)).next().unwrap_or_else(|| self.eof());
+
+        let start = match range.start_bound() {
+            Bound::Included(&at) => self.clamp(at),
+            Bound::Excluded(&at) => after(at),
+            Bound::Unbounded => Cursor::origin(),
+        };
+
+        let end = match range.end_bound() {
+            Bound::Included(&at) => after(at),
+            Bound::Excluded(&at) => self.clamp(at),
+            Bound::Unbounded => self.eof(),
+        };
+
+        (start, end.max(start))
     }
 
     /// Attempts to move the cursor forward over a given metric.
     ///
     /// Returns the new position on success.
-    pub fn forward<'a, It>(&'a mut self) -> Option<Cursor>
-    where
-        It: Iterator<Item = Cursor>,
-    {
-        unimplemented!()
+    pub fn forward<M: Metric>(&mut self) -> Option<Cursor> {
+        self.cursor = M::after(self.cursor, self)?;
+        Some(self.cursor)
     }
 
     /// Attempts to move the cursor backward over a given metric.
     ///
     /// Returns the new position on success.
-    pub fn backward<'a, It>(&'a mut self) -> Option<Cursor>
-    where
-        It: DoubleEndedIterator<Item = Cursor>,
-    {
-        unimplemented!()
+    pub fn backward<M: Metric>(&mut self) -> Option<Cursor> {
+        self.cursor = M::before(self.cursor, self)?;
+        Some(self.cursor)
     }
 
     /// Returns an iterator over the cells of the buffer, starting at the specified position.
+    #[must_use]
     pub fn cells(&self, cursor: Cursor) -> Cells<'_> {
-        Cells::new(cursor, &self)
+        Cells::new(cursor, self)
     }
 
-    /// Returns an iterator over the paragraphs of the buffer, starting at the specified
-    /// position.
+    /// Returns an iterator over the characters of the buffer and their positions, going forward
+    /// from the specified position or backward before it.
+    #[must_use]
+    pub fn char_indices_from(&self, cursor: Cursor) -> CharIndices<'_> {
+        CharIndices::new(cursor, self)
+    }
+
+    /// Returns an iterator over the boundaries of the paragraphs of the buffer, as visited by `}`
+    /// and `{`, starting at the specified position.
+    #[must_use]
     pub fn paragraphs(&self, cursor: Cursor) -> Paragraphs<'_> {
-        Paragraphs::new(cursor, &self)
+        Paragraphs::new(cursor, self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Edit;
+    use crate::{Buffer, Cursor};
+
+    #[test]
+    fn test_adjust_insert_before() {
+        let edit =
+            Edit { start: Cursor::new(0, 1), end: Cursor::new(0, 1), new_end: Cursor::new(1, 2) };
+
+        assert_eq!(edit.adjust_cursor(Cursor::new(0, 0)), Cursor::new(0, 0));
+        assert_eq!(edit.adjust_cursor(Cursor::new(0, 1)), Cursor::new(1, 2));
+        assert_eq!(edit.adjust_cursor(Cursor::new(0, 4)), Cursor::new(1, 5));
+        assert_eq!(edit.adjust_cursor(Cursor::new(3, 4)), Cursor::new(4, 4));
+    }
+
+    #[test]
+    fn test_adjust_delete_before() {
+        let edit =
+            Edit { start: Cursor::new(1, 2), end: Cursor::new(3, 1), new_end: Cursor::new(1, 2) };
+
+        assert_eq!(edit.adjust_cursor(Cursor::new(1, 1)), Cursor::new(1, 1));
+        assert_eq!(edit.adjust_cursor(Cursor::new(3, 1)), Cursor::new(1, 2));
+        assert_eq!(edit.adjust_cursor(Cursor::new(3, 5)), Cursor::new(1, 6));
+        assert_eq!(edit.adjust_cursor(Cursor::new(5, 0)), Cursor::new(3, 0));
+    }
+
+    #[test]
+    fn test_adjust_delete_containing() {
+        let edit =
+            Edit { start: Cursor::new(1, 0), end: Cursor::new(3, 0), new_end: Cursor::new(1, 0) };
+
+        assert_eq!(edit.adjust_cursor(Cursor::new(1, 0)), Cursor::new(1, 0));
+        assert_eq!(edit.adjust_cursor(Cursor::new(2, 7)), Cursor::new(1, 0));
+
+        assert_eq!(
+            edit.adjust_range(Cursor::new(0, 2), Cursor::new(2, 1)),
+            (Cursor::new(0, 2), Cursor::new(1, 0))
+        );
+    }
+
+    #[test]
+    fn test_removes_line() {
+        let edit =
+            Edit { start: Cursor::new(1, 0), end: Cursor::new(3, 0), new_end: Cursor::new(1, 0) };
+
+        assert!(edit.removes_line(Cursor::new(1, 0), Cursor::new(1, 4)));
+        assert!(edit.removes_line(Cursor::new(2, 0), Cursor::new(2, 0)));
+        assert!(!edit.removes_line(Cursor::new(3, 0), Cursor::new(3, 2)));
+
+        let edit =
+            Edit { start: Cursor::new(1, 0), end: Cursor::new(1, 4), new_end: Cursor::new(1, 0) };
+
+        assert!(!edit.removes_line(Cursor::new(1, 0), Cursor::new(1, 4)));
+    }
+
+    #[test]
+    fn test_take_edits() {
+        let mut buffer = Buffer::from("foo\nbar");
+
+        buffer.insert('!', Cursor::new(0, 0));
+        assert!(buffer.take_edits().is_empty());
+
+        buffer.set_record_edits(true);
+        buffer.insert('x', Cursor::new(0, 2));
+        let end = buffer.edit("", Cursor::new(0, 3)..Cursor::new(1, 1));
+
+        let edits = buffer.take_edits();
+
+        assert_eq!(edits.len(), 2);
+        assert_eq!(edits[0].new_end, Cursor::new(0, 3));
+        assert_eq!(
+            edits[1],
+            Edit { start: Cursor::new(0, 3), end: Cursor::new(1, 1), new_end: end }
+        );
+        assert!(buffer.take_edits().is_empty());
+    }
+
+    #[test]
+    fn test_insert() {
+        let mut buffer = Buffer::from("fo\nbar");
+
+        buffer.insert('x', Cursor::new(0, 1));
+        assert_eq!(buffer.to_string(), "fxo\nbar");
+
+        buffer.insert('!', Cursor::new(1, 5));
+        assert_eq!(buffer.to_string(), "fxo\nbar  !");
+    }
+
+    #[test]
+    fn test_insert_line_break() {
+        let mut buffer = Buffer::from("foobar\nbaz");
+
+        buffer.insert('\n', Cursor::new(0, 3));
+        assert_eq!(buffer.to_string(), "foo\nbar\nbaz");
+
+        buffer.insert('\n', Cursor::new(2, 3));
+        assert_eq!(buffer.to_string(), "foo\nbar\nbaz\n");
+
+        buffer.insert('\n', Cursor::new(0, 0));
+        assert_eq!(buffer.to_string(), "\nfoo\nbar\nbaz\n");
+    }
+
+    #[test]
+    fn test_append() {
+        let mut buffer = Buffer::from("ab");
+        buffer.set_cursor(Cursor::new(0, 1));
+
+        "x\ny".chars().for_each(|ch| buffer.append(ch));
+        assert_eq!(buffer.to_string(), "ax\nyb");
+        assert_eq!(buffer.cursor(), Cursor::new(1, 1));
+
+        buffer.set_cursor(Cursor::new(1, 4));
+        "!?".chars().for_each(|ch| buffer.append(ch));
+        assert_eq!(buffer.to_string(), "ax\nyb  !?");
+        assert_eq!(buffer.cursor(), Cursor::new(1, 6));
+    }
+
+    #[test]
+    fn test_line_matches_split() {
+        let text = (0..1000).map(|row| "x".repeat(row % 7)).collect::<Vec<_>>().join("\n");
+        let buffer = Buffer::from(text.as_str());
+
+        for (row, line) in text.split('\n').enumerate() {
+            assert_eq!(buffer.line(row).map(ToString::to_string).as_deref(), Some(line));
+        }
+
+        assert!(buffer.line(1000).is_none());
+    }
+
+    #[test]
+    fn test_is_blank_line() {
+        let buffer = Buffer::from("\n \t\n foo\nbar");
+
+        assert!(buffer.is_blank_line(0));
+        assert!(buffer.is_blank_line(1));
+        assert!(!buffer.is_blank_line(2));
+        assert!(!buffer.is_blank_line(3));
+        assert!(!buffer.is_blank_line(4));
+    }
+
+    #[test]
+    fn test_join_lines() {
+        let mut buffer = Buffer::from("foo\nbar\n    baz\n  qux");
+
+        assert_eq!(buffer.join_lines(1..3, " "), Some(Cursor::new(1, 3)));
+        assert_eq!(buffer.to_string(), "foo\nbar baz\n  qux");
+
+        let mut buffer = Buffer::from("foo\nbar\n    baz\n  qux");
+
+        assert_eq!(buffer.join_lines(1..3, ""), Some(Cursor::new(1, 3)));
+        assert_eq!(buffer.to_string(), "foo\nbar    baz\n  qux");
+
+        assert_eq!(buffer.join_lines(2..3, " "), None);
+        assert_eq!(buffer.join_lines(2..5, " "), None);
+        assert_eq!(buffer.to_string(), "foo\nbar    baz\n  qux");
+    }
+
+    #[test]
+    fn test_join_lines_blanks() {
+        let mut buffer = Buffer::from("foo \nbar\n\n  )\nbaz");
+
+        assert_eq!(buffer.join_lines(0..4, " "), Some(Cursor::new(0, 7)));
+        assert_eq!(buffer.to_string(), "foo bar)\nbaz");
+    }
+
+    #[test]
+    fn test_byte_offsets() {
+        let buffer = Buffer::from("h\u{e9}llo\n\u{65e5}\u{672c}\n\u{1f600}x");
+        let text = buffer.to_string();
+
+        let cursors = [
+            (Cursor::new(0, 0), 0),
+            (Cursor::new(0, 2), 3),
+            (Cursor::new(0, 5), 6),
+            (Cursor::new(1, 1), 10),
+            (Cursor::new(1, 2), 13),
+            (Cursor::new(2, 1), 18),
+            (Cursor::new(2, 2), 19),
+        ];
+
+        for &(cursor, offset) in &cursors {
+            assert_eq!(buffer.cursor_to_byte(cursor), Some(offset));
+            assert_eq!(buffer.byte_to_cursor(offset), Some(cursor));
+            assert!(text.is_char_boundary(offset));
+        }
+
+        assert_eq!(buffer.byte_to_cursor(2), None);
+        assert_eq!(buffer.byte_to_cursor(16), None);
+        assert_eq!(buffer.byte_to_cursor(20), None);
+        assert_eq!(buffer.cursor_to_byte(Cursor::new(0, 6)), None);
+        assert_eq!(buffer.cursor_to_byte(Cursor::new(3, 0)), None);
+    }
+
+    #[test]
+    fn test_byte_offsets_dos() {
+        let buffer = Buffer::from("foo\r\nb\u{e9}r\r\nbaz");
+        let text = buffer.to_string_with_endings();
+
+        for &(cursor, offset, ch) in &[(Cursor::new(1, 0), 5, 'b'), (Cursor::new(2, 1), 12, 'a')] {
+            assert_eq!(buffer.cursor_to_byte(cursor), Some(offset));
+            assert_eq!(buffer.byte_to_cursor(offset), Some(cursor));
+            assert!(text[offset..].starts_with(ch));
+        }
+
+        assert_eq!(buffer.cursor_to_utf16(Cursor::new(2, 0)), Some(10));
+        assert_eq!(buffer.byte_to_cursor(4), None);
+    }
+
+    #[test]
+    fn test_utf16_offsets() {
+        let buffer = Buffer::from("h\u{e9}llo\n\u{65e5}\u{672c}\n\u{1f600}x");
+
+        let cursors = [
+            (Cursor::new(0, 2), 2),
+            (Cursor::new(1, 0), 6),
+            (Cursor::new(1, 2), 8),
+            (Cursor::new(2, 1), 11),
+            (Cursor::new(2, 2), 12),
+        ];
+
+        for &(cursor, offset) in &cursors {
+            assert_eq!(buffer.cursor_to_utf16(cursor), Some(offset));
+            assert_eq!(buffer.utf16_to_cursor(offset), Some(cursor));
+        }
+
+        assert_eq!(buffer.utf16_to_cursor(10), None);
+        assert_eq!(buffer.utf16_to_cursor(13), None);
+    }
+
+    #[test]
+    fn test_utf16_positions() {
+        let buffer = Buffer::from("a\u{1f600}b\n\u{1f600}");
+
+        let positions = [
+            (Cursor::new(0, 1), (0, 1)),
+            (Cursor::new(0, 2), (0, 3)),
+            (Cursor::new(0, 3), (0, 4)),
+            (Cursor::new(1, 0), (1, 0)),
+            (Cursor::new(1, 1), (1, 2)),
+        ];
+
+        for &(cursor, (row, col)) in &positions {
+            assert_eq!(buffer.cursor_to_utf16_position(cursor), Some((row, col)));
+            assert_eq!(buffer.utf16_position_to_cursor(row, col), Some(cursor));
+        }
+
+        assert_eq!(buffer.utf16_position_to_cursor(0, 2), None);
+        assert_eq!(buffer.utf16_position_to_cursor(1, 1), None);
+        assert_eq!(buffer.utf16_position_to_cursor(0, 9), Some(Cursor::new(0, 3)));
+        assert_eq!(buffer.utf16_position_to_cursor(2, 0), None);
+        assert_eq!(buffer.cursor_to_utf16_position(Cursor::new(1, 2)), None);
+    }
+
+    #[test]
+    fn test_display_col() {
+        let mut buffer = Buffer::from("a\tb\t\tc\n\u{4e16}x");
+
+        assert_eq!(buffer.display_col(Cursor::new(0, 1)), 1);
+        assert_eq!(buffer.display_col(Cursor::new(0, 2)), 8);
+        assert_eq!(buffer.display_col(Cursor::new(0, 5)), 24);
+        assert_eq!(buffer.display_col(Cursor::new(1, 1)), 2);
+
+        buffer.set_tab_stop(4);
+        assert_eq!(buffer.display_col(Cursor::new(0, 5)), 12);
+    }
+
+    #[test]
+    fn test_shift() {
+        let mut buffer = Buffer::from("foo\n  bar");
+        buffer.set_shift_width(4);
+        buffer.set_cursor(Cursor::new(1, 3));
+
+        buffer.shift(1, true);
+        assert_eq!(buffer.to_string(), "foo\n      bar");
+        assert_eq!(buffer.cursor(), Cursor::new(1, 7));
+
+        buffer.shift(1, true);
+        assert_eq!(buffer.to_string(), "foo\n\t  bar");
+        assert_eq!(buffer.cursor(), Cursor::new(1, 4));
+
+        buffer.set_expand_tab(true);
+        buffer.shift(1, false);
+        assert_eq!(buffer.to_string(), "foo\n      bar");
+
+        buffer.shift(1, false);
+        buffer.shift(1, false);
+        assert_eq!(buffer.to_string(), "foo\nbar");
+        assert_eq!(buffer.cursor(), Cursor::new(1, 1));
+
+        buffer.shift(0, true);
+        assert_eq!(buffer.to_string(), "    foo\nbar");
+        assert_eq!(buffer.cursor(), Cursor::new(1, 1));
+    }
+
+    #[test]
+    fn test_not_modifiable() {
+        let mut buffer = Buffer::from("foo\nbar");
+        buffer.set_modifiable(false);
+
+        buffer.insert('x', Cursor::new(0, 1));
+        buffer.append('\n');
+        buffer.shift(1, true);
+
+        assert_eq!(buffer.edit("baz", ..), Cursor::origin());
+        assert_eq!(buffer.to_string(), "foo\nbar");
+        assert_eq!(buffer.cursor(), Cursor::origin());
+        assert!(buffer.take_refused());
+        assert!(!buffer.take_refused());
+
+        buffer.set_modifiable(true);
+        buffer.edit("baz", ..);
+        assert_eq!(buffer.to_string(), "baz");
+    }
+
+    #[test]
+    fn test_cursor_at() {
+        let buffer = Buffer::from("foo\n\tbar\n\u{4e16}\u{754c}x\n");
+
+        assert_eq!(buffer.cursor_at(0, 1), Cursor::new(0, 1));
+        assert_eq!(buffer.cursor_at(1, 0), Cursor::new(1, 0));
+        assert_eq!(buffer.cursor_at(1, 7), Cursor::new(1, 0));
+        assert_eq!(buffer.cursor_at(1, 9), Cursor::new(1, 2));
+        assert_eq!(buffer.cursor_at(2, 3), Cursor::new(2, 1));
+        assert_eq!(buffer.cursor_at(2, 4), Cursor::new(2, 2));
+
+        assert_eq!(buffer.cursor_at(0, 10), Cursor::new(0, 2));
+        assert_eq!(buffer.cursor_at(3, 10), Cursor::new(3, 0));
+        assert_eq!(buffer.cursor_at(9, 1), Cursor::new(3, 0));
+    }
+
+    #[test]
+    fn test_content_hash() {
+        let mut buffer = Buffer::from("foo\nbar");
+        let hash = buffer.content_hash();
+
+        assert_eq!(Buffer::from("foo\r\nbar").content_hash(), hash);
+        assert_ne!(Buffer::from("foob\nar").content_hash(), hash);
+
+        let end = buffer.edit("x", Cursor::new(0, 1)..Cursor::new(0, 1));
+        assert_ne!(buffer.content_hash(), hash);
+
+        buffer.edit("", Cursor::new(0, 1)..end);
+        assert_eq!(buffer.content_hash(), hash);
     }
 }
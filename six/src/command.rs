@@ -0,0 +1,1563 @@
+mod range;
+
+pub use range::{Address, Base, Range};
+
+#[cfg(feature = "io")]
+use std::fs;
+#[cfg(feature = "io")]
+use std::io;
+use std::ops::RangeInclusive;
+#[cfg(feature = "io")]
+use std::path::MAIN_SEPARATOR;
+use std::str::FromStr;
+use std::time::Duration;
+
+use itertools::Itertools;
+
+use crate::buffer::{Buffer, LineEnding, Row};
+use crate::event::{self, Event, Key, Modifiers};
+#[cfg(feature = "io")]
+use crate::fold::Folds;
+use crate::mode::{Mode, Normal};
+use crate::register::{Register, Registers};
+use crate::script;
+use crate::state::Context;
+use crate::Cursor;
+
+/// The outcome of a command, holding a message describing the failure if there was one.
+pub type Result = std::result::Result<(), String>;
+
+/// An editor command, as typed in the command line.
+pub struct Command {
+    /// The full name of the command.
+    pub name: &'static str,
+
+    /// The length of the shortest accepted abbreviation of the name.
+    pub abbreviation: usize,
+
+    /// Whether the arguments are file names, which are completed from the file system.
+    pub files: bool,
+
+    /// Runs the command with the specified arguments.
+    run: Run,
+}
+
+/// How a command is run, which tells whether it takes a range.
+#[derive(Clone, Copy)]
+enum Run {
+    /// Runs with the arguments, rejecting any range.
+    Plain(fn(&mut Context, &str) -> Result),
+
+    /// Runs over the rows of a range, which defaults to the line of the cursor, with the
+    /// arguments.
+    Lines(fn(&mut Context, RangeInclusive<usize>, &str) -> Result),
+
+    /// Runs over the rows of a range, which defaults to every line, with the arguments.
+    All(fn(&mut Context, RangeInclusive<usize>, &str) -> Result),
+}
+
+/// A replacement of text in lines, as made by `:s/pattern/replacement/g`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Substitution {
+    /// The text to be replaced, which is matched literally.
+    pub pattern: String,
+
+    /// The text that replaces the pattern.
+    pub replacement: String,
+
+    /// Whether every match in a line is replaced, as with the `g` flag, rather than the first one.
+    pub global: bool,
+}
+
+/// The available commands.
+pub static COMMANDS: &[Command] = &[
+    Command { name: "&", abbreviation: 1, files: false, run: Run::Lines(repeat) },
+    Command { name: "<", abbreviation: 1, files: false, run: Run::Lines(shift_left) },
+    Command { name: ">", abbreviation: 1, files: false, run: Run::Lines(shift_right) },
+    Command { name: "call", abbreviation: 3, files: false, run: Run::Plain(call) },
+    Command { name: "copy", abbreviation: 2, files: false, run: Run::Lines(copy) },
+    Command { name: "delete", abbreviation: 1, files: false, run: Run::Lines(delete) },
+    #[cfg(feature = "io")]
+    Command { name: "edit", abbreviation: 1, files: true, run: Run::Plain(edit) },
+    Command { name: "global", abbreviation: 1, files: false, run: Run::All(global) },
+    Command { name: "iabbrev", abbreviation: 3, files: false, run: Run::Plain(iabbrev) },
+    Command { name: "lua", abbreviation: 3, files: false, run: Run::Plain(script::eval) },
+    Command { name: "luajump", abbreviation: 4, files: false, run: Run::Plain(script::jump) },
+    Command { name: "move", abbreviation: 1, files: false, run: Run::Lines(move_lines) },
+    Command { name: "normal", abbreviation: 4, files: false, run: Run::Lines(normal) },
+    Command { name: "set", abbreviation: 2, files: false, run: Run::Plain(set) },
+    Command { name: "substitute", abbreviation: 1, files: false, run: Run::Lines(substitute) },
+    Command { name: "t", abbreviation: 1, files: false, run: Run::Lines(copy) },
+    Command { name: "vglobal", abbreviation: 1, files: false, run: Run::All(vglobal) },
+    #[cfg(feature = "io")]
+    Command { name: "write", abbreviation: 1, files: true, run: Run::Plain(write) },
+    Command { name: "yank", abbreviation: 1, files: false, run: Run::Lines(yank) },
+];
+
+impl Command {
+    /// Returns whether the specified name refers to this command.
+    #[must_use]
+    pub fn matches(&self, name: &str) -> bool {
+        name.len() >= self.abbreviation && self.name.starts_with(name)
+    }
+}
+
+impl Substitution {
+    /// Parses the arguments of `:s`, as in `/pattern/replacement/g`, where the first character
+    /// delimits the others and can be any punctuation but `\`, `"` and `|`.
+    ///
+    /// An empty pattern stands for the one of the previous substitution.
+    fn parse(args: &str, previous: Option<&Self>) -> std::result::Result<Self, String> {
+        let delimiter = delimiter(args)?;
+        let mut parts = args[1..].splitn(3, delimiter);
+
+        let pattern = match (parts.next().unwrap_or_default(), previous) {
+            ("", Some(previous)) => previous.pattern.clone(),
+            ("", None) => return Err("No previous pattern".to_owned()),
+            (pattern, _) => pattern.to_owned(),
+        };
+
+        let replacement = parts.next().unwrap_or_default().to_owned();
+
+        let global = match parts.next().unwrap_or_default() {
+            "" => false,
+            "g" => true,
+            flags => return Err(format!("Trailing characters: {}", flags)),
+        };
+
+        Ok(Self { pattern, replacement, global })
+    }
+
+    /// Replaces the pattern in the lines from `first` to `last`, ignoring the case of letters if
+    /// `ignore_case` is set, returning the last line changed, if any.
+    fn apply(
+        &self,
+        buffer: &mut Buffer,
+        first: usize,
+        last: usize,
+        ignore_case: bool,
+    ) -> Option<usize> {
+        let count = if self.global { usize::MAX } else { 1 };
+        let mut changed = None;
+
+        for row in first..=last {
+            let line = match buffer.line(row) {
+                Some(line) => line.to_string(),
+                None => break,
+            };
+
+            let mut text = String::new();
+            let mut rest = line.as_str();
+
+            for _ in 0..count {
+                match find(rest, &self.pattern, ignore_case) {
+                    Some(range) => {
+                        text.push_str(&rest[..range.start]);
+                        text.push_str(&self.replacement);
+                        rest = &rest[range.end..];
+                    },
+
+                    None => break,
+                }
+            }
+
+            if rest.len() < line.len() {
+                text.push_str(rest);
+                buffer.edit(&text, Cursor::new(row, 0)..Cursor::new(row, line.chars().count()));
+
+                changed = Some(row);
+            }
+        }
+
+        changed
+    }
+}
+
+/// Returns the bytes of the first occurrence of a pattern in some text, which is matched
+/// literally, ignoring the case of letters if `ignore_case` is set, as with Vim's `ignorecase`.
+///
+/// An empty pattern matches nothing.
+fn find(text: &str, pattern: &str, ignore_case: bool) -> Option<std::ops::Range<usize>> {
+    if pattern.is_empty() {
+        return None;
+    }
+
+    if !ignore_case {
+        return text.find(pattern).map(|start| start..start + pattern.len());
+    }
+
+    text.char_indices().find_map(|(start, _)| {
+        let mut chars = text[start..].char_indices();
+        let mut end = start;
+
+        for expected in pattern.chars() {
+            let (idx, ch) = chars.next()?;
+
+            if !ch.to_lowercase().eq(expected.to_lowercase()) {
+                return None;
+            }
+
+            end = start + idx + ch.len_utf8();
+        }
+
+        Some(start..end)
+    })
+}
+
+/// Returns the character delimiting the pattern at the start of the arguments of `:s` and `:g`,
+/// which can be any punctuation but `\`, `"` and `|`.
+fn delimiter(args: &str) -> std::result::Result<char, String> {
+    match args.chars().next() {
+        Some(ch) if ch.is_ascii_punctuation() && !"\\\"|".contains(ch) => Ok(ch),
+        _ => Err(format!("Invalid argument: {}", args)),
+    }
+}
+
+/// Parses and runs a command line.
+///
+/// The line may start with a range, as in `:%s` or `:2,5&`, and a range alone moves the cursor to
+/// the first non-blank of its last line, as in `:12`. The name of a command is made of the letters
+/// after the range, or of the character after it otherwise, as in `:&&`, and the rest of the line
+/// are its arguments.
+///
+/// # Errors
+///
+/// Fails if the command does not exist, if it is given a range it doesn't take or one outside of
+/// the buffer, or if it fails itself.
+pub fn execute(context: &mut Context, line: &str) -> Result {
+    let (range, line) = Range::parse(line.trim());
+    let line = line.trim_start();
+
+    let (row, last) = (context.buffer.cursor().row(), context.buffer.content().len() - 1);
+    let rows = match range {
+        Some(range) => Some(range.resolve(row, last + 1)?),
+        None => None,
+    };
+
+    if let (Some(rows), "") = (&rows, line) {
+        first_non_blank(&mut context.buffer, *rows.end());
+        return Ok(());
+    }
+
+    let len = line.find(|ch: char| !ch.is_ascii_alphabetic()).unwrap_or(line.len());
+    let len = if len > 0 { len } else { line.chars().next().map_or(0, char::len_utf8) };
+
+    let (name, args) = line.split_at(len);
+
+    let command = COMMANDS
+        .iter()
+        .find(|command| command.matches(name))
+        .ok_or_else(|| format!("Not an editor command: {}", line))?;
+
+    match (command.run, rows) {
+        (Run::Plain(_), Some(_)) => Err("No range allowed".to_owned()),
+        (Run::Plain(run), None) => run(context, args.trim()),
+        (Run::Lines(run), rows) => run(context, rows.unwrap_or(row..=row), args.trim()),
+        (Run::All(run), rows) => run(context, rows.unwrap_or(0..=last), args.trim()),
+    }
+}
+
+/// Returns the command lines that complete a partially typed one.
+///
+/// Command names are completed in the order of the command table. Arguments are only completed
+/// for commands taking file names, in alphabetical order.
+#[must_use]
+pub fn complete(_: &Context, line: &str) -> Vec<String> {
+    let (name, args) = line.split_at(line.find(char::is_whitespace).unwrap_or(line.len()));
+
+    if args.is_empty() {
+        return COMMANDS
+            .iter()
+            .filter(|command| command.name.starts_with(name))
+            .map(|command| command.name.to_owned())
+            .collect();
+    }
+
+    match COMMANDS.iter().find(|command| command.matches(name.trim_end_matches('!'))) {
+        #[cfg(feature = "io")]
+        Some(command) if command.files => complete_path(args.trim_start())
+            .into_iter()
+            .map(|path| format!("{} {}", name, path))
+            .collect(),
+
+        _ => Vec::new(),
+    }
+}
+
+/// Returns the paths that complete a partially typed one, ending directories with a separator.
+#[cfg(feature = "io")]
+fn complete_path(path: &str) -> Vec<String> {
+    let (dir, prefix) = path.split_at(path.rfind(std::path::is_separator).map_or(0, |idx| idx + 1));
+
+    let entries = match fs::read_dir(if dir.is_empty() { "." } else { dir }) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut paths = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let name = entry.file_name().into_string().ok()?;
+
+            if !name.starts_with(prefix) {
+                return None;
+            }
+
+            if entry.file_type().ok()?.is_dir() {
+                Some(format!("{}{}{}", dir, name, MAIN_SEPARATOR))
+            } else {
+                Some(format!("{}{}", dir, name))
+            }
+        })
+        .collect::<Vec<_>>();
+
+    paths.sort();
+    paths
+}
+
+/// Calls a registered function, as in `:call name`.
+fn call(context: &mut Context, args: &str) -> Result {
+    context.call(args)
+}
+
+/// Defines an abbreviation for insert mode, as in `:iabbrev lhs rhs`.
+fn iabbrev(context: &mut Context, args: &str) -> Result {
+    let (lhs, rhs) = args.split_at(args.find(char::is_whitespace).unwrap_or(args.len()));
+    let rhs = rhs.trim_start();
+
+    if lhs.is_empty() || rhs.is_empty() {
+        return Err("Argument required".to_owned());
+    }
+
+    if !lhs.chars().all(|ch| context.buffer.is_keyword(ch)) {
+        return Err(format!("Invalid argument: {}", lhs));
+    }
+
+    context.abbreviations.insert(lhs.to_owned(), rhs.to_owned());
+    Ok(())
+}
+
+/// Edits a file, as in `:edit [path]`.
+///
+/// Without a path, reloads the file associated with the buffer. A file that doesn't exist yet is
+/// edited as an empty buffer.
+///
+/// A modified buffer is only replaced when forced, as in `:edit!`, which discards its changes. The
+/// folds, signs and last selection of the buffer are dropped along with it, while its settings,
+/// such as `shiftwidth`, carry over to the new one.
+#[cfg(feature = "io")]
+fn edit(context: &mut Context, args: &str) -> Result {
+    let (force, args) = match args.strip_prefix('!') {
+        Some(args) => (true, args.trim_start()),
+        None => (false, args),
+    };
+
+    if !force && context.buffer.is_modified() {
+        return Err("No write since last change (add ! to override)".to_owned());
+    }
+
+    let path = match (args, context.buffer.path()) {
+        ("", Some(path)) => path.to_owned(),
+        ("", None) => return Err("No file name".to_owned()),
+        (path, _) => path.into(),
+    };
+
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err.to_string()),
+    };
+
+    let old = std::mem::replace(&mut context.buffer, Buffer::from(text.as_str()));
+    let buffer = &mut context.buffer;
+
+    buffer.set_file_hash(buffer.content_hash());
+    buffer.set_path(path);
+
+    buffer.set_keyword(old.keyword());
+    buffer.set_expand_tab(old.expand_tab());
+    buffer.set_tab_stop(old.tab_stop());
+    buffer.set_shift_width(old.shift_width());
+    buffer.set_soft_tab_stop(old.soft_tab_stop());
+    buffer.set_text_width(old.text_width());
+    buffer.set_comment(old.comment());
+    buffer.set_auto_pairs(old.auto_pairs());
+    buffer.set_modifiable(old.modifiable());
+    buffer.set_record_edits(old.record_edits());
+
+    context.folds = Folds::default();
+    context.signs.clear();
+    context.selection = None;
+
+    Ok(())
+}
+
+/// Sets options, as in `:set fileformat=dos`, or shows them, as in `:set fileformat?`.
+///
+/// Toggles are turned on by their name and off by their name prefixed with `no`, as in
+/// `:set noexpandtab`.
+fn set(context: &mut Context, args: &str) -> Result {
+    let mut shown = Vec::new();
+
+    for arg in args.split_whitespace() {
+        match arg.strip_suffix('?') {
+            Some(name) => shown.push(show(context, name)?),
+            None => assign(context, arg)?,
+        }
+    }
+
+    if !shown.is_empty() {
+        context.message = Some(shown.join(" "));
+    }
+
+    Ok(())
+}
+
+/// Returns how an option is shown by `:set option?`, as `option=value`, or as `option` or
+/// `nooption` for toggles.
+fn show(context: &Context, name: &str) -> std::result::Result<String, String> {
+    let (buffer, options) = (&context.buffer, &context.options);
+
+    let toggle = |name: &str, on: bool| if on { name.to_owned() } else { format!("no{}", name) };
+
+    let shown = match name {
+        "fileformat" | "ff" => match buffer.line_ending() {
+            LineEnding::Unix => "fileformat=unix".to_owned(),
+            LineEnding::Dos => "fileformat=dos".to_owned(),
+        },
+
+        "fixendofline" | "fixeol" => toggle("fixendofline", buffer.fix_end_of_line()),
+        "expandtab" | "et" => toggle("expandtab", buffer.expand_tab()),
+        "autopairs" => toggle("autopairs", buffer.auto_pairs()),
+        "modifiable" | "ma" => toggle("modifiable", buffer.modifiable()),
+        "relativenumber" | "rnu" => toggle("relativenumber", options.relative_number),
+        "ignorecase" | "ic" => toggle("ignorecase", options.ignore_case),
+        "yanktoend" => toggle("yanktoend", options.yank_to_end),
+
+        "tabstop" | "ts" => format!("tabstop={}", buffer.tab_stop()),
+        "shiftwidth" | "sw" => format!("shiftwidth={}", buffer.shift_width()),
+        "softtabstop" | "sts" => format!("softtabstop={}", buffer.soft_tab_stop()),
+        "textwidth" | "tw" => format!("textwidth={}", buffer.text_width()),
+        "scrolloff" | "so" => format!("scrolloff={}", options.scroll_off),
+        "timeoutlen" | "tm" => format!("timeoutlen={}", options.timeout_len.as_millis()),
+        "comment" => format!("comment={}", buffer.comment()),
+
+        _ => return Err(format!("Unknown option: {}", name)),
+    };
+
+    Ok(shown)
+}
+
+/// Parses the number given to an option.
+fn number<T: FromStr>(value: &str) -> std::result::Result<T, String> {
+    value.parse().map_err(|_| format!("Invalid argument: {}", value))
+}
+
+/// Sets a single option, as in `fileformat=dos`, `expandtab` or `noexpandtab`.
+fn assign(context: &mut Context, arg: &str) -> Result {
+    let mut arg = arg.splitn(2, '=');
+
+    match (arg.next().unwrap_or_default(), arg.next()) {
+        ("fileformat", Some(value)) | ("ff", Some(value)) => {
+            let ending = match value {
+                "unix" => LineEnding::Unix,
+                "dos" => LineEnding::Dos,
+                _ => return Err(format!("Invalid argument: {}", value)),
+            };
+
+            context.buffer.set_line_ending(ending);
+            Ok(())
+        },
+
+        ("fixendofline", None) | ("fixeol", None) => {
+            context.buffer.set_fix_end_of_line(true);
+            Ok(())
+        },
+
+        ("nofixendofline", None) | ("nofixeol", None) => {
+            context.buffer.set_fix_end_of_line(false);
+            Ok(())
+        },
+
+        ("expandtab", None) | ("et", None) => {
+            context.buffer.set_expand_tab(true);
+            Ok(())
+        },
+
+        ("noexpandtab", None) | ("noet", None) => {
+            context.buffer.set_expand_tab(false);
+            Ok(())
+        },
+
+        ("autopairs", None) => {
+            context.buffer.set_auto_pairs(true);
+            Ok(())
+        },
+
+        ("noautopairs", None) => {
+            context.buffer.set_auto_pairs(false);
+            Ok(())
+        },
+
+        ("modifiable", None) | ("ma", None) => {
+            context.buffer.set_modifiable(true);
+            Ok(())
+        },
+
+        ("nomodifiable", None) | ("noma", None) => {
+            context.buffer.set_modifiable(false);
+            Ok(())
+        },
+
+        ("tabstop", Some(value)) | ("ts", Some(value)) => {
+            match value.parse() {
+                Ok(width) if width > 0 => context.buffer.set_tab_stop(width),
+                _ => return Err(format!("Invalid argument: {}", value)),
+            }
+
+            Ok(())
+        },
+
+        ("shiftwidth", Some(value)) | ("sw", Some(value)) => {
+            context.buffer.set_shift_width(number(value)?);
+            Ok(())
+        },
+
+        ("softtabstop", Some(value)) | ("sts", Some(value)) => {
+            context.buffer.set_soft_tab_stop(number(value)?);
+            Ok(())
+        },
+
+        ("textwidth", Some(value)) | ("tw", Some(value)) => {
+            context.buffer.set_text_width(number(value)?);
+            Ok(())
+        },
+
+        ("relativenumber", None) | ("rnu", None) => {
+            context.options.relative_number = true;
+            Ok(())
+        },
+
+        ("norelativenumber", None) | ("nornu", None) => {
+            context.options.relative_number = false;
+            Ok(())
+        },
+
+        ("ignorecase", None) | ("ic", None) => {
+            context.options.ignore_case = true;
+            Ok(())
+        },
+
+        ("noignorecase", None) | ("noic", None) => {
+            context.options.ignore_case = false;
+            Ok(())
+        },
+
+        ("yanktoend", None) => {
+            context.options.yank_to_end = true;
+            Ok(())
+        },
+
+        ("noyanktoend", None) => {
+            context.options.yank_to_end = false;
+            Ok(())
+        },
+
+        ("scrolloff", Some(value)) | ("so", Some(value)) => {
+            context.options.scroll_off = number(value)?;
+            Ok(())
+        },
+
+        ("timeoutlen", Some(value)) | ("tm", Some(value)) => {
+            context.options.timeout_len = Duration::from_millis(number(value)?);
+            Ok(())
+        },
+
+        ("comment", Some(value)) if !value.is_empty() => {
+            context.buffer.set_comment(value);
+            Ok(())
+        },
+
+        (option, _) => Err(format!("Unknown option: {}", option)),
+    }
+}
+
+/// Replaces text in a range of lines, as in `:%s/pattern/replacement/g`, and remembers the
+/// substitution for `&`.
+///
+/// Without arguments, repeats the last substitution without its flags, as in `:&`. Leaves the
+/// cursor on the first non-blank of the last line changed.
+fn substitute(context: &mut Context, rows: RangeInclusive<usize>, args: &str) -> Result {
+    let (first, last) = rows.into_inner();
+
+    if args.is_empty() {
+        return resubstitute(context, first, last, false);
+    }
+
+    let substitution = Substitution::parse(args, context.substitution.as_ref())?;
+    context.substitution = Some(substitution.clone());
+
+    replace(context, &substitution, first, last)
+}
+
+/// Repeats the last substitution in a range of lines, as in `:&`, keeping its flags if the
+/// argument is `&`, as in `:&&`.
+fn repeat(context: &mut Context, rows: RangeInclusive<usize>, args: &str) -> Result {
+    let (first, last) = rows.into_inner();
+
+    match args {
+        "" | "&" => resubstitute(context, first, last, args == "&"),
+        _ => Err(format!("Trailing characters: {}", args)),
+    }
+}
+
+/// Repeats the last substitution in the lines from `first` to `last`, keeping its flags if `flags`
+/// is set, as in `&` and `g&`.
+///
+/// # Errors
+///
+/// Fails if there was no substitution yet or if the pattern is not found.
+pub fn resubstitute(context: &mut Context, first: usize, last: usize, flags: bool) -> Result {
+    let mut substitution =
+        context.substitution.clone().ok_or_else(|| "No previous substitute".to_owned())?;
+
+    substitution.global &= flags;
+    replace(context, &substitution, first, last)
+}
+
+/// Makes a substitution in the lines from `first` to `last`, leaving the cursor on the first
+/// non-blank of the last line changed.
+fn replace(
+    context: &mut Context,
+    substitution: &Substitution,
+    first: usize,
+    last: usize,
+) -> Result {
+    let ignore_case = context.options.ignore_case;
+    let buffer = &mut context.buffer;
+
+    let row = substitution
+        .apply(buffer, first, last, ignore_case)
+        .ok_or_else(|| format!("Pattern not found: {}", substitution.pattern))?;
+
+    first_non_blank(buffer, row);
+    Ok(())
+}
+
+/// Returns the register named by the arguments of `:d` and `:y`, as in `:d a`, or the unnamed
+/// register without arguments.
+fn register(args: &str) -> std::result::Result<char, String> {
+    let mut chars = args.chars();
+
+    match (chars.next(), chars.next()) {
+        (None, _) => Ok(Registers::UNNAMED),
+        (Some(name), None) => Ok(name),
+        _ => Err(format!("Trailing characters: {}", args)),
+    }
+}
+
+/// Returns the line number given as the destination of `:m` and `:t`, where 0 stands for the
+/// position before the first line.
+fn destination(context: &Context, args: &str) -> std::result::Result<usize, String> {
+    let buffer = &context.buffer;
+
+    match Address::parse(args) {
+        Some((address, "")) => address
+            .resolve(buffer.cursor().row(), buffer.content().len())
+            .ok_or_else(|| "Invalid address".to_owned()),
+
+        Some((_, rest)) => Err(format!("Trailing characters: {}", rest)),
+        None => Err("Invalid address".to_owned()),
+    }
+}
+
+/// Returns the text of the lines from `first` to `last`, without a line break at the end.
+fn text(buffer: &Buffer, first: usize, last: usize) -> String {
+    (first..=last).filter_map(|row| buffer.line(row)).map(Row::to_string).join("\n")
+}
+
+/// Removes the lines from `first` to `last`, along with a line break.
+fn remove_lines(buffer: &mut Buffer, first: usize, last: usize) {
+    if last + 1 < buffer.content().len() {
+        buffer.edit("", Cursor::new(first, 0)..Cursor::new(last + 1, 0));
+    } else if first > 0 {
+        // Take the line break before the lines, as there is none after them.
+        let eol = buffer.clamp(Cursor::new(first - 1, usize::MAX));
+        buffer.edit("", eol..);
+    } else {
+        buffer.edit("", ..);
+    }
+}
+
+/// Inserts text as whole lines starting at a row, or after the last line if the row is past it.
+fn insert_lines(buffer: &mut Buffer, row: usize, text: &str) {
+    if row < buffer.content().len() {
+        let start = Cursor::new(row, 0);
+        buffer.edit(&format!("{}\n", text), start..start);
+    } else {
+        let end = buffer.clamp(Cursor::new(row - 1, usize::MAX));
+        buffer.edit(&format!("\n{}", text), end..end);
+    }
+}
+
+/// Moves the cursor to the first non-blank of a line.
+fn first_non_blank(buffer: &mut Buffer, row: usize) {
+    let col = buffer.line(row).map_or(0, Row::first_non_blank);
+    buffer.set_cursor(Cursor::new(row, col).clamp_to_line(buffer));
+}
+
+/// Deletes a range of lines into a register, as in `:d` or `:d a`, leaving the cursor on the first
+/// non-blank of the line after them.
+fn delete(context: &mut Context, rows: RangeInclusive<usize>, args: &str) -> Result {
+    let (first, last) = rows.into_inner();
+    let name = register(args)?;
+
+    let text = text(&context.buffer, first, last);
+    context.store(name, Register::new(format!("{}\n", text), true));
+
+    let buffer = &mut context.buffer;
+    remove_lines(buffer, first, last);
+    first_non_blank(buffer, first.min(buffer.content().len() - 1));
+
+    Ok(())
+}
+
+/// Copies a range of lines into a register, as in `:y` or `:y a`.
+fn yank(context: &mut Context, rows: RangeInclusive<usize>, args: &str) -> Result {
+    let (first, last) = rows.into_inner();
+    let name = register(args)?;
+
+    let text = text(&context.buffer, first, last);
+    context.store(name, Register::new(format!("{}\n", text), true));
+
+    Ok(())
+}
+
+/// Moves a range of lines below the line given as argument, as in `:m $` or `:m 0`, leaving the
+/// cursor on the first non-blank of the last line moved.
+fn move_lines(context: &mut Context, rows: RangeInclusive<usize>, args: &str) -> Result {
+    let (first, last) = rows.into_inner();
+    let line = destination(context, args)?;
+
+    if line > first && line <= last {
+        return Err("Cannot move a range of lines into itself".to_owned());
+    }
+
+    let buffer = &mut context.buffer;
+    let count = last - first + 1;
+
+    // The destination moves up along with the lines after the range once it's removed.
+    let row = if line > last { line - count } else { line };
+
+    if row != first {
+        let text = text(buffer, first, last);
+
+        remove_lines(buffer, first, last);
+        insert_lines(buffer, row, &text);
+    }
+
+    first_non_blank(buffer, row + count - 1);
+    Ok(())
+}
+
+/// Copies a range of lines below the line given as argument, as in `:t .` or `:t 0`, leaving the
+/// cursor on the first non-blank of the last line copied.
+fn copy(context: &mut Context, rows: RangeInclusive<usize>, args: &str) -> Result {
+    let (first, last) = rows.into_inner();
+    let line = destination(context, args)?;
+
+    let buffer = &mut context.buffer;
+    let text = text(buffer, first, last);
+
+    insert_lines(buffer, line, &text);
+    first_non_blank(buffer, line + last - first);
+
+    Ok(())
+}
+
+/// Runs a command on each line of a range that contains a pattern, as in `:g/pattern/d`, or on
+/// each line that doesn't, as in `:g!/pattern/d`.
+fn global(context: &mut Context, rows: RangeInclusive<usize>, args: &str) -> Result {
+    match args.strip_prefix('!') {
+        Some(args) => each(context, rows, args, false),
+        None => each(context, rows, args, true),
+    }
+}
+
+/// Runs a command on each line of a range that doesn't contain a pattern, as in `:v/pattern/d`.
+fn vglobal(context: &mut Context, rows: RangeInclusive<usize>, args: &str) -> Result {
+    each(context, rows, args, false)
+}
+
+/// Runs a command with the cursor on each line of a range that contains a pattern, or on each one
+/// that doesn't if `matching` is unset.
+///
+/// The lines are found before running the command on any of them. An empty pattern stands for the
+/// one of the previous substitution.
+fn each(context: &mut Context, rows: RangeInclusive<usize>, args: &str, matching: bool) -> Result {
+    let delimiter = delimiter(args)?;
+    let mut parts = args[1..].splitn(2, delimiter);
+
+    let pattern = match (parts.next().unwrap_or_default(), &context.substitution) {
+        ("", Some(previous)) => previous.pattern.clone(),
+        ("", None) => return Err("No previous pattern".to_owned()),
+        (pattern, _) => pattern.to_owned(),
+    };
+
+    let command = parts.next().unwrap_or_default();
+
+    if command.trim().is_empty() {
+        return Err("Argument required".to_owned());
+    }
+
+    let buffer = &context.buffer;
+    let ignore_case = context.options.ignore_case;
+
+    let rows = rows
+        .filter(|&row| {
+            let line = buffer.line(row).map(ToString::to_string);
+            line.map_or(false, |line| find(&line, &pattern, ignore_case).is_some() == matching)
+        })
+        .collect::<Vec<_>>();
+
+    if rows.is_empty() {
+        return Err(format!("Pattern not found: {}", pattern));
+    }
+
+    on_rows(context, rows, |context| execute(context, command))
+}
+
+/// Runs a function with the cursor at the start of each of some rows, in order.
+///
+/// The rows are followed through the edits made by the function, which are recorded meanwhile, so
+/// that the ones it deletes are skipped. Failures don't stop it, and are only reported if it failed
+/// on every row.
+fn on_rows(
+    context: &mut Context,
+    rows: impl IntoIterator<Item = usize>,
+    mut run: impl FnMut(&mut Context) -> Result,
+) -> Result {
+    let record = context.buffer.record_edits();
+    context.buffer.set_record_edits(true);
+
+    let buffer = &context.buffer;
+    let mut lines = rows
+        .into_iter()
+        .map(|row| Some((Cursor::new(row, 0), buffer.clamp(Cursor::new(row, usize::MAX)))))
+        .collect::<Vec<_>>();
+
+    let mut failure = None;
+    let mut succeeded = false;
+
+    for idx in 0..lines.len() {
+        let (start, _) = match lines[idx] {
+            Some(line) => line,
+            None => continue,
+        };
+
+        context.buffer.set_cursor(start);
+
+        let done = context.buffer.edits().len();
+
+        match run(context) {
+            Ok(()) => succeeded = true,
+            Err(message) => failure = Some(message),
+        }
+
+        for edit in context.buffer.edits().get(done..).unwrap_or_default() {
+            for line in &mut lines[idx + 1..] {
+                *line = line
+                    .filter(|&(start, end)| !edit.removes_line(start, end))
+                    .map(|(start, end)| edit.adjust_range(start, end));
+            }
+        }
+    }
+
+    context.buffer.set_record_edits(record);
+
+    match failure {
+        Some(message) if !succeeded => Err(message),
+        _ => Ok(()),
+    }
+}
+
+/// Runs normal mode commands on each line of a range, starting from the start of the line, as in
+/// `:%normal A;`.
+///
+/// Keys are written as for `Editor::feed`. A command still pending once the keys run out is
+/// cancelled, and insert mode is left as with `Esc`.
+fn normal(context: &mut Context, rows: RangeInclusive<usize>, args: &str) -> Result {
+    if args.is_empty() {
+        return Err("Argument required".to_owned());
+    }
+
+    let events = event::parse(args);
+
+    on_rows(context, rows, |context| {
+        let mode = events
+            .iter()
+            .fold(Normal::new() as Box<dyn Mode>, |mode, &event| mode.advance(context, event));
+
+        let _ = mode.advance(context, Event::Key(Key::Esc, Modifiers::NONE));
+        Ok(())
+    })
+}
+
+/// Shifts a range of lines left by one level of indentation, as in `:<`, or by one more for each
+/// extra `<`, as in `:<<`.
+fn shift_left(context: &mut Context, rows: RangeInclusive<usize>, args: &str) -> Result {
+    shift(context, rows, args, false)
+}
+
+/// Shifts a range of lines right by one level of indentation, as in `:>`, or by one more for each
+/// extra `>`, as in `:>>`.
+fn shift_right(context: &mut Context, rows: RangeInclusive<usize>, args: &str) -> Result {
+    shift(context, rows, args, true)
+}
+
+/// Shifts a range of lines, skipping blank ones, and leaves the cursor on the first non-blank of
+/// the last line.
+fn shift(context: &mut Context, rows: RangeInclusive<usize>, args: &str, right: bool) -> Result {
+    let key = if right { '>' } else { '<' };
+
+    if !args.chars().all(|ch| ch == key) {
+        return Err(format!("Trailing characters: {}", args));
+    }
+
+    let buffer = &mut context.buffer;
+
+    for _ in 0..=args.len() {
+        for row in rows.clone() {
+            if !buffer.is_blank_line(row) {
+                buffer.shift(row, right);
+            }
+        }
+    }
+
+    first_non_blank(buffer, *rows.end());
+    Ok(())
+}
+
+/// Writes the buffer to a file, as in `:write [path]`.
+///
+/// Without a path, writes to the file associated with the buffer. Otherwise, associates the buffer
+/// with the path if it had no file yet.
+///
+/// Warns when the associated file was changed by something else since it was last read or
+/// written, although it is overwritten all the same.
+#[cfg(feature = "io")]
+fn write(context: &mut Context, args: &str) -> Result {
+    let buffer = &mut context.buffer;
+
+    let path = match (args, buffer.path()) {
+        ("", Some(path)) => path.to_owned(),
+        ("", None) => return Err("No file name".to_owned()),
+        (path, _) => path.into(),
+    };
+
+    let own = buffer.path().map_or(true, |own| own == path);
+
+    let changed = match (own, buffer.file_hash(), fs::read_to_string(&path)) {
+        (true, Some(hash), Ok(text)) => Buffer::from(text.as_str()).content_hash() != hash,
+        _ => false,
+    };
+
+    let text = buffer.to_string_with_endings();
+    fs::write(&path, &text).map_err(|err| err.to_string())?;
+
+    if own {
+        buffer.set_file_hash(Buffer::from(text.as_str()).content_hash());
+        buffer.set_path(path);
+    }
+
+    if changed {
+        context.message = Some("Warning: the file was changed since reading it".to_owned());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "io")]
+    use std::fs;
+    use std::time::Duration;
+
+    use crate::{Cursor, Editor, Event, Key, Modifiers};
+    #[cfg(feature = "io")]
+    use crate::{LineEnding, Sign};
+
+    fn run(editor: &mut Editor, line: &str) {
+        let events = std::iter::once(':')
+            .chain(line.chars())
+            .chain(std::iter::once('\n'))
+            .map(|ch| Event::Key(Key::Char(ch), Modifiers::NONE))
+            .collect::<Vec<_>>();
+
+        editor.advance(&events);
+    }
+
+    #[test]
+    fn test_call() {
+        let mut editor = Editor::new();
+
+        editor.define("greet", |context| {
+            context.buffer.edit("hello", ..);
+            Ok(())
+        });
+
+        run(&mut editor, "call greet");
+        assert_eq!(editor.buffer().to_string(), "hello");
+        assert_eq!(editor.message(), None);
+
+        run(&mut editor, "call missing");
+        assert_eq!(editor.message(), Some("Unknown function: missing"));
+    }
+
+    #[test]
+    fn test_set_modifiable() {
+        let mut editor = Editor::with_text("foo");
+
+        run(&mut editor, "set noma");
+        editor.feed("ibar<Esc>");
+        assert_eq!(editor.buffer().to_string(), "foo");
+        assert_eq!(editor.mode(), "Normal");
+
+        run(&mut editor, "set modifiable");
+        editor.feed("ibar<Esc>");
+        assert_eq!(editor.buffer().to_string(), "barfoo");
+    }
+
+    #[test]
+    fn test_iabbrev() {
+        let mut editor = Editor::new();
+
+        run(&mut editor, "iab teh the");
+        editor.feed("iteh teh, tehx<Esc>");
+        assert_eq!(editor.buffer().to_string(), "the the, tehx");
+
+        run(&mut editor, "iabbrev teh");
+        assert_eq!(editor.message(), Some("Argument required"));
+
+        run(&mut editor, "iabbrev a-b c");
+        assert_eq!(editor.message(), Some("Invalid argument: a-b"));
+    }
+
+    #[test]
+    fn test_lua() {
+        let mut editor = Editor::new();
+
+        run(&mut editor, "lua buffer:insert('hello') buffer:set_cursor(0, 1)");
+        assert_eq!(editor.buffer().to_string(), "hello");
+        assert_eq!(editor.cursor(), Cursor::new(0, 1));
+        assert_eq!(editor.mode(), "Normal");
+
+        run(&mut editor, "lua error('oops')");
+        assert!(editor.message().map_or(false, |message| message.contains("oops")));
+        assert_eq!(editor.mode(), "Normal");
+    }
+
+    #[test]
+    fn test_lua_jump() {
+        let mut editor = Editor::new();
+
+        run(&mut editor, "lua buffer:insert('foo bar baz') buffer:set_cursor(0, 0)");
+        run(&mut editor, "luaj local r, c = buffer:next('word', buffer:cursor()) return r, c + 1");
+        assert_eq!(editor.cursor(), Cursor::new(0, 5));
+
+        run(&mut editor, "luaj return buffer:prev('word', buffer:cursor())");
+        assert_eq!(editor.cursor(), Cursor::new(0, 4));
+
+        run(&mut editor, "luaj return buffer:next('end', 0, 9)");
+        assert_eq!(editor.cursor(), Cursor::new(0, 10));
+
+        run(&mut editor, "luaj return buffer:next('word', 0, 9)");
+        assert_eq!(editor.cursor(), Cursor::new(0, 10));
+        assert_eq!(editor.message(), None);
+    }
+
+    #[test]
+    fn test_complete() {
+        let mut editor = Editor::new();
+
+        editor.feed(":se<Tab>");
+        assert_eq!(editor.input().as_deref(), Some("set"));
+
+        editor.feed(" ff<Tab>");
+        assert_eq!(editor.input().as_deref(), Some("set ff"));
+    }
+
+    #[test]
+    fn test_complete_cycle() {
+        let mut editor = Editor::new();
+
+        editor.feed(":lu<Tab>");
+        assert_eq!(editor.input().as_deref(), Some("lua"));
+
+        editor.feed("<Tab>");
+        assert_eq!(editor.input().as_deref(), Some("luajump"));
+
+        editor.feed("<Tab>");
+        assert_eq!(editor.input().as_deref(), Some("lu"));
+
+        editor.feed("<Tab><Tab><BS><Tab>");
+        assert_eq!(editor.input().as_deref(), Some("luajump"));
+    }
+
+    #[test]
+    #[cfg(feature = "io")]
+    fn test_write_preserves_line_endings() {
+        let name = format!("six-{}-write-preserves-line-endings.txt", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        fs::write(&path, "foo\r\nbar\r\n").unwrap();
+
+        let mut editor = Editor::open(&path).unwrap();
+        assert_eq!(editor.buffer().line_ending(), LineEnding::Dos);
+        assert_eq!(editor.buffer().to_string(), "foo\nbar\n");
+
+        run(&mut editor, "w");
+        assert_eq!(editor.message(), None);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "foo\r\nbar\r\n");
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "io")]
+    fn test_set_fileformat() {
+        let name = format!("six-{}-set-fileformat.txt", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        fs::write(&path, "foo\nbar\n").unwrap();
+
+        let mut editor = Editor::open(&path).unwrap();
+        assert_eq!(editor.buffer().line_ending(), LineEnding::Unix);
+
+        run(&mut editor, "set fileformat=dos");
+        run(&mut editor, "w");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "foo\r\nbar\r\n");
+
+        run(&mut editor, "set ff=mac");
+        assert_eq!(editor.message(), Some("Invalid argument: mac"));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_set_tabs() {
+        let mut editor = Editor::default();
+
+        run(&mut editor, "set et ts=4 sw=2 sts=2");
+        assert!(editor.buffer().expand_tab());
+        assert_eq!(editor.buffer().tab_stop(), 4);
+        assert_eq!(editor.buffer().shift_width(), 2);
+        assert_eq!(editor.buffer().soft_tab_stop(), 2);
+
+        run(&mut editor, "set noexpandtab");
+        assert!(!editor.buffer().expand_tab());
+
+        run(&mut editor, "set tabstop=0");
+        assert_eq!(editor.message(), Some("Invalid argument: 0"));
+        assert_eq!(editor.buffer().tab_stop(), 4);
+
+        run(&mut editor, "set tw=72");
+        assert_eq!(editor.buffer().text_width(), 72);
+
+        run(&mut editor, "set comment=#");
+        assert_eq!(editor.buffer().comment(), "#");
+    }
+
+    #[test]
+    fn test_set_options() {
+        let mut editor = Editor::default();
+
+        run(&mut editor, "set ic so=5");
+        assert!(editor.options().ignore_case);
+        assert_eq!(editor.options().scroll_off, 5);
+
+        run(&mut editor, "set ignorecase? scrolloff? rnu?");
+        assert_eq!(editor.message(), Some("ignorecase scrolloff=5 norelativenumber"));
+
+        run(&mut editor, "set noignorecase tm=200 et");
+        assert!(!editor.options().ignore_case);
+        assert_eq!(editor.options().timeout_len, Duration::from_millis(200));
+
+        run(&mut editor, "set ic? tm? expandtab? ff?");
+        assert_eq!(editor.message(), Some("noignorecase timeoutlen=200 expandtab fileformat=unix"));
+
+        run(&mut editor, "set so=x");
+        assert_eq!(editor.message(), Some("Invalid argument: x"));
+
+        run(&mut editor, "set nothing?");
+        assert_eq!(editor.message(), Some("Unknown option: nothing"));
+    }
+
+    #[test]
+    fn test_ignore_case() {
+        let mut editor = Editor::with_text("Foo foo\nFOO");
+
+        run(&mut editor, "s/foo/x/g");
+        assert_eq!(editor.buffer().to_string(), "Foo x\nFOO");
+
+        run(&mut editor, "set ic");
+        run(&mut editor, "s/foo/x/g");
+        assert_eq!(editor.buffer().to_string(), "x x\nFOO");
+
+        let mut editor = Editor::with_text("Foo\nbar\nFOO");
+
+        run(&mut editor, "set ignorecase");
+        run(&mut editor, "g/fOo/d");
+        assert_eq!(editor.buffer().to_string(), "bar");
+    }
+
+    #[test]
+    fn test_substitute() {
+        let mut editor = Editor::with_text("  foo foo\nbar\nfoo foo");
+
+        run(&mut editor, "s/foo/baz/");
+        assert_eq!(editor.buffer().to_string(), "  baz foo\nbar\nfoo foo");
+        assert_eq!(editor.cursor(), Cursor::new(0, 2));
+
+        editor.feed("jj&");
+        assert_eq!(editor.buffer().to_string(), "  baz foo\nbar\nbaz foo");
+
+        run(&mut editor, "s#o#0#g");
+        assert_eq!(editor.buffer().to_string(), "  baz foo\nbar\nbaz f00");
+
+        editor.feed("gg");
+        run(&mut editor, "s//0/");
+        assert_eq!(editor.buffer().to_string(), "  baz f0o\nbar\nbaz f00");
+
+        editor.feed("j");
+        run(&mut editor, "&&");
+        assert_eq!(editor.message(), Some("Pattern not found: o"));
+
+        run(&mut editor, "s/a/o/g");
+        editor.feed("g&");
+        assert_eq!(editor.buffer().to_string(), "  boz f0o\nbor\nboz f00");
+        assert_eq!(editor.cursor(), Cursor::new(2, 0));
+    }
+
+    #[test]
+    fn test_substitute_errors() {
+        let mut editor = Editor::with_text("foo");
+
+        editor.feed("&");
+        assert_eq!(editor.message(), Some("No previous substitute"));
+
+        run(&mut editor, "s//bar/");
+        assert_eq!(editor.message(), Some("No previous pattern"));
+
+        run(&mut editor, "s/foo/bar/x");
+        assert_eq!(editor.message(), Some("Trailing characters: x"));
+
+        run(&mut editor, "sfoo");
+        assert_eq!(editor.message(), Some("Not an editor command: sfoo"));
+
+        run(&mut editor, "s foo");
+        assert_eq!(editor.message(), Some("Invalid argument: foo"));
+        assert_eq!(editor.buffer().to_string(), "foo");
+    }
+
+    #[test]
+    fn test_range() {
+        let mut editor = Editor::with_text("foo\n  foo\nfoo\nfoo");
+
+        run(&mut editor, "%s/foo/bar/");
+        assert_eq!(editor.buffer().to_string(), "bar\n  bar\nbar\nbar");
+        assert_eq!(editor.cursor(), Cursor::new(3, 0));
+
+        run(&mut editor, "2,3s/bar/baz/");
+        assert_eq!(editor.buffer().to_string(), "bar\n  baz\nbaz\nbar");
+        assert_eq!(editor.cursor(), Cursor::new(2, 0));
+
+        run(&mut editor, ".-1,.s/baz/qux/");
+        assert_eq!(editor.buffer().to_string(), "bar\n  qux\nqux\nbar");
+
+        run(&mut editor, "2");
+        assert_eq!(editor.cursor(), Cursor::new(1, 2));
+
+        run(&mut editor, "$");
+        assert_eq!(editor.cursor(), Cursor::new(3, 0));
+
+        run(&mut editor, "1,9s/bar/baz/");
+        assert_eq!(editor.message(), Some("Invalid range"));
+
+        run(&mut editor, "2,3>");
+        assert_eq!(editor.buffer().to_string(), "bar\n\t  qux\n\tqux\nbar");
+        assert_eq!(editor.cursor(), Cursor::new(2, 1));
+
+        run(&mut editor, "%<<");
+        assert_eq!(editor.buffer().to_string(), "bar\nqux\nqux\nbar");
+
+        run(&mut editor, ">x");
+        assert_eq!(editor.message(), Some("Trailing characters: x"));
+
+        run(&mut editor, "%set ic");
+        assert_eq!(editor.message(), Some("No range allowed"));
+        assert_eq!(editor.buffer().to_string(), "bar\nqux\nqux\nbar");
+    }
+
+    #[test]
+    fn test_delete_yank() {
+        let mut editor = Editor::with_text("foo\n  bar\nbaz\nqux");
+
+        run(&mut editor, "1,2y a");
+        assert_eq!(editor.cursor(), Cursor::new(0, 0));
+
+        run(&mut editor, "2d");
+        assert_eq!(editor.buffer().to_string(), "foo\nbaz\nqux");
+        assert_eq!(editor.cursor(), Cursor::new(1, 0));
+
+        editor.feed("p");
+        assert_eq!(editor.buffer().to_string(), "foo\nbaz\n  bar\nqux");
+
+        editor.feed("G\"ap");
+        assert_eq!(editor.buffer().to_string(), "foo\nbaz\n  bar\nqux\nfoo\n  bar");
+
+        run(&mut editor, "3,$d");
+        assert_eq!(editor.buffer().to_string(), "foo\nbaz");
+        assert_eq!(editor.cursor(), Cursor::new(1, 0));
+
+        run(&mut editor, "%d");
+        assert_eq!(editor.buffer().to_string(), "");
+
+        run(&mut editor, "d ab");
+        assert_eq!(editor.message(), Some("Trailing characters: ab"));
+    }
+
+    #[test]
+    fn test_move() {
+        let mut editor = Editor::with_text("a\nb\nc\nd\ne");
+
+        run(&mut editor, "1,2m4");
+        assert_eq!(editor.buffer().to_string(), "c\nd\na\nb\ne");
+        assert_eq!(editor.cursor(), Cursor::new(3, 0));
+
+        run(&mut editor, "m0");
+        assert_eq!(editor.buffer().to_string(), "b\nc\nd\na\ne");
+        assert_eq!(editor.cursor(), Cursor::new(0, 0));
+
+        run(&mut editor, "2,3m$");
+        assert_eq!(editor.buffer().to_string(), "b\na\ne\nc\nd");
+
+        run(&mut editor, "1,3m2");
+        assert_eq!(editor.message(), Some("Cannot move a range of lines into itself"));
+
+        run(&mut editor, "m9");
+        assert_eq!(editor.message(), Some("Invalid address"));
+        assert_eq!(editor.buffer().to_string(), "b\na\ne\nc\nd");
+    }
+
+    #[test]
+    fn test_copy() {
+        let mut editor = Editor::with_text("foo\n  bar\nbaz");
+
+        run(&mut editor, "2t0");
+        assert_eq!(editor.buffer().to_string(), "  bar\nfoo\n  bar\nbaz");
+        assert_eq!(editor.cursor(), Cursor::new(0, 2));
+
+        run(&mut editor, "1,2co$");
+        assert_eq!(editor.buffer().to_string(), "  bar\nfoo\n  bar\nbaz\n  bar\nfoo");
+        assert_eq!(editor.cursor(), Cursor::new(5, 0));
+
+        run(&mut editor, "t.x");
+        assert_eq!(editor.message(), Some("Trailing characters: x"));
+    }
+
+    #[test]
+    fn test_global() {
+        let mut editor = Editor::with_text("foo 1\nbar 2\nfoo 3\nfoo 4\nbaz 5");
+
+        run(&mut editor, "g/foo/d");
+        assert_eq!(editor.buffer().to_string(), "bar 2\nbaz 5");
+
+        let mut editor = Editor::with_text("foo 1\nbar 2\nfoo 3\nfoo 4\nbaz 5");
+
+        run(&mut editor, "v/foo/d");
+        assert_eq!(editor.buffer().to_string(), "foo 1\nfoo 3\nfoo 4");
+
+        run(&mut editor, "2,$g!/3/d");
+        assert_eq!(editor.buffer().to_string(), "foo 1\nfoo 3");
+
+        run(&mut editor, "g/o/s/o/0/g");
+        assert_eq!(editor.buffer().to_string(), "f00 1\nf00 3");
+        assert_eq!(editor.cursor(), Cursor::new(1, 0));
+    }
+
+    #[test]
+    fn test_global_failures() {
+        let mut editor = Editor::with_text("foo 1\nfoo 3\nfoo 1");
+
+        run(&mut editor, "g/foo/s/1/one/");
+        assert_eq!(editor.buffer().to_string(), "foo one\nfoo 3\nfoo one");
+        assert_eq!(editor.message(), None);
+
+        run(&mut editor, "g/foo/s/2/two/");
+        assert_eq!(editor.message(), Some("Pattern not found: 2"));
+    }
+
+    #[test]
+    fn test_global_deleted_lines() {
+        let mut editor = Editor::with_text("foo 1\nfoo 2\nbar 3\nfoo 4\nbar 5");
+
+        run(&mut editor, "g/foo/.,+1d");
+        assert_eq!(editor.buffer().to_string(), "bar 3");
+
+        run(&mut editor, "g/foo/d");
+        assert_eq!(editor.message(), Some("Pattern not found: foo"));
+
+        run(&mut editor, "g/bar/");
+        assert_eq!(editor.message(), Some("Argument required"));
+    }
+
+    #[test]
+    fn test_normal() {
+        let mut editor = Editor::with_text("foo\n  bar\nbaz");
+
+        run(&mut editor, "%normal A;");
+        assert_eq!(editor.buffer().to_string(), "foo;\n  bar;\nbaz;");
+        assert_eq!(editor.mode(), "Normal");
+
+        run(&mut editor, "1,2norm ^i<lt>");
+        assert_eq!(editor.buffer().to_string(), "<foo;\n  <bar;\nbaz;");
+
+        run(&mut editor, "g/bar/norm ddp");
+        assert_eq!(editor.buffer().to_string(), "<foo;\nbaz;\n  <bar;");
+
+        run(&mut editor, "norm");
+        assert_eq!(editor.message(), Some("Argument required"));
+    }
+
+    #[test]
+    #[cfg(feature = "io")]
+    fn test_write_changed_file() {
+        let name = format!("six-{}-write-changed-file.txt", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        fs::write(&path, "foo\n").unwrap();
+
+        let mut editor = Editor::open(&path).unwrap();
+
+        run(&mut editor, "w");
+        assert_eq!(editor.message(), None);
+
+        fs::write(&path, "bar\n").unwrap();
+        run(&mut editor, "w");
+        assert_eq!(editor.message(), Some("Warning: the file was changed since reading it"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "foo\n");
+
+        run(&mut editor, "w");
+        assert_eq!(editor.message(), None);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "io")]
+    fn test_write_adds_end_of_line() {
+        let name = format!("six-{}-write-adds-end-of-line.txt", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        fs::write(&path, "foo\nbar").unwrap();
+
+        let mut editor = Editor::open(&path).unwrap();
+        assert_eq!(editor.buffer().content().len(), 2);
+
+        run(&mut editor, "w");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "foo\nbar\n");
+        assert_eq!(editor.buffer().content().len(), 2);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "io")]
+    fn test_write_trims_end_of_line() {
+        let name = format!("six-{}-write-trims-end-of-line.txt", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        fs::write(&path, "foo\nbar\n\n").unwrap();
+
+        let mut editor = Editor::open(&path).unwrap();
+
+        run(&mut editor, "w");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "foo\nbar\n");
+        assert_eq!(editor.buffer().content().len(), 4);
+
+        run(&mut editor, "set nofixeol");
+        run(&mut editor, "w");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "foo\nbar\n\n");
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "io")]
+    fn test_edit() {
+        let name = format!("six-{}-edit.txt", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        fs::write(&path, "foo\nbar").unwrap();
+
+        let mut editor = Editor::new();
+
+        run(&mut editor, &format!("e {}", path.display()));
+        assert_eq!(editor.message(), None);
+        assert_eq!(editor.buffer().to_string(), "foo\nbar");
+        assert_eq!(editor.buffer().path(), Some(path.as_path()));
+
+        editor.set_sign(1, Sign('>'));
+        editor.feed("zfj");
+        assert_eq!(editor.folds().iter().count(), 1);
+        editor.feed("v<Esc>");
+        editor.feed("ix<Esc>");
+
+        run(&mut editor, "e");
+        assert_eq!(editor.message(), Some("No write since last change (add ! to override)"));
+        assert_eq!(editor.buffer().to_string(), "xfoo\nbar");
+
+        run(&mut editor, "e!");
+        assert_eq!(editor.message(), None);
+        assert_eq!(editor.buffer().to_string(), "foo\nbar");
+        assert_eq!(editor.folds().iter().count(), 0);
+        assert!(editor.signs().is_empty());
+
+        editor.feed("gv");
+        assert_eq!(editor.mode(), "Normal");
+
+        run(&mut editor, "set et sw=4 autopairs");
+        run(&mut editor, "e");
+        assert!(editor.buffer().expand_tab());
+        assert_eq!(editor.buffer().shift_width(), 4);
+        assert!(editor.buffer().auto_pairs());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "io")]
+    fn test_complete_path() {
+        let name = format!("six-{}-complete-path", std::process::id());
+        let dir = std::env::temp_dir().join(name);
+        fs::create_dir_all(dir.join("beta")).unwrap();
+        fs::write(dir.join("alpha.txt"), "").unwrap();
+        fs::write(dir.join("alps.txt"), "").unwrap();
+
+        let dir = format!("{}{}", dir.display(), std::path::MAIN_SEPARATOR);
+        let mut editor = Editor::new();
+
+        editor.feed(&format!(":e {}al<Tab>", dir));
+        assert_eq!(editor.input(), Some(format!("e {}alpha.txt", dir)));
+
+        editor.feed("<Tab>");
+        assert_eq!(editor.input(), Some(format!("e {}alps.txt", dir)));
+
+        editor.feed(&format!("<Esc>:e! {}alp<Tab>", dir));
+        assert_eq!(editor.input(), Some(format!("e! {}alpha.txt", dir)));
+
+        editor.feed("<Esc>");
+        editor.feed(&format!(":w {}b<Tab>", dir));
+        assert_eq!(editor.input(), Some(format!("w {}beta{}", dir, std::path::MAIN_SEPARATOR)));
+
+        editor.feed("<Esc>:set ff=<Tab>");
+        assert_eq!(editor.input().as_deref(), Some("set ff="));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
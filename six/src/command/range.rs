@@ -0,0 +1,196 @@
+use std::convert::TryFrom;
+use std::ops::RangeInclusive;
+
+/// The line an address counts from.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Base {
+    /// A line number, counting from 1, as in `5`, where 0 stands for the position before the
+    /// first line.
+    Number(usize),
+
+    /// The line of the cursor, as in `.`, or as implied by a lone offset, as in `+2`.
+    Current,
+
+    /// The last line, as in `$`.
+    Last,
+}
+
+/// A line given to an ex command, as in `5`, `.` or `$-1`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Address {
+    /// The line the address counts from.
+    pub base: Base,
+
+    /// The number of lines to move from the base, as in `+2` or `-1`.
+    pub offset: isize,
+}
+
+/// The lines given to an ex command, as in `1,5` or `%`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Range {
+    /// The first line.
+    pub start: Address,
+
+    /// The last line.
+    pub end: Address,
+}
+
+impl Address {
+    /// Parses an address at the start of some text, returning it along with the rest of the text.
+    ///
+    /// Offsets follow a base, as in `.+2` or `$-1`, where a sign without a number moves by one
+    /// line, and several of them add up. Without a base, they count from the line of the cursor.
+    ///
+    /// Returns `None` if there is no address, or if its offsets add up past the range of `isize`.
+    #[must_use]
+    pub fn parse(text: &str) -> Option<(Self, &str)> {
+        let digits = |text: &str| text.find(|ch: char| !ch.is_ascii_digit()).unwrap_or(text.len());
+
+        let (base, mut rest) = match text.chars().next() {
+            Some('.') => (Some(Base::Current), &text[1..]),
+            Some('$') => (Some(Base::Last), &text[1..]),
+            Some(ch) if ch.is_ascii_digit() => {
+                let len = digits(text);
+                (Some(Base::Number(text[..len].parse().ok()?)), &text[len..])
+            },
+
+            _ => (None, text),
+        };
+
+        let mut offset: Option<isize> = None;
+
+        while let Some(sign) = rest.chars().next().filter(|&ch| ch == '+' || ch == '-') {
+            let len = digits(&rest[1..]);
+            let lines = if len > 0 { rest[1..=len].parse::<isize>().ok()? } else { 1 };
+
+            let lines = if sign == '+' { lines } else { -lines };
+
+            offset = Some(offset.unwrap_or(0).checked_add(lines)?);
+            rest = &rest[1 + len..];
+        }
+
+        if base.is_none() && offset.is_none() {
+            return None;
+        }
+
+        Some((Self { base: base.unwrap_or(Base::Current), offset: offset.unwrap_or(0) }, rest))
+    }
+
+    /// Returns the line number of the address, counting from 1, given the row of the cursor and the
+    /// number of lines, or `None` if it is past the last line or before 0.
+    #[must_use]
+    pub fn resolve(self, row: usize, len: usize) -> Option<usize> {
+        let base = match self.base {
+            Base::Number(line) => line,
+            Base::Current => row + 1,
+            Base::Last => len,
+        };
+
+        let offset = usize::try_from(self.offset.checked_abs()?).ok()?;
+
+        let line =
+            if self.offset < 0 { base.checked_sub(offset)? } else { base.checked_add(offset)? };
+
+        Some(line).filter(|&line| line <= len)
+    }
+}
+
+impl Range {
+    /// Parses a range at the start of a command line, returning it, if there is one, along with
+    /// the rest of the line.
+    ///
+    /// A range is either `%`, for every line, a single address, or two addresses separated by a
+    /// comma, where a missing one stands for the line of the cursor, as in `,$`.
+    #[must_use]
+    pub fn parse(line: &str) -> (Option<Self>, &str) {
+        let current = Address { base: Base::Current, offset: 0 };
+
+        if let Some(rest) = line.strip_prefix('%') {
+            let start = Address { base: Base::Number(1), offset: 0 };
+            let end = Address { base: Base::Last, offset: 0 };
+
+            return (Some(Self { start, end }), rest);
+        }
+
+        let (start, rest) = match Address::parse(line) {
+            Some((start, rest)) => (Some(start), rest),
+            None => (None, line),
+        };
+
+        match rest.strip_prefix(',') {
+            Some(rest) => {
+                let (end, rest) = Address::parse(rest).unwrap_or((current, rest));
+                (Some(Self { start: start.unwrap_or(current), end }), rest)
+            },
+
+            None => (start.map(|start| Self { start, end: start }), rest),
+        }
+    }
+
+    /// Returns the rows of the range, counting from 0, given the row of the cursor and the number
+    /// of lines.
+    ///
+    /// Line 0 stands for the first line, and a range given backwards is swapped.
+    ///
+    /// # Errors
+    ///
+    /// Fails if either end is outside of the buffer.
+    pub fn resolve(self, row: usize, len: usize) -> Result<RangeInclusive<usize>, String> {
+        let start = self.start.resolve(row, len).ok_or_else(|| "Invalid range".to_owned())?;
+        let end = self.end.resolve(row, len).ok_or_else(|| "Invalid range".to_owned())?;
+
+        let (start, end) = (start.min(end).max(1), start.max(end).max(1));
+        Ok(start - 1..=end - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Address, Base, Range};
+
+    fn parse(line: &str) -> Option<Range> {
+        let (range, rest) = Range::parse(line);
+        assert_eq!(rest, "d");
+
+        range
+    }
+
+    #[test]
+    fn test_parse_address() {
+        let address = |base, offset| Address { base, offset };
+
+        assert_eq!(Address::parse("12d"), Some((address(Base::Number(12), 0), "d")));
+        assert_eq!(Address::parse(".d"), Some((address(Base::Current, 0), "d")));
+        assert_eq!(Address::parse("$-2d"), Some((address(Base::Last, -2), "d")));
+        assert_eq!(Address::parse("+3d"), Some((address(Base::Current, 3), "d")));
+        assert_eq!(Address::parse("-d"), Some((address(Base::Current, -1), "d")));
+        assert_eq!(Address::parse(".+1+1-3d"), Some((address(Base::Current, -1), "d")));
+        assert_eq!(Address::parse("d"), None);
+        assert_eq!(Address::parse("+9223372036854775807+9223372036854775807d"), None);
+        assert_eq!(Address::parse("-9223372036854775807-2d"), None);
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse("d"), None);
+        assert_eq!(parse("%d").map(|range| range.resolve(4, 10)), Some(Ok(0..=9)));
+        assert_eq!(parse("3d").map(|range| range.resolve(4, 10)), Some(Ok(2..=2)));
+        assert_eq!(parse("2,5d").map(|range| range.resolve(4, 10)), Some(Ok(1..=4)));
+        assert_eq!(parse(".,$d").map(|range| range.resolve(4, 10)), Some(Ok(4..=9)));
+        assert_eq!(parse(".-1,.+2d").map(|range| range.resolve(4, 10)), Some(Ok(3..=6)));
+        assert_eq!(parse(",+2d").map(|range| range.resolve(4, 10)), Some(Ok(4..=6)));
+        assert_eq!(parse("3,d").map(|range| range.resolve(4, 10)), Some(Ok(2..=4)));
+        assert_eq!(parse("$d").map(|range| range.resolve(4, 10)), Some(Ok(9..=9)));
+    }
+
+    #[test]
+    fn test_resolve() {
+        let range = |line| Range::parse(line).0.unwrap();
+
+        assert_eq!(range("5,2").resolve(0, 10), Ok(1..=4));
+        assert_eq!(range("0").resolve(0, 10), Ok(0..=0));
+        assert_eq!(range("11").resolve(0, 10), Err("Invalid range".to_owned()));
+        assert_eq!(range("-2").resolve(0, 10), Err("Invalid range".to_owned()));
+        assert_eq!(range("$+1").resolve(0, 10), Err("Invalid range".to_owned()));
+    }
+}
@@ -1,19 +1,77 @@
 mod cells;
+mod chars;
+mod find;
+mod head;
+mod line;
+mod object;
 mod paragraphs;
+mod tail;
 
 pub use cells::Cells;
+pub use chars::CharIndices;
+pub use find::Find;
+pub use head::Head;
+pub use line::Line;
+pub use object::{Object, Span};
 pub use paragraphs::Paragraphs;
+pub use tail::Tail;
+
+use crate::buffer::Row;
+use crate::Buffer;
 
 /// A text text coordinate.
+///
+/// Columns count characters, as in Unicode scalar values, rather than bytes or screen cells. See
+/// [`Buffer::cursor_to_byte`] and [`Buffer::display_col`] for those.
 #[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd, Eq, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Cursor {
     /// The vertical position of the cursor.
     row: usize,
 
-    /// The horizontal position of the cursor.
+    /// The horizontal position of the cursor, in characters from the start of the line.
     col: usize,
 }
 
+/// A unit of movement over a buffer.
+pub trait Metric {
+    /// Returns the first position of the metric after the specified position, if any.
+    fn after(cursor: Cursor, buffer: &Buffer) -> Option<Cursor>;
+
+    /// Returns the first position of the metric before the specified position, if any.
+    fn before(cursor: Cursor, buffer: &Buffer) -> Option<Cursor>;
+}
+
+/// The class of a character, for the purposes of word motions.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Class {
+    /// Whitespace, including line breaks.
+    Blank,
+
+    /// Characters that are part of keywords.
+    Keyword,
+
+    /// Anything else.
+    Punctuation,
+}
+
+impl Class {
+    /// Returns the class of a character, where no character counts as whitespace.
+    fn of(ch: Option<char>, buffer: &Buffer) -> Self {
+        match ch {
+            Some(ch) if ch.is_whitespace() => Self::Blank,
+            Some(ch) if buffer.is_keyword(ch) => Self::Keyword,
+            Some(_) => Self::Punctuation,
+            None => Self::Blank,
+        }
+    }
+
+    /// Returns the class of the character at the specified position.
+    fn at(cursor: Cursor, buffer: &Buffer) -> Self {
+        Self::of(buffer.get(cursor), buffer)
+    }
+}
+
 impl Cursor {
     /// Creates a new cursor at the specified position.
     #[inline]
@@ -42,4 +100,32 @@ impl Cursor {
     pub fn row(self) -> usize {
         self.row
     }
+
+    /// Moves the cursor onto the last character of its line if it is past it, as required outside
+    /// of insert mode.
+    ///
+    /// On an empty line, the cursor moves to its start.
+    #[must_use]
+    pub fn clamp_to_line(self, buffer: &Buffer) -> Self {
+        let cursor = buffer.clamp(self);
+        let len = buffer.line(cursor.row).map_or(0, Row::len);
+
+        Self::new(cursor.row, cursor.col.min(len.saturating_sub(1)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Buffer, Cursor};
+
+    #[test]
+    fn test_clamp_to_line() {
+        let buffer = Buffer::from("foo\n\nbar");
+
+        assert_eq!(Cursor::new(0, 3).clamp_to_line(&buffer), Cursor::new(0, 2));
+        assert_eq!(Cursor::new(0, 9).clamp_to_line(&buffer), Cursor::new(0, 2));
+        assert_eq!(Cursor::new(0, 1).clamp_to_line(&buffer), Cursor::new(0, 1));
+        assert_eq!(Cursor::new(1, 0).clamp_to_line(&buffer), Cursor::new(1, 0));
+        assert_eq!(Cursor::new(9, 9).clamp_to_line(&buffer), Cursor::new(2, 2));
+    }
 }
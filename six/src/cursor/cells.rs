@@ -1,3 +1,4 @@
+use crate::cursor::Metric;
 use crate::{Buffer, Cursor};
 
 /// An iterator over the Unicode codepoint boundaries of a buffer.
@@ -7,11 +8,22 @@ pub struct Cells<'a> {
 }
 
 impl<'a> Cells<'a> {
+    #[must_use]
     pub fn new(cursor: Cursor, buffer: &'a Buffer) -> Self {
         Self { cursor, buffer }
     }
 }
 
+impl Metric for Cells<'_> {
+    fn after(cursor: Cursor, buffer: &Buffer) -> Option<Cursor> {
+        Cells::new(cursor, buffer).next()
+    }
+
+    fn before(cursor: Cursor, buffer: &Buffer) -> Option<Cursor> {
+        Cells::new(cursor, buffer).next_back()
+    }
+}
+
 impl Iterator for Cells<'_> {
     type Item = Cursor;
 
@@ -19,6 +31,8 @@ impl Iterator for Cells<'_> {
         if self.cursor.col < self.buffer.line(self.cursor.row)?.len() {
             self.cursor.col += 1;
         } else {
+            self.buffer.line(self.cursor.row + 1)?;
+
             self.cursor.col = 0;
             self.cursor.row += 1;
         }
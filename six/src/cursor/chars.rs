@@ -0,0 +1,92 @@
+use crate::cursor::Cells;
+use crate::{Buffer, Cursor};
+
+/// An iterator over the characters of a buffer along with their positions.
+///
+/// Iterating forward yields the characters from the starting position onwards, while iterating
+/// backward yields the ones before it. Every row but the last ends with a line break, yielded as
+/// `'\n'`.
+pub struct CharIndices<'a> {
+    cursor: Cursor,
+    buffer: &'a Buffer,
+}
+
+impl<'a> CharIndices<'a> {
+    #[must_use]
+    pub fn new(cursor: Cursor, buffer: &'a Buffer) -> Self {
+        Self { cursor, buffer }
+    }
+
+    /// Returns the character at the specified position, including line breaks.
+    fn get(&self, cursor: Cursor) -> Option<char> {
+        self.buffer.get(cursor).or_else(|| {
+            let row = self.buffer.line(cursor.row())?;
+            self.buffer.line(cursor.row() + 1).filter(|_| cursor.col() == row.len()).map(|_| '\n')
+        })
+    }
+}
+
+impl Iterator for CharIndices<'_> {
+    type Item = (Cursor, char);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let cursor = self.cursor;
+        let ch = self.get(cursor)?;
+
+        self.cursor = Cells::new(cursor, self.buffer).next()?;
+        Some((cursor, ch))
+    }
+}
+
+impl DoubleEndedIterator for CharIndices<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let cursor = Cells::new(self.cursor, self.buffer).next_back()?;
+        let ch = self.get(cursor)?;
+
+        self.cursor = cursor;
+        Some((cursor, ch))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Buffer, Cursor};
+
+    const TEXT: &str = "foo\n\nbär baz\n";
+
+    #[test]
+    fn test_forward() {
+        let buffer = Buffer::from(TEXT);
+
+        let (cursors, chars): (Vec<_>, String) = buffer.char_indices_from(Cursor::origin()).unzip();
+
+        assert_eq!(chars, TEXT);
+
+        let expected = std::iter::once(Cursor::origin()).chain(buffer.cells(Cursor::origin()));
+        assert!(cursors.into_iter().eq(expected.take(TEXT.chars().count())));
+    }
+
+    #[test]
+    fn test_backward() {
+        let buffer = Buffer::from(TEXT);
+
+        let chars = buffer.char_indices_from(buffer.eof()).rev().map(|(_, ch)| ch);
+        assert!(chars.eq(TEXT.chars().rev()));
+    }
+
+    #[test]
+    fn test_from_middle() {
+        let buffer = Buffer::from(TEXT);
+        let cursor = Cursor::new(2, 1);
+
+        let after = buffer.char_indices_from(cursor).map(|(_, ch)| ch).collect::<String>();
+        let before = buffer.char_indices_from(cursor).rev().map(|(_, ch)| ch).collect::<String>();
+
+        assert_eq!(after, "är baz\n");
+        assert_eq!(before, "b\n\noof");
+        assert_eq!(buffer.char_indices_from(cursor).next_back(), Some((Cursor::new(2, 0), 'b')));
+
+        let eol = Cursor::new(0, 3);
+        assert_eq!(buffer.char_indices_from(eol).next(), Some((eol, '\n')));
+    }
+}
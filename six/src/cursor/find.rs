@@ -0,0 +1,59 @@
+use crate::{Buffer, Cursor};
+
+/// A search for a character within a line, as in `f`, `F`, `t` and `T`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Find {
+    /// The character to search for.
+    pub target: char,
+
+    /// Whether to search after the cursor, rather than before it.
+    pub forward: bool,
+
+    /// Whether to stop right before the match, rather than on it.
+    pub till: bool,
+}
+
+impl Find {
+    /// Returns the same search in the opposite direction.
+    #[must_use]
+    pub fn reversed(self) -> Self {
+        Self { forward: !self.forward, ..self }
+    }
+
+    /// Returns the position of the `count`-th match from the cursor, if any.
+    ///
+    /// When repeating a `till` search, a match right next to the cursor is skipped, since
+    /// otherwise the cursor would never move.
+    #[must_use]
+    pub fn locate(
+        self,
+        buffer: &Buffer,
+        cursor: Cursor,
+        count: usize,
+        repeat: bool,
+    ) -> Option<Cursor> {
+        let row = buffer.line(cursor.row())?;
+        let matches = |col: &usize| row.get(*col) == Some(self.target);
+
+        let skip = usize::from(self.till && repeat);
+        let mut col = cursor.col();
+
+        if self.forward {
+            col += skip;
+
+            for _ in 0..count {
+                col = (col + 1..row.len()).find(matches)?;
+            }
+
+            Some(Cursor::new(cursor.row(), if self.till { col - 1 } else { col }))
+        } else {
+            col = col.checked_sub(skip)?;
+
+            for _ in 0..count {
+                col = (0..col).rev().find(matches)?;
+            }
+
+            Some(Cursor::new(cursor.row(), if self.till { col + 1 } else { col }))
+        }
+    }
+}
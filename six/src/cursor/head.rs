@@ -1,30 +1,37 @@
-use crate::cursor::{Codepoint, Cursor, Iter};
+use crate::buffer::Row;
+use crate::cursor::{Cells, Class, Metric};
+use crate::{Buffer, Cursor};
 
+/// An iterator over the first characters of the words of a buffer.
 pub struct Head<'a> {
-    iter: Codepoint<'a>,
-    text: &'a str,
+    cells: Cells<'a>,
+    buffer: &'a Buffer,
 }
 
-fn is_word_head(cursor: Cursor, text: &str) -> bool {
-    if let Some(slice) = text.get(..=cursor.offset) {
-        let mut chars = slice.chars();
+/// Returns whether a word starts at the specified position.
+///
+/// Empty lines are considered words by themselves.
+fn is_word_head(cursor: Cursor, buffer: &Buffer) -> bool {
+    let p = Class::at(cursor, buffer);
+    let q = Class::of(buffer.char_indices_from(cursor).next_back().map(|(_, ch)| ch), buffer);
 
-        let p = chars.next_back();
-        let q = chars.next_back();
+    (p != Class::Blank && p != q) || buffer.line(cursor.row()).map_or(false, Row::is_empty)
+}
 
-        !p.map_or(true, char::is_whitespace) && q.map_or(true, char::is_whitespace)
-    } else {
-        false
+impl<'a> Head<'a> {
+    #[must_use]
+    pub fn new(cursor: Cursor, buffer: &'a Buffer) -> Self {
+        Self { buffer, cells: Cells::new(cursor, buffer) }
     }
 }
 
-impl<'a> Iter<'a> for Head<'a> {
-    fn new(cursor: Cursor, text: &'a str) -> Self {
-        Self { text, iter: Codepoint::new(cursor, text) }
+impl Metric for Head<'_> {
+    fn after(cursor: Cursor, buffer: &Buffer) -> Option<Cursor> {
+        Head::new(cursor, buffer).next()
     }
 
-    fn at(&self) -> Self::Item {
-        self.iter.at()
+    fn before(cursor: Cursor, buffer: &Buffer) -> Option<Cursor> {
+        Head::new(cursor, buffer).next_back()
     }
 }
 
@@ -33,15 +40,53 @@ impl Iterator for Head<'_> {
 
     /// Moves forward by a word unit.
     fn next(&mut self) -> Option<Self::Item> {
-        let text = self.text;
-        self.iter.find(|&cursor| is_word_head(cursor, text))
+        let buffer = self.buffer;
+        self.cells.find(|&cursor| is_word_head(cursor, buffer))
     }
 }
 
 impl DoubleEndedIterator for Head<'_> {
     /// Moves backward by a word unit.
     fn next_back(&mut self) -> Option<Self::Item> {
-        let text = self.text;
-        self.iter.rfind(|&cursor| is_word_head(cursor, text))
+        let buffer = self.buffer;
+        self.cells.rfind(|&cursor| is_word_head(cursor, buffer))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Head;
+    use crate::{Buffer, Cursor};
+
+    #[test]
+    fn test_leading_whitespace() {
+        let buffer = Buffer::from("  foo bar");
+
+        assert_eq!(Head::new(Cursor::origin(), &buffer).collect::<Vec<_>>(), vec![
+            Cursor::new(0, 2),
+            Cursor::new(0, 6),
+        ]);
+
+        assert_eq!(Head::new(buffer.eof(), &buffer).rev().collect::<Vec<_>>(), vec![
+            Cursor::new(0, 6),
+            Cursor::new(0, 2),
+        ]);
+    }
+
+    #[test]
+    fn test_line_breaks() {
+        let buffer = Buffer::from("foo\n  bar\n\nbaz");
+
+        assert_eq!(Head::new(Cursor::origin(), &buffer).collect::<Vec<_>>(), vec![
+            Cursor::new(1, 2),
+            Cursor::new(2, 0),
+            Cursor::new(3, 0),
+        ]);
+
+        assert_eq!(Head::new(Cursor::new(3, 0), &buffer).rev().collect::<Vec<_>>(), vec![
+            Cursor::new(2, 0),
+            Cursor::new(1, 2),
+            Cursor::new(0, 0),
+        ]);
     }
 }
@@ -1,18 +1,41 @@
-use crate::cursor::{Bounded, Cursor, Iter};
+use crate::cursor::Metric;
+use crate::{Buffer, Cursor};
 
+/// An iterator over the positions right above or below each other, as in `j` and `k`.
+///
+/// The screen column of the starting position is preserved across shorter lines, so moving
+/// through them and back onto a longer line restores it.
 pub struct Line<'a> {
     cursor: Cursor,
     column: usize,
-    text: &'a str,
+    buffer: &'a Buffer,
 }
 
-impl<'a> Iter<'a> for Line<'a> {
-    fn new(cursor: Cursor, text: &'a str) -> Self {
-        Self { text, cursor, column: cursor.to_col(text) }
+impl<'a> Line<'a> {
+    #[must_use]
+    pub fn new(cursor: Cursor, buffer: &'a Buffer) -> Self {
+        Self { cursor, column: buffer.display_col(cursor), buffer }
     }
 
-    fn at(&self) -> Self::Item {
-        self.cursor
+    /// Moves to the specified row, at the last position that doesn't go past the column.
+    fn visit(&mut self, row: usize) -> Option<Cursor> {
+        let line = self.buffer.line(row)?;
+        let col = (0..=line.len())
+            .take_while(|&col| self.buffer.display_col(Cursor::new(row, col)) <= self.column)
+            .last();
+
+        self.cursor = Cursor::new(row, col.unwrap_or(0));
+        Some(self.cursor)
+    }
+}
+
+impl Metric for Line<'_> {
+    fn after(cursor: Cursor, buffer: &Buffer) -> Option<Cursor> {
+        Line::new(cursor, buffer).next()
+    }
+
+    fn before(cursor: Cursor, buffer: &Buffer) -> Option<Cursor> {
+        Line::new(cursor, buffer).next_back()
     }
 }
 
@@ -20,30 +43,75 @@ impl Iterator for Line<'_> {
     type Item = Cursor;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.cursor.offset += self.text[self.cursor.offset..].find('\n')? + 1;
-
-        self.cursor = self
-            .cursor
-            .iter::<Bounded>(self.text)
-            .take_while(|cursor| cursor.to_col(self.text) <= self.column)
-            .last()
-            .unwrap_or(self.cursor);
-
-        Some(self.cursor)
+        self.visit(self.cursor.row() + 1)
     }
 }
 
 impl DoubleEndedIterator for Line<'_> {
     fn next_back(&mut self) -> Option<Self::Item> {
-        self.cursor.offset = self.text[..self.cursor.offset].rfind('\n')?;
+        self.visit(self.cursor.row().checked_sub(1)?)
+    }
+}
 
-        self.cursor = self
-            .cursor
-            .iter::<Bounded>(self.text)
-            .rev()
-            .find(|cursor| cursor.to_col(self.text) == self.column)
-            .unwrap_or(self.cursor);
+#[cfg(test)]
+mod tests {
+    use super::Line;
+    use crate::{Buffer, Cursor};
 
-        Some(self.cursor)
+    #[test]
+    fn test_iter() {
+        let buffer = Buffer::from("lorem ipsum\ndolor\n\nsit amet");
+        let cursor = Cursor::new(0, 7);
+
+        assert_eq!(Line::new(cursor, &buffer).collect::<Vec<_>>(), vec![
+            Cursor::new(1, 5),
+            Cursor::new(2, 0),
+            Cursor::new(3, 7),
+        ]);
+
+        assert_eq!(Line::new(Cursor::new(3, 7), &buffer).rev().collect::<Vec<_>>(), vec![
+            Cursor::new(2, 0),
+            Cursor::new(1, 5),
+            Cursor::new(0, 7),
+        ]);
+    }
+
+    #[test]
+    fn test_preserves_column() {
+        let buffer = Buffer::from("lorem ipsum\ndolor");
+        let mut line = Line::new(Cursor::new(0, 9), &buffer);
+
+        assert_eq!(line.next(), Some(Cursor::new(1, 5)));
+        assert_eq!(line.next_back(), Some(Cursor::new(0, 9)));
+    }
+
+    #[test]
+    fn test_wide_characters() {
+        let buffer = Buffer::from("abcdef\n日本語x\nabcdef");
+        let mut line = Line::new(Cursor::new(0, 3), &buffer);
+
+        assert_eq!(line.next(), Some(Cursor::new(1, 1)));
+        assert_eq!(line.next(), Some(Cursor::new(2, 3)));
+
+        assert_eq!(Line::new(Cursor::new(1, 3), &buffer).next(), Some(Cursor::new(2, 6)));
+        assert_eq!(Line::new(Cursor::new(1, 2), &buffer).next_back(), Some(Cursor::new(0, 4)));
+    }
+
+    #[test]
+    fn test_tabs() {
+        let buffer = Buffer::from("\tfoo\nabcdefghij");
+
+        assert_eq!(Line::new(Cursor::new(0, 1), &buffer).next(), Some(Cursor::new(1, 8)));
+        assert_eq!(Line::new(Cursor::new(1, 3), &buffer).next_back(), Some(Cursor::new(0, 0)));
+        assert_eq!(Line::new(Cursor::new(1, 9), &buffer).next_back(), Some(Cursor::new(0, 2)));
+    }
+
+    #[test]
+    fn test_bounds() {
+        let buffer = Buffer::from("foo\nbar");
+
+        assert_eq!(Line::new(Cursor::new(0, 1), &buffer).next_back(), None);
+        assert_eq!(Line::new(Cursor::new(1, 1), &buffer).next(), None);
+        assert_eq!(Line::new(Cursor::new(0, 1), &Buffer::default()).next(), None);
     }
 }
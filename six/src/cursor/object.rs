@@ -0,0 +1,410 @@
+use std::ops::RangeInclusive;
+
+use crate::cursor::{Class, Cursor};
+use crate::Buffer;
+
+/// A text object, as selected by `iw`, `a(`, `it`, `ip` or `ae`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Object {
+    /// A run of characters of the same class, as in `iw`.
+    Word,
+
+    /// The text between a pair of brackets, as in `i(`.
+    Bracket(char, char),
+
+    /// The text between a pair of matching markup tags, as in `it`.
+    Tag,
+
+    /// A run of lines that are all blank or all not blank, as in `ip`.
+    Paragraph,
+
+    /// The whole buffer, as in `ae`.
+    Entire,
+}
+
+/// The text covered by a text object.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Span {
+    /// The characters from the first position to the last one, both included.
+    Chars(Cursor, Cursor),
+
+    /// The whole lines from the first row to the last one, both included.
+    Lines(usize, usize),
+}
+
+impl Object {
+    /// Returns the text object named by a key, as the `w` in `iw`.
+    #[must_use]
+    pub fn from_char(ch: char) -> Option<Self> {
+        let object = match ch {
+            'w' => Self::Word,
+            'p' => Self::Paragraph,
+            'e' => Self::Entire,
+            '(' | ')' | 'b' => Self::Bracket('(', ')'),
+            '[' | ']' => Self::Bracket('[', ']'),
+            '{' | '}' | 'B' => Self::Bracket('{', '}'),
+            '<' | '>' => Self::Bracket('<', '>'),
+            't' => Self::Tag,
+            _ => return None,
+        };
+
+        Some(object)
+    }
+
+    /// Returns the text covered by the object at a position, as in `iw`, or along with its
+    /// surroundings, as in `aw`.
+    #[must_use]
+    pub fn span(self, buffer: &Buffer, cursor: Cursor, around: bool) -> Option<Span> {
+        match self {
+            Self::Word => word(buffer, cursor, around),
+            Self::Bracket(open, close) => bracket(buffer, cursor, open, close, around),
+            Self::Tag => tag(buffer, cursor, around),
+            Self::Paragraph => {
+                buffer.line(cursor.row())?;
+
+                let (first, last) = paragraph(buffer, cursor.row(), around);
+                Some(Span::Lines(first, last))
+            },
+
+            Self::Entire => Some(entire(buffer, around)),
+        }
+    }
+
+    /// Returns the text covered by the innermost object around the characters from `start` to
+    /// `end` that covers more than them, as when repeating `i(` or `it` in visual mode.
+    ///
+    /// Only brackets and tags are looked for around characters.
+    #[must_use]
+    pub fn enclosing(
+        self,
+        buffer: &Buffer,
+        start: Cursor,
+        end: Cursor,
+        around: bool,
+    ) -> Option<Span> {
+        let covers = |span: &Span| match *span {
+            Span::Chars(first, last) => {
+                first <= start && end <= last && (first, last) != (start, end)
+            },
+            Span::Lines(..) => false,
+        };
+
+        match self {
+            Self::Bracket(..) => std::iter::once(start)
+                .chain(buffer.char_indices_from(start).rev().map(|(at, _)| at))
+                .filter_map(|at| self.span(buffer, at, around))
+                .find(covers),
+
+            // Pairs of tags are nested, so the innermost one covering the characters starts last.
+            Self::Tag => tags(buffer, around)
+                .into_iter()
+                .filter_map(|(tags, span)| Some((*tags.start(), span?)))
+                .filter(|(_, span)| covers(span))
+                .max_by_key(|&(first, _)| first)
+                .map(|(_, span)| span),
+
+            _ => None,
+        }
+    }
+}
+
+/// Returns the word at a position, along with the blanks after it, or before it if there are none,
+/// if `around` is set.
+///
+/// On blanks, the blanks themselves are taken instead, followed by the next word if `around` is set.
+fn word(buffer: &Buffer, cursor: Cursor, around: bool) -> Option<Span> {
+    let line = buffer.line(cursor.row())?;
+    let class = |col: usize| Class::of(line.get(col), buffer);
+
+    if line.is_empty() {
+        return Some(Span::Chars(cursor, cursor));
+    }
+
+    let run = |col: usize| {
+        let first = (0..col).rev().take_while(|&prev| class(prev) == class(col)).last();
+        let last = (col + 1..line.len()).take_while(|&next| class(next) == class(col)).last();
+
+        (first.unwrap_or(col), last.unwrap_or(col))
+    };
+
+    let col = cursor.col().min(line.len() - 1);
+    let (mut first, mut last) = run(col);
+
+    if around {
+        let blank = |col: usize| class(col) == Class::Blank;
+
+        if last + 1 < line.len() && (blank(col) || blank(last + 1)) {
+            last = run(last + 1).1;
+        } else if first > 0 && blank(first - 1) {
+            first = run(first - 1).0;
+        }
+    }
+
+    Some(Span::Chars(Cursor::new(cursor.row(), first), Cursor::new(cursor.row(), last)))
+}
+
+/// Returns the text within the innermost pair of brackets around a position, or along with the
+/// brackets if `around` is set.
+///
+/// A position on either bracket counts as within their pair.
+fn bracket(buffer: &Buffer, cursor: Cursor, open: char, close: char, around: bool) -> Option<Span> {
+    let depth = |depth: &mut usize, (at, ch): (Cursor, char), opening: char, closing: char| {
+        if ch == closing {
+            *depth += 1;
+        } else if ch == opening {
+            if *depth == 0 {
+                return Some(at);
+            }
+
+            *depth -= 1;
+        }
+
+        None
+    };
+
+    let start = match buffer.get(cursor) {
+        Some(ch) if ch == open => cursor,
+        _ => {
+            let mut level = 0;
+            let mut before = buffer.char_indices_from(cursor).rev();
+
+            before.find_map(|item| depth(&mut level, item, open, close))?
+        },
+    };
+
+    let mut level = 0;
+    let mut after = buffer.char_indices_from(start).skip(1);
+    let end = after.find_map(|item| depth(&mut level, item, close, open))?;
+
+    if around {
+        return Some(Span::Chars(start, end));
+    }
+
+    let inner = buffer.char_indices_from(start).nth(1).map(|(at, _)| at)?;
+    let last = buffer.char_indices_from(end).next_back().map(|(at, _)| at)?;
+
+    Some(Span::Chars(inner, last)).filter(|_| inner < end)
+}
+
+/// Returns the text within the innermost pair of matching tags around a position, as in `it`, or
+/// along with the tags if `around` is set, as in `at`.
+///
+/// A position on either tag counts as within their pair.
+fn tag(buffer: &Buffer, cursor: Cursor, around: bool) -> Option<Span> {
+    let cursor = buffer.clamp(cursor);
+
+    tags(buffer, around)
+        .into_iter()
+        .filter(|(tags, _)| tags.contains(&cursor))
+        .max_by_key(|(tags, _)| *tags.start())
+        .and_then(|(_, span)| span)
+}
+
+/// Returns every pair of matching tags, from the start of the opening tag to the end of the closing
+/// one, along with the text within them, or along with the tags if `around` is set.
+///
+/// Closing tags match the closest opening tag with the same name, dropping any unclosed tags in
+/// between. Self-closing tags, as in `<br/>`, and declarations, as in `<!DOCTYPE html>`, are
+/// skipped.
+fn tags(buffer: &Buffer, around: bool) -> Vec<(RangeInclusive<Cursor>, Option<Span>)> {
+    let text = (0..buffer.content().len())
+        .filter_map(|row| buffer.line(row).map(|line| (row, line)))
+        .flat_map(|(row, line)| {
+            (0..=line.len()).map(move |col| (Cursor::new(row, col), line.get(col).unwrap_or('\n')))
+        })
+        .collect::<Vec<_>>();
+
+    let mut open = Vec::new();
+    let mut pairs = Vec::new();
+
+    let starts = text.iter().enumerate().filter(|&(_, &(_, ch))| ch == '<').map(|(idx, _)| idx);
+
+    for start in starts {
+        let end = match text[start..].iter().position(|&(_, ch)| ch == '>') {
+            Some(len) => start + len,
+            None => break,
+        };
+
+        let inside = text[start + 1..end].iter().map(|&(_, ch)| ch).collect::<String>();
+        let name = inside.trim_start_matches('/').split_whitespace().next().unwrap_or_default();
+
+        if name.is_empty() || inside.starts_with(&['!', '?'][..]) || inside.ends_with('/') {
+            continue;
+        }
+
+        if !inside.starts_with('/') {
+            open.push((name.to_owned(), start, end));
+        } else if let Some(idx) = open.iter().rposition(|(other, ..)| other == name) {
+            let (_, first, last) = open[idx];
+            open.truncate(idx);
+
+            pairs.push((first, last, start, end));
+        }
+    }
+
+    pairs
+        .into_iter()
+        .map(|(first, last, start, end)| {
+            let span = if around {
+                Some(Span::Chars(text[first].0, text[end].0))
+            } else {
+                Some(Span::Chars(text[last + 1].0, text[start - 1].0)).filter(|_| last + 1 < start)
+            };
+
+            (text[first].0..=text[end].0, span)
+        })
+        .collect()
+}
+
+/// Returns the lines of the whole buffer, as in `ae`, or the ones between its leading and trailing
+/// blank lines, as in `ie`.
+///
+/// A buffer made only of blank lines is taken whole either way.
+fn entire(buffer: &Buffer, around: bool) -> Span {
+    let last = buffer.content().len() - 1;
+
+    if around {
+        return Span::Lines(0, last);
+    }
+
+    let filled = |&row: &usize| !buffer.is_blank_line(row);
+
+    match ((0..=last).find(filled), (0..=last).rev().find(filled)) {
+        (Some(first), Some(last)) => Span::Lines(first, last),
+        _ => Span::Lines(0, last),
+    }
+}
+
+/// Returns the first and last rows of the paragraph at a row, as in `ip`, or along with the blank
+/// lines after it, as in `ap`.
+///
+/// A paragraph is a run of lines that are all blank or all not blank. When there are no blank lines
+/// after a paragraph, `ap` takes the ones before it instead.
+fn paragraph(buffer: &Buffer, row: usize, around: bool) -> (usize, usize) {
+    let is_blank = |row: usize| (row < buffer.content().len()).then(|| buffer.is_blank_line(row));
+    let blank = is_blank(row);
+
+    let run = |row: usize, blank| {
+        let same = |&row: &usize| is_blank(row) == blank;
+
+        let first = (0..row).rev().take_while(same).last().unwrap_or(row);
+        let last = (row + 1..buffer.content().len()).take_while(same).last().unwrap_or(row);
+
+        (first, last)
+    };
+
+    let (first, last) = run(row, blank);
+
+    if !around || blank != Some(false) {
+        return (first, last);
+    }
+
+    match (is_blank(last + 1), first.checked_sub(1).and_then(is_blank)) {
+        (Some(true), _) => (first, run(last + 1, Some(true)).1),
+        (_, Some(true)) => (run(first - 1, Some(true)).0, last),
+        _ => (first, last),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{paragraph, Object, Span};
+    use crate::{Buffer, Cursor};
+
+    fn chars(buffer: &Buffer, object: Object, cursor: Cursor, around: bool) -> Option<String> {
+        match object.span(buffer, cursor, around)? {
+            Span::Chars(start, end) => Some(buffer.slice(start..=end)),
+            Span::Lines(..) => None,
+        }
+    }
+
+    #[test]
+    fn test_word() {
+        let buffer = Buffer::from("foo  bar.baz qux");
+
+        assert_eq!(chars(&buffer, Object::Word, Cursor::new(0, 1), false).unwrap(), "foo");
+        assert_eq!(chars(&buffer, Object::Word, Cursor::new(0, 1), true).unwrap(), "foo  ");
+        assert_eq!(chars(&buffer, Object::Word, Cursor::new(0, 3), false).unwrap(), "  ");
+        assert_eq!(chars(&buffer, Object::Word, Cursor::new(0, 3), true).unwrap(), "  bar");
+        assert_eq!(chars(&buffer, Object::Word, Cursor::new(0, 8), true).unwrap(), ".");
+        assert_eq!(chars(&buffer, Object::Word, Cursor::new(0, 14), true).unwrap(), " qux");
+
+        let buffer = Buffer::from("foo   bar");
+        assert_eq!(chars(&buffer, Object::Word, Cursor::new(0, 7), true).unwrap(), "   bar");
+    }
+
+    #[test]
+    fn test_bracket() {
+        let buffer = Buffer::from("f(a, (b)\n  c) ()");
+        let parens = Object::Bracket('(', ')');
+
+        assert_eq!(chars(&buffer, parens, Cursor::new(0, 2), false).unwrap(), "a, (b)\n  c");
+        assert_eq!(chars(&buffer, parens, Cursor::new(0, 6), false).unwrap(), "b");
+        assert_eq!(chars(&buffer, parens, Cursor::new(0, 7), true).unwrap(), "(b)");
+        assert_eq!(chars(&buffer, parens, Cursor::new(1, 0), true).unwrap(), "(a, (b)\n  c)");
+        assert_eq!(chars(&buffer, parens, Cursor::new(1, 5), true).unwrap(), "()");
+
+        assert_eq!(parens.span(&buffer, Cursor::new(1, 5), false), None);
+        assert_eq!(parens.span(&buffer, Cursor::new(0, 0), false), None);
+    }
+
+    #[test]
+    fn test_tag() {
+        let buffer = Buffer::from("<div><span>x</span>\n  <br/><p class=\"y\">z</p></div><a></a>");
+
+        assert_eq!(chars(&buffer, Object::Tag, Cursor::new(0, 11), false).unwrap(), "x");
+        assert_eq!(
+            chars(&buffer, Object::Tag, Cursor::new(0, 11), true).unwrap(),
+            "<span>x</span>"
+        );
+        assert_eq!(
+            chars(&buffer, Object::Tag, Cursor::new(0, 16), true).unwrap(),
+            "<span>x</span>"
+        );
+
+        assert_eq!(
+            chars(&buffer, Object::Tag, Cursor::new(1, 3), false).unwrap(),
+            "<span>x</span>\n  <br/><p class=\"y\">z</p>"
+        );
+
+        assert_eq!(chars(&buffer, Object::Tag, Cursor::new(1, 17), false).unwrap(), "z");
+        assert_eq!(chars(&buffer, Object::Tag, Cursor::new(1, 31), true).unwrap(), "<a></a>");
+        assert_eq!(Object::Tag.span(&buffer, Cursor::new(1, 31), false), None);
+    }
+
+    #[test]
+    fn test_tag_unclosed() {
+        let buffer = Buffer::from("<ul><li>foo<li>bar</ul> <b>");
+
+        assert_eq!(
+            chars(&buffer, Object::Tag, Cursor::new(0, 16), false).unwrap(),
+            "<li>foo<li>bar"
+        );
+        assert_eq!(Object::Tag.span(&buffer, Cursor::new(0, 25), false), None);
+    }
+
+    #[test]
+    fn test_entire() {
+        let buffer = Buffer::from("\nfoo\n\nbar\n  \n");
+        let cursor = Cursor::new(3, 1);
+
+        assert_eq!(Object::Entire.span(&buffer, cursor, true), Some(Span::Lines(0, 5)));
+        assert_eq!(Object::Entire.span(&buffer, cursor, false), Some(Span::Lines(1, 3)));
+
+        let buffer = Buffer::from("\n ");
+        assert_eq!(Object::Entire.span(&buffer, cursor, false), Some(Span::Lines(0, 1)));
+    }
+
+    #[test]
+    fn test_paragraph() {
+        let buffer = Buffer::from("foo\nbar\n\n  \nbaz\n\nqux");
+
+        assert_eq!(paragraph(&buffer, 1, false), (0, 1));
+        assert_eq!(paragraph(&buffer, 1, true), (0, 3));
+        assert_eq!(paragraph(&buffer, 2, false), (2, 3));
+        assert_eq!(paragraph(&buffer, 2, true), (2, 3));
+        assert_eq!(paragraph(&buffer, 4, true), (4, 5));
+        assert_eq!(paragraph(&buffer, 6, false), (6, 6));
+        assert_eq!(paragraph(&buffer, 6, true), (5, 6));
+    }
+}
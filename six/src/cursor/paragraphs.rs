@@ -1,51 +1,111 @@
-use crate::cursor::Cells;
+use crate::buffer::Row;
+use crate::cursor::Metric;
 use crate::{Buffer, Cursor};
 
+/// An iterator over the blank lines that separate paragraphs, as visited by `}` and `{`.
+///
+/// Going forward, each paragraph ends at the first blank line after it, and going backward it
+/// starts at the last blank line before it. Past the last paragraph, the iterator stops at the end
+/// of the buffer, and before the first one, at its start.
 pub struct Paragraphs<'a> {
-    buffer: &'a Buffer,
     cursor: Cursor,
+    buffer: &'a Buffer,
 }
 
-impl<'b> Paragraphs<'b> {
-    pub fn new(cursor: Cursor, buffer: &'b Buffer) -> Self {
-        Self { buffer, cursor }
+impl<'a> Paragraphs<'a> {
+    #[must_use]
+    pub fn new(cursor: Cursor, buffer: &'a Buffer) -> Self {
+        Self { cursor, buffer }
     }
-}
-
-fn find(buffer: &Buffer, cells: impl Iterator<Item = Cursor>) -> Option<Cursor> {
-    let mut lookahead = {
-        let mut cells = itertools::multipeek(cells);
 
-        std::iter::from_fn(move || {
-            let p = cells.next()?;
-            let q = cells.peek().and_then(|&q| buffer.get(q));
-            let r = cells.peek().and_then(|&r| buffer.get(r));
+    /// Moves to a position if it is in the direction of travel.
+    fn visit(&mut self, cursor: Cursor, forward: bool) -> Option<Cursor> {
+        if (cursor > self.cursor) != forward || cursor == self.cursor {
+            return None;
+        }
 
-            Some((p, q, r))
-        })
-    };
-
-    let point = lookahead.find(|&(p, q, r)| {
-        let p = buffer.get(p);
+        self.cursor = cursor;
+        Some(cursor)
+    }
+}
 
-        !p.map_or(true, |ch| ch == '\n')
-            && q.map_or(true, |ch| ch == '\n')
-            && r.map_or(true, |ch| ch == '\n')
-    })?;
+impl Metric for Paragraphs<'_> {
+    fn after(cursor: Cursor, buffer: &Buffer) -> Option<Cursor> {
+        Paragraphs::new(cursor, buffer).next()
+    }
 
-    Some(point.0)
+    fn before(cursor: Cursor, buffer: &Buffer) -> Option<Cursor> {
+        Paragraphs::new(cursor, buffer).next_back()
+    }
 }
 
 impl Iterator for Paragraphs<'_> {
     type Item = Cursor;
 
+    /// Moves forward to the end of a paragraph.
     fn next(&mut self) -> Option<Self::Item> {
-        find(self.buffer, Cells::new(self.cursor, self.buffer))
+        let last = self.buffer.content().len() - 1;
+
+        let buffer = self.buffer;
+        let row = (self.cursor.row() + 1..=last)
+            .find(|&row| buffer.is_blank_line(row) && !buffer.is_blank_line(row - 1));
+
+        let end = || {
+            let len = self.buffer.line(last).map_or(0, Row::len);
+            Cursor::new(last, len.saturating_sub(1))
+        };
+
+        let cursor = row.map_or_else(end, |row| Cursor::new(row, 0));
+        self.visit(cursor, true)
     }
 }
 
 impl DoubleEndedIterator for Paragraphs<'_> {
+    /// Moves backward to the start of a paragraph.
     fn next_back(&mut self) -> Option<Self::Item> {
-        find(self.buffer, Cells::new(self.cursor, self.buffer).rev())
+        let buffer = self.buffer;
+        let row = (0..self.cursor.row())
+            .rev()
+            .find(|&row| buffer.is_blank_line(row) && !buffer.is_blank_line(row + 1));
+
+        let cursor = row.map_or_else(Cursor::origin, |row| Cursor::new(row, 0));
+        self.visit(cursor, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Buffer, Cursor};
+
+    #[test]
+    fn test_forward() {
+        let buffer = Buffer::from("foo\nbar\n\n\nbaz\n  \nqux");
+        let stops = buffer.paragraphs(Cursor::new(0, 1)).collect::<Vec<_>>();
+
+        assert_eq!(stops, [Cursor::new(2, 0), Cursor::new(5, 0), Cursor::new(6, 2)]);
+        assert_eq!(buffer.paragraphs(Cursor::new(6, 2)).next(), None);
+    }
+
+    #[test]
+    fn test_backward() {
+        let buffer = Buffer::from("foo\nbar\n\n\nbaz\n  \nqux");
+        let stops = buffer.paragraphs(Cursor::new(6, 1)).rev().collect::<Vec<_>>();
+
+        assert_eq!(stops, [Cursor::new(5, 0), Cursor::new(3, 0), Cursor::new(0, 0)]);
+    }
+
+    #[test]
+    fn test_backward_at_start() {
+        let buffer = Buffer::from("foo bar\nbaz");
+
+        assert_eq!(buffer.paragraphs(Cursor::new(1, 2)).next_back(), Some(Cursor::origin()));
+        assert_eq!(buffer.paragraphs(Cursor::new(0, 4)).next_back(), Some(Cursor::origin()));
+        assert_eq!(buffer.paragraphs(Cursor::origin()).next_back(), None);
+
+        let buffer = Buffer::from("\n\nfoo\nbar");
+        let stops = buffer.paragraphs(Cursor::new(3, 1)).rev().collect::<Vec<_>>();
+
+        assert_eq!(stops, [Cursor::new(1, 0), Cursor::origin()]);
+        assert_eq!(buffer.paragraphs(Cursor::new(1, 0)).next_back(), Some(Cursor::origin()));
     }
 }
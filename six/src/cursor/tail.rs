@@ -1,28 +1,40 @@
-use crate::cursor::{Codepoint, Cursor, Iter};
+use crate::cursor::{Cells, Class, Metric};
+use crate::{Buffer, Cursor};
 
+/// An iterator over the last characters of the words of a buffer.
 pub struct Tail<'a> {
-    text: &'a str,
-    iter: Codepoint<'a>,
+    cells: Cells<'a>,
+    buffer: &'a Buffer,
 }
 
-fn is_word_tail(cursor: Cursor, text: &str) -> bool {
-    let mut chars = text[cursor.offset..].chars();
+/// Returns whether a word ends at the specified position.
+fn is_word_tail(cursor: Cursor, buffer: &Buffer) -> bool {
+    let p = Class::at(cursor, buffer);
+    let q = Class::of(buffer.char_indices_from(cursor).nth(1).map(|(_, ch)| ch), buffer);
 
-    let p = chars.next();
-    let q = chars.next();
+    p != Class::Blank && p != q
+}
 
-    let res = !p.map_or(true, char::is_whitespace) && q.map_or(true, char::is_whitespace);
+impl<'a> Tail<'a> {
+    #[must_use]
+    pub fn new(cursor: Cursor, buffer: &'a Buffer) -> Self {
+        Self { buffer, cells: Cells::new(cursor, buffer) }
+    }
 
-    res
+    /// Returns whether a word ends at the specified position.
+    #[must_use]
+    pub fn ends_word(cursor: Cursor, buffer: &Buffer) -> bool {
+        is_word_tail(cursor, buffer)
+    }
 }
 
-impl<'a> Iter<'a> for Tail<'a> {
-    fn new(cursor: Cursor, text: &'a str) -> Self {
-        Self { text, iter: Codepoint::new(cursor, text) }
+impl Metric for Tail<'_> {
+    fn after(cursor: Cursor, buffer: &Buffer) -> Option<Cursor> {
+        Tail::new(cursor, buffer).next()
     }
 
-    fn at(&self) -> Self::Item {
-        self.iter.at()
+    fn before(cursor: Cursor, buffer: &Buffer) -> Option<Cursor> {
+        Tail::new(cursor, buffer).next_back()
     }
 }
 
@@ -30,30 +42,33 @@ impl Iterator for Tail<'_> {
     type Item = Cursor;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let text = self.text;
-        eprintln!("hi");
-        self.iter.find(|&p| is_word_tail(p, text))
+        let buffer = self.buffer;
+        self.cells.find(|&cursor| is_word_tail(cursor, buffer))
     }
 }
 
 impl DoubleEndedIterator for Tail<'_> {
     fn next_back(&mut self) -> Option<Self::Item> {
-        let text = self.text;
-        self.iter.rfind(|&p| is_word_tail(p, text))
+        let buffer = self.buffer;
+        self.cells.rfind(|&cursor| is_word_tail(cursor, buffer))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::Tail;
-    use crate::Cursor;
+    use crate::{Buffer, Cursor};
 
     static LOREM: &str = include_str!("../../assets/lorem.txt");
 
     #[test]
     fn test_iter() {
-        let codepoints = Cursor::origin().iter::<Tail>(LOREM).collect::<Vec<_>>();
+        let buffer = Buffer::from(LOREM);
+        let tails = Tail::new(Cursor::origin(), &buffer).take(4).collect::<Vec<_>>();
 
-        assert_eq!(codepoints, vec![]);
+        assert_eq!(
+            tails,
+            vec![Cursor::new(0, 2), Cursor::new(0, 6), Cursor::new(0, 10), Cursor::new(1, 2)]
+        );
     }
 }
@@ -1,5 +1,10 @@
+use std::fmt;
+use std::str::FromStr;
 use std::time::Duration;
 
+#[cfg(feature = "crossterm")]
+mod crossterm;
+
 bitflags! {
     pub struct Modifiers: u8 {
         const NONE  = 0;
@@ -10,8 +15,27 @@ bitflags! {
     }
 }
 
+/// Modifiers are serialized as their bits.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Modifiers {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(self.bits())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Modifiers {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bits = <u8 as serde::Deserialize>::deserialize(deserializer)?;
+
+        Self::from_bits(bits)
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid modifiers: {:#010b}", bits)))
+    }
+}
+
 /// A key.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Key {
     /// Backspace.
     Backspace,
@@ -44,11 +68,384 @@ pub enum Key {
     Char(char),
 }
 
+/// An input event.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Event {
-    /// An event periodically sent after no other event has been emitted.
+    /// An event periodically sent while there is no input, holding the time since the last input.
+    ///
+    /// Frontends send one each time no input arrives within their timeout, until the next input.
     Idle(Duration),
 
     /// A key press.
     Key(Key, Modifiers),
 }
+
+impl Key {
+    /// Returns the name of the key in the angle bracket notation, unless it is a character that
+    /// stands for itself.
+    fn name(self) -> Option<&'static str> {
+        let name = match self {
+            Self::Backspace => "BS",
+            Self::Delete => "Del",
+            Self::Esc => "Esc",
+            Self::Left => "Left",
+            Self::Right => "Right",
+            Self::Up => "Up",
+            Self::Down => "Down",
+            Self::End => "End",
+            Self::Home => "Home",
+            Self::Char('\n') => "CR",
+            Self::Char('\t') => "Tab",
+            Self::Char(' ') => "Space",
+            Self::Char('<') => "lt",
+            Self::Char('>') => "gt",
+            Self::Char(_) => return None,
+        };
+
+        Some(name)
+    }
+}
+
+impl Event {
+    /// Returns whether the editor has a meaning for the event.
+    ///
+    /// Control characters other than line breaks and tabs aren't, such as the null characters
+    /// frontends may send for keys they have no name for, and are ignored rather than inserted.
+    #[must_use]
+    pub fn is_supported(&self) -> bool {
+        match *self {
+            Event::Key(Key::Char(ch), _) => !ch.is_control() || ch == '\n' || ch == '\t',
+            _ => true,
+        }
+    }
+}
+
+impl fmt::Display for Key {
+    /// Formats the key in the notation accepted by `parse`, as in `<Esc>` or `x`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.name(), self) {
+            (Some(name), _) => write!(f, "<{}>", name),
+            (None, Key::Char(ch)) => write!(f, "{}", ch),
+            (None, _) => unreachable!(),
+        }
+    }
+}
+
+impl fmt::Display for Modifiers {
+    /// Formats the modifiers as the prefixes of the angle bracket notation, as in `C-S-`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let prefixes = [(Self::CTRL, "C-"), (Self::SHFT, "S-"), (Self::META, "M-")];
+
+        prefixes
+            .iter()
+            .filter(|(modifier, _)| self.contains(*modifier))
+            .try_for_each(|(_, prefix)| f.write_str(prefix))
+    }
+}
+
+impl fmt::Display for Event {
+    /// Formats the event in the notation accepted by `parse`, as in `<C-w>`.
+    ///
+    /// Idle events have no such notation, and are formatted as `<Idle>`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Event::Idle(_) => f.write_str("<Idle>"),
+            Event::Key(key, modifiers) if modifiers.is_empty() => write!(f, "{}", key),
+            Event::Key(key, modifiers) => match (key.name(), key) {
+                (Some(name), _) => write!(f, "<{}{}>", modifiers, name),
+                (_, Key::Char(ch)) => write!(f, "<{}{}>", modifiers, ch),
+                (None, _) => unreachable!(),
+            },
+        }
+    }
+}
+
+/// Returns the key named in the angle bracket notation, as in `Esc` for `<Esc>`.
+fn named(name: &str) -> Option<Key> {
+    let key = match name.to_ascii_lowercase().as_str() {
+        "bs" | "backspace" => Key::Backspace,
+        "del" | "delete" => Key::Delete,
+        "esc" => Key::Esc,
+        "left" => Key::Left,
+        "right" => Key::Right,
+        "up" => Key::Up,
+        "down" => Key::Down,
+        "end" => Key::End,
+        "home" => Key::Home,
+        "cr" | "enter" | "return" => Key::Char('\n'),
+        "tab" => Key::Char('\t'),
+        "space" => Key::Char(' '),
+        "lt" => Key::Char('<'),
+        "gt" => Key::Char('>'),
+        _ => {
+            let mut chars = name.chars();
+            match (chars.next(), chars.next()) {
+                (Some(ch), None) => Key::Char(ch),
+                _ => return None,
+            }
+        },
+    };
+
+    Some(key)
+}
+
+/// Parses the contents of an angle bracket notation, as in `C-w` for `<C-w>`.
+fn bracketed(notation: &str) -> Result<Event, ParseError> {
+    let mut modifiers = Modifiers::NONE;
+    let mut name = notation;
+
+    while let (Some(prefix), Some('-')) = (name.chars().next(), name.chars().nth(1)) {
+        if name.len() <= 2 {
+            break;
+        }
+
+        modifiers |= match prefix.to_ascii_uppercase() {
+            'C' => Modifiers::CTRL,
+            'S' => Modifiers::SHFT,
+            'M' | 'A' => Modifiers::META,
+            _ => return Err(ParseError(format!("Unknown modifier: {}-", prefix))),
+        };
+
+        name = &name[prefix.len_utf8() + 1..];
+    }
+
+    let key = named(name).ok_or_else(|| ParseError(format!("Unknown key: <{}>", notation)))?;
+    Ok(Event::Key(key, modifiers))
+}
+
+/// Parses a sequence of key presses written in a compact notation.
+///
+/// Characters stand for themselves, except for `<`, which starts a key name in angle brackets:
+///
+/// - `<Esc>`, `<BS>`, `<Del>`, `<Left>`, `<Right>`, `<Up>`, `<Down>`, `<Home>` and `<End>` name the
+///   respective keys;
+/// - `<CR>`, `<Tab>` and `<Space>` stand for a line feed, a tab and a space;
+/// - `<lt>` and `<gt>` stand for a literal `<` and `>`;
+/// - Any key may be prefixed with `C-` (Ctrl), `S-` (Shift) and `M-` or `A-` (Meta), as in
+///   `<C-w>` or `<C-S-Left>`.
+///
+/// Names are case insensitive. A `<` that doesn't start a valid name is taken literally.
+#[must_use]
+pub fn parse(keys: &str) -> Vec<Event> {
+    let mut events = Vec::new();
+    let mut rest = keys;
+
+    while let Some(ch) = rest.chars().next() {
+        let notation = rest
+            .strip_prefix('<')
+            .and_then(|tail| tail.find('>').map(|end| (&tail[..end], &tail[end + 1..])))
+            .and_then(|(notation, tail)| Some((bracketed(notation).ok()?, tail)));
+
+        if let Some((event, tail)) = notation {
+            events.push(event);
+            rest = tail;
+        } else {
+            events.push(Event::Key(Key::Char(ch), Modifiers::NONE));
+            rest = &rest[ch.len_utf8()..];
+        }
+    }
+
+    events
+}
+
+/// An error in the notation of a key.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseError(String);
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl FromStr for Event {
+    type Err = ParseError;
+
+    /// Parses a single key press, as in `x`, `<CR>` or `<C-w>`.
+    ///
+    /// Unlike `parse`, a malformed angle bracket notation is an error rather than a sequence of
+    /// characters.
+    fn from_str(notation: &str) -> Result<Self, Self::Err> {
+        let mut chars = notation.chars();
+
+        match (chars.next(), chars.next()) {
+            (None, _) => Err(ParseError("Empty key".to_owned())),
+            (Some(ch), None) => Ok(Event::Key(Key::Char(ch), Modifiers::NONE)),
+            _ => match notation.strip_prefix('<').and_then(|tail| tail.strip_suffix('>')) {
+                Some(inner) if !inner.contains('>') => bracketed(inner),
+                _ => Err(ParseError(format!("Expected a single key: {}", notation))),
+            },
+        }
+    }
+}
+
+impl FromStr for Key {
+    type Err = ParseError;
+
+    /// Parses a single key without modifiers, as in `x` or `<Esc>`.
+    fn from_str(notation: &str) -> Result<Self, Self::Err> {
+        match notation.parse()? {
+            Event::Key(key, modifiers) if modifiers.is_empty() => Ok(key),
+            _ => Err(ParseError(format!("Unexpected modifiers: {}", notation))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{parse, Event, Key, Modifiers, ParseError};
+
+    #[test]
+    fn test_parse_chars() {
+        assert_eq!(parse("ab"), vec![
+            Event::Key(Key::Char('a'), Modifiers::NONE),
+            Event::Key(Key::Char('b'), Modifiers::NONE),
+        ]);
+    }
+
+    #[test]
+    fn test_parse_special_keys() {
+        assert_eq!(parse("<Esc><CR><bs><lt><Left>"), vec![
+            Event::Key(Key::Esc, Modifiers::NONE),
+            Event::Key(Key::Char('\n'), Modifiers::NONE),
+            Event::Key(Key::Backspace, Modifiers::NONE),
+            Event::Key(Key::Char('<'), Modifiers::NONE),
+            Event::Key(Key::Left, Modifiers::NONE),
+        ]);
+    }
+
+    #[test]
+    fn test_parse_modifiers() {
+        assert_eq!(parse("<C-w><c-S-Left><M-b><C-->"), vec![
+            Event::Key(Key::Char('w'), Modifiers::CTRL),
+            Event::Key(Key::Left, Modifiers::CTRL | Modifiers::SHFT),
+            Event::Key(Key::Char('b'), Modifiers::META),
+            Event::Key(Key::Char('-'), Modifiers::CTRL),
+        ]);
+    }
+
+    #[test]
+    fn test_parse_literal_brackets() {
+        assert_eq!(parse("<x-y><"), vec![
+            Event::Key(Key::Char('<'), Modifiers::NONE),
+            Event::Key(Key::Char('x'), Modifiers::NONE),
+            Event::Key(Key::Char('-'), Modifiers::NONE),
+            Event::Key(Key::Char('y'), Modifiers::NONE),
+            Event::Key(Key::Char('>'), Modifiers::NONE),
+            Event::Key(Key::Char('<'), Modifiers::NONE),
+        ]);
+    }
+
+    #[test]
+    fn test_display_keys() {
+        assert_eq!(Key::Esc.to_string(), "<Esc>");
+        assert_eq!(Key::Left.to_string(), "<Left>");
+        assert_eq!(Key::Backspace.to_string(), "<BS>");
+        assert_eq!(Key::Char('x').to_string(), "x");
+        assert_eq!(Key::Char('\n').to_string(), "<CR>");
+        assert_eq!(Key::Char('<').to_string(), "<lt>");
+        assert_eq!(Key::Char('>').to_string(), "<gt>");
+    }
+
+    #[test]
+    fn test_display_modifiers() {
+        assert_eq!(Modifiers::NONE.to_string(), "");
+        assert_eq!(Modifiers::CTRL.to_string(), "C-");
+        assert_eq!((Modifiers::META | Modifiers::CTRL).to_string(), "C-M-");
+        assert_eq!(Modifiers::all().to_string(), "C-S-M-");
+    }
+
+    #[test]
+    fn test_display_events() {
+        assert_eq!(Event::Key(Key::Char('w'), Modifiers::CTRL).to_string(), "<C-w>");
+        assert_eq!(
+            Event::Key(Key::Left, Modifiers::CTRL | Modifiers::SHFT).to_string(),
+            "<C-S-Left>"
+        );
+        assert_eq!(Event::Key(Key::Char('<'), Modifiers::META).to_string(), "<M-lt>");
+        assert_eq!(Event::Key(Key::Char('>'), Modifiers::CTRL).to_string(), "<C-gt>");
+        assert_eq!(Event::Key(Key::Esc, Modifiers::NONE).to_string(), "<Esc>");
+        assert_eq!(Event::Key(Key::Char('a'), Modifiers::NONE).to_string(), "a");
+        assert_eq!(Event::Idle(Duration::from_secs(1)).to_string(), "<Idle>");
+    }
+
+    #[test]
+    fn test_display_parses_back() {
+        let events = parse("a<C-w><Esc><lt><C-S-Left><M-->< ><C-gt>>");
+        let keys = events.iter().map(ToString::to_string).collect::<String>();
+
+        assert_eq!(parse(&keys), events);
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!("x".parse(), Ok(Event::Key(Key::Char('x'), Modifiers::NONE)));
+        assert_eq!("<".parse(), Ok(Event::Key(Key::Char('<'), Modifiers::NONE)));
+        assert_eq!("<CR>".parse(), Ok(Event::Key(Key::Char('\n'), Modifiers::NONE)));
+        assert_eq!("<C-w>".parse(), Ok(Event::Key(Key::Char('w'), Modifiers::CTRL)));
+        assert_eq!("<esc>".parse(), Ok(Key::Esc));
+    }
+
+    #[test]
+    fn test_from_str_malformed() {
+        fn error<T>(message: &str) -> Result<T, ParseError> {
+            Err(ParseError(message.to_owned()))
+        }
+
+        assert_eq!("".parse::<Event>(), error("Empty key"));
+        assert_eq!("ab".parse::<Event>(), error("Expected a single key: ab"));
+        assert_eq!("<Esc".parse::<Event>(), error("Expected a single key: <Esc"));
+        assert_eq!("<a><b>".parse::<Event>(), error("Expected a single key: <a><b>"));
+        assert_eq!("<Foo>".parse::<Event>(), error("Unknown key: <Foo>"));
+        assert_eq!("<X-a>".parse::<Event>(), error("Unknown modifier: X-"));
+        assert_eq!("<C-w>".parse::<Key>(), error("Unexpected modifiers: <C-w>"));
+    }
+
+    #[test]
+    fn test_from_str_round_trip() {
+        for event in parse("a<C-w><Esc><lt><C-S-Left><M-->< ><Tab>") {
+            assert_eq!(event.to_string().parse(), Ok(event));
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    mod serde {
+        use std::fmt::Debug;
+        use std::time::Duration;
+
+        use serde::de::DeserializeOwned;
+        use serde::Serialize;
+
+        use super::{Event, Key, Modifiers};
+        use crate::Cursor;
+
+        fn round_trip<T: Serialize + DeserializeOwned + Debug + PartialEq>(value: T) {
+            let json = serde_json::to_string(&value).unwrap();
+            assert_eq!(serde_json::from_str::<T>(&json).unwrap(), value);
+        }
+
+        #[test]
+        fn test_round_trip() {
+            round_trip(Cursor::new(3, 7));
+
+            round_trip(Key::Esc);
+            round_trip(Key::Char('x'));
+
+            round_trip(Modifiers::NONE);
+            round_trip(Modifiers::CTRL | Modifiers::META);
+
+            round_trip(Event::Key(Key::Char('w'), Modifiers::CTRL));
+            round_trip(Event::Idle(Duration::from_millis(250)));
+        }
+
+        #[test]
+        fn test_invalid_modifiers() {
+            assert!(serde_json::from_str::<Modifiers>("1").is_err());
+        }
+    }
+}
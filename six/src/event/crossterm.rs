@@ -0,0 +1,149 @@
+//! Translation of key presses read by `crossterm`, so that terminal frontends share one mapping.
+
+use std::convert::TryFrom;
+
+use ::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::event::{Event, Key, Modifiers};
+
+impl From<KeyModifiers> for Modifiers {
+    fn from(modifiers: KeyModifiers) -> Self {
+        let mut result = Self::NONE;
+
+        result.set(Self::CTRL, modifiers.contains(KeyModifiers::CONTROL));
+        result.set(Self::SHFT, modifiers.contains(KeyModifiers::SHIFT));
+        result.set(Self::META, modifiers.contains(KeyModifiers::ALT));
+
+        result
+    }
+}
+
+/// Translates a key press, failing with the key press itself when the editor has no key for it,
+/// as with function keys.
+///
+/// Shift is dropped from characters, since it is already part of the character, as in `A`.
+impl TryFrom<KeyEvent> for Event {
+    type Error = KeyEvent;
+
+    fn try_from(event: KeyEvent) -> Result<Self, Self::Error> {
+        let mut modifiers = Modifiers::from(event.modifiers);
+
+        let key = match event.code {
+            KeyCode::Esc => Key::Esc,
+
+            KeyCode::Char(ch) => {
+                modifiers.remove(Modifiers::SHFT);
+                Key::Char(ch)
+            },
+
+            KeyCode::Enter => Key::Char('\n'),
+            KeyCode::Tab => Key::Char('\t'),
+
+            KeyCode::BackTab => {
+                modifiers.insert(Modifiers::SHFT);
+                Key::Char('\t')
+            },
+
+            KeyCode::Left => Key::Left,
+            KeyCode::Up => Key::Up,
+            KeyCode::Down => Key::Down,
+            KeyCode::Right => Key::Right,
+
+            KeyCode::Backspace => Key::Backspace,
+            KeyCode::Delete => Key::Delete,
+
+            KeyCode::Home => Key::Home,
+            KeyCode::End => Key::End,
+
+            _ => return Err(event),
+        };
+
+        Ok(Self::Key(key, modifiers))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use ::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use crate::event::{parse, Event, Key, Modifiers};
+
+    fn translate(code: KeyCode, modifiers: KeyModifiers) -> Option<Event> {
+        Event::try_from(KeyEvent { code, modifiers }).ok()
+    }
+
+    fn translate_all(keys: &[(KeyCode, KeyModifiers)]) -> Vec<Event> {
+        keys.iter().filter_map(|&(code, modifiers)| translate(code, modifiers)).collect()
+    }
+
+    #[test]
+    fn test_normal_keys() {
+        let keys = [
+            (KeyCode::Char('d'), KeyModifiers::NONE),
+            (KeyCode::Char('W'), KeyModifiers::SHIFT),
+            (KeyCode::Char('v'), KeyModifiers::CONTROL),
+            (KeyCode::Esc, KeyModifiers::NONE),
+        ];
+
+        assert_eq!(translate_all(&keys), parse("dW<C-v><Esc>"));
+    }
+
+    #[test]
+    fn test_insert_keys() {
+        let keys = [
+            (KeyCode::Char('a'), KeyModifiers::NONE),
+            (KeyCode::Tab, KeyModifiers::NONE),
+            (KeyCode::Enter, KeyModifiers::NONE),
+            (KeyCode::Backspace, KeyModifiers::NONE),
+            (KeyCode::Delete, KeyModifiers::NONE),
+        ];
+
+        assert_eq!(translate_all(&keys), parse("a\t<CR><BS><Del>"));
+    }
+
+    #[test]
+    fn test_query_keys() {
+        let keys = [
+            (KeyCode::Char(':'), KeyModifiers::SHIFT),
+            (KeyCode::Left, KeyModifiers::NONE),
+            (KeyCode::Home, KeyModifiers::NONE),
+            (KeyCode::End, KeyModifiers::NONE),
+            (KeyCode::Up, KeyModifiers::NONE),
+            (KeyCode::Down, KeyModifiers::NONE),
+            (KeyCode::Right, KeyModifiers::NONE),
+        ];
+
+        assert_eq!(translate_all(&keys), parse(":<Left><Home><End><Up><Down><Right>"));
+    }
+
+    #[test]
+    fn test_modifiers() {
+        let keys =
+            [(KeyCode::Char('b'), KeyModifiers::ALT), (KeyCode::Char('F'), KeyModifiers::ALT)];
+        assert_eq!(translate_all(&keys), parse("<M-b><M-F>"));
+
+        assert_eq!(
+            translate(KeyCode::Char('x'), KeyModifiers::ALT | KeyModifiers::CONTROL),
+            Some(Event::Key(Key::Char('x'), Modifiers::CTRL | Modifiers::META))
+        );
+
+        assert_eq!(
+            translate(KeyCode::BackTab, KeyModifiers::SHIFT),
+            Some(Event::Key(Key::Char('\t'), Modifiers::SHFT))
+        );
+
+        assert_eq!(
+            translate(KeyCode::Left, KeyModifiers::SHIFT),
+            Some(Event::Key(Key::Left, Modifiers::SHFT))
+        );
+    }
+
+    #[test]
+    fn test_unsupported_keys() {
+        assert_eq!(translate(KeyCode::F(1), KeyModifiers::NONE), None);
+        assert_eq!(translate(KeyCode::Insert, KeyModifiers::NONE), None);
+        assert_eq!(translate(KeyCode::PageDown, KeyModifiers::CONTROL), None);
+    }
+}
@@ -0,0 +1,179 @@
+use crate::buffer::Edit;
+use crate::Cursor;
+
+/// A range of lines that can be collapsed into a single one, as in Vim's manual folds.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Fold {
+    /// The first line of the fold.
+    pub first: usize,
+
+    /// The last line of the fold.
+    pub last: usize,
+
+    /// Whether the fold is collapsed.
+    pub closed: bool,
+}
+
+impl Fold {
+    /// Returns whether the fold spans a line.
+    #[must_use]
+    pub fn contains(&self, row: usize) -> bool {
+        self.first <= row && row <= self.last
+    }
+}
+
+/// The folds of a buffer.
+#[derive(Debug, Default)]
+pub struct Folds(Vec<Fold>);
+
+impl Folds {
+    /// Returns an iterator over the folds, in the order they were created.
+    pub fn iter(&self) -> impl Iterator<Item = &Fold> {
+        self.0.iter()
+    }
+
+    /// Creates a closed fold over the lines from `first` to `last`.
+    pub fn create(&mut self, first: usize, last: usize) {
+        self.0.push(Fold { first: first.min(last), last: first.max(last), closed: true });
+    }
+
+    /// Returns the lines hidden behind the outermost closed fold spanning a line, if any, as the
+    /// first and the last one.
+    #[must_use]
+    pub fn closed(&self, row: usize) -> Option<(usize, usize)> {
+        self.0
+            .iter()
+            .filter(|fold| fold.closed && fold.contains(row))
+            .map(|fold| (fold.first, fold.last))
+            .max_by_key(|&(first, last)| last - first)
+    }
+
+    /// Opens the outermost closed fold spanning a line, or closes the innermost open one, returning
+    /// whether there was one.
+    pub fn toggle(&mut self, row: usize, closed: bool) -> bool {
+        let folds = self.0.iter_mut().filter(|fold| fold.closed != closed && fold.contains(row));
+
+        let fold = if closed {
+            folds.min_by_key(|fold| fold.last - fold.first)
+        } else {
+            folds.max_by_key(|fold| fold.last - fold.first)
+        };
+
+        match fold {
+            Some(fold) => {
+                fold.closed = closed;
+                true
+            },
+
+            None => false,
+        }
+    }
+
+    /// Moves the folds along with the lines they span after a change to the text.
+    ///
+    /// Folds over lines that were removed shrink to the lines that are left, or to the line where
+    /// the removal happened.
+    pub fn adjust(&mut self, edit: &Edit) {
+        for fold in &mut self.0 {
+            let (first, last) =
+                edit.adjust_range(Cursor::new(fold.first, 0), Cursor::new(fold.last, 0));
+
+            fold.first = first.row();
+            fold.last = last.row();
+        }
+    }
+
+    /// Returns the line reached by moving `count` lines down, or up if `forward` is not set,
+    /// counting each closed fold as a single line.
+    ///
+    /// Lines within closed folds are reported as the first line of the fold. Returns `None` if the
+    /// line can't move at all.
+    #[must_use]
+    pub fn travel(&self, row: usize, rows: usize, count: usize, forward: bool) -> Option<usize> {
+        let start = self.closed(row).map_or(row, |(first, _)| first);
+
+        let lines = std::iter::successors(Some(start), |&row| {
+            let next = if forward {
+                self.closed(row).map_or(row, |(_, last)| last) + 1
+            } else {
+                row.checked_sub(1)?
+            };
+
+            Some(self.closed(next).map_or(next, |(first, _)| first)).filter(|&next| next < rows)
+        });
+
+        lines.take(count + 1).skip(1).last()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Folds;
+    use crate::buffer::Edit;
+    use crate::Cursor;
+
+    #[test]
+    fn test_closed() {
+        let mut folds = Folds::default();
+
+        folds.create(4, 2);
+        folds.create(1, 6);
+        assert_eq!(folds.closed(3), Some((1, 6)));
+        assert_eq!(folds.closed(7), None);
+
+        assert!(folds.toggle(3, false));
+        assert_eq!(folds.closed(3), Some((2, 4)));
+        assert_eq!(folds.closed(5), None);
+
+        assert!(folds.toggle(3, false));
+        assert!(!folds.toggle(3, false));
+        assert_eq!(folds.closed(3), None);
+
+        assert!(folds.toggle(3, true));
+        assert_eq!(folds.closed(5), None);
+        assert_eq!(folds.closed(3), Some((2, 4)));
+
+        assert!(folds.toggle(5, true));
+        assert_eq!(folds.closed(3), Some((1, 6)));
+    }
+
+    #[test]
+    fn test_adjust() {
+        let mut folds = Folds::default();
+
+        folds.create(2, 4);
+        folds.create(6, 7);
+
+        folds.adjust(&Edit {
+            start: Cursor::new(0, 3),
+            end: Cursor::new(0, 3),
+            new_end: Cursor::new(1, 0),
+        });
+        assert_eq!(folds.closed(3), Some((3, 5)));
+
+        folds.adjust(&Edit {
+            start: Cursor::new(4, 0),
+            end: Cursor::new(7, 0),
+            new_end: Cursor::new(4, 0),
+        });
+        assert_eq!(folds.closed(3), Some((3, 4)));
+        assert_eq!(folds.closed(5), Some((4, 5)));
+    }
+
+    #[test]
+    fn test_travel() {
+        let mut folds = Folds::default();
+        folds.create(2, 4);
+
+        assert_eq!(folds.travel(1, 8, 1, true), Some(2));
+        assert_eq!(folds.travel(1, 8, 2, true), Some(5));
+        assert_eq!(folds.travel(3, 8, 1, true), Some(5));
+        assert_eq!(folds.travel(5, 8, 1, false), Some(2));
+        assert_eq!(folds.travel(6, 8, 9, false), Some(0));
+        assert_eq!(folds.travel(6, 8, 9, true), Some(7));
+
+        assert_eq!(folds.travel(7, 8, 1, true), None);
+        assert_eq!(folds.travel(3, 8, 1, false), Some(1));
+        assert_eq!(folds.travel(0, 8, 1, false), None);
+    }
+}
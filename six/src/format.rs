@@ -0,0 +1,111 @@
+//! Text formatting, as in `gq` and `=`.
+
+use itertools::Itertools;
+use unicode_width::UnicodeWidthChar;
+
+use crate::buffer::Buffer;
+
+/// Decides the indentation of lines, as in `=`.
+///
+/// Hosts can provide their own to indent according to the syntax of a language.
+pub trait Indenter: Send + Sync {
+    /// Returns the leading whitespace for a line of a buffer.
+    ///
+    /// When indenting several lines, the ones before have already been indented.
+    fn indent(&self, buffer: &Buffer, row: usize) -> String;
+}
+
+/// Indents each line like the closest non-blank line before it, as in Vim's `autoindent`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CopyIndent;
+
+impl Indenter for CopyIndent {
+    fn indent(&self, buffer: &Buffer, row: usize) -> String {
+        let previous = (0..row).rev().find(|&row| !buffer.is_blank_line(row));
+
+        previous.and_then(|row| buffer.line(row)).map_or_else(String::new, |line| {
+            line.chars().take_while(|ch| ch.is_whitespace()).collect()
+        })
+    }
+}
+
+/// Returns the number of screen columns spanned by some text at the start of a line.
+fn width(text: &str, tab_stop: usize) -> usize {
+    text.chars().fold(0, |col, ch| match ch {
+        '\t' => col + tab_stop - col % tab_stop,
+        _ => col + ch.width().unwrap_or(0),
+    })
+}
+
+/// Reflows each paragraph of some text into lines no wider than `width` screen columns, as in
+/// `gq`.
+///
+/// Paragraphs are separated by blank lines, which are kept as is. Lines are broken at whitespace,
+/// and every line of a paragraph takes the indentation of its first line. Words wider than `width`
+/// get lines of their own.
+#[must_use]
+pub fn reflow(text: &str, width: usize, tab_stop: usize) -> String {
+    let mut lines = Vec::new();
+
+    for (blank, group) in &text.split('\n').group_by(|line| line.trim().is_empty()) {
+        if blank {
+            lines.extend(group.map(str::to_owned));
+            continue;
+        }
+
+        let mut group = group.peekable();
+
+        let first = group.peek().copied().unwrap_or_default();
+        let indent = &first[..first.len() - first.trim_start().len()];
+
+        let mut line = String::new();
+
+        for word in group.flat_map(str::split_whitespace) {
+            if line.is_empty() {
+                line = format!("{}{}", indent, word);
+            } else if self::width(&line, tab_stop) + 1 + self::width(word, tab_stop) <= width {
+                line.push(' ');
+                line.push_str(word);
+            } else {
+                lines.push(std::mem::replace(&mut line, format!("{}{}", indent, word)));
+            }
+        }
+
+        lines.push(line);
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{reflow, CopyIndent, Indenter};
+    use crate::Buffer;
+
+    #[test]
+    fn test_copy_indent() {
+        let buffer = Buffer::from("foo\n  bar\n\n baz");
+
+        assert_eq!(CopyIndent.indent(&buffer, 0), "");
+        assert_eq!(CopyIndent.indent(&buffer, 1), "");
+        assert_eq!(CopyIndent.indent(&buffer, 2), "  ");
+        assert_eq!(CopyIndent.indent(&buffer, 3), "  ");
+    }
+
+    #[test]
+    fn test_reflow_long_line() {
+        let text = "  the quick brown fox jumps over the lazy dog";
+
+        assert_eq!(reflow(text, 16, 8), "  the quick\n  brown fox\n  jumps over the\n  lazy dog");
+        assert_eq!(reflow("\tfoo bar baz", 16, 8), "\tfoo bar\n\tbaz");
+        assert_eq!(reflow("foo supercalifragilistic bar", 8, 8), "foo\nsupercalifragilistic\nbar");
+    }
+
+    #[test]
+    fn test_reflow_paragraphs() {
+        let text = "foo\nbar\n  \nbaz\n  qux\n  quux";
+
+        assert_eq!(reflow(text, 79, 8), "foo bar\n  \nbaz qux quux");
+        assert_eq!(reflow(text, 8, 8), "foo bar\n  \nbaz qux\nquux");
+    }
+}
@@ -0,0 +1,75 @@
+//! Finding the definitions of identifiers, as in `gd`.
+
+use crate::buffer::Buffer;
+use crate::cursor::{Cursor, Object, Span};
+
+/// Finds where identifiers are defined, as in `gd`.
+///
+/// Hosts can provide their own to resolve definitions according to the syntax of a language.
+pub trait Resolver: Send + Sync {
+    /// Returns the position of the definition of an identifier used at a position, if it is in the
+    /// buffer.
+    ///
+    /// Hosts that find the definition elsewhere, as in another file, can take it from there and
+    /// return `None`.
+    fn definition(&self, buffer: &Buffer, word: &str, cursor: Cursor) -> Option<Cursor>;
+}
+
+/// Takes the first occurrence of an identifier in the buffer as its definition, as in Vim's `gd`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FirstOccurrence;
+
+impl Resolver for FirstOccurrence {
+    fn definition(&self, buffer: &Buffer, word: &str, _cursor: Cursor) -> Option<Cursor> {
+        let word: Vec<char> = word.chars().collect();
+        let is_keyword = |ch: Option<&char>| ch.map_or(false, |&ch| buffer.is_keyword(ch));
+
+        buffer.content().iter().enumerate().find_map(|(row, line)| {
+            let chars: Vec<char> = line.chars().collect();
+
+            let col = (0..chars.len()).find(|&col| {
+                chars[col..].starts_with(&word)
+                    && !is_keyword(col.checked_sub(1).and_then(|prev| chars.get(prev)))
+                    && !is_keyword(chars.get(col + word.len()))
+            })?;
+
+            Some(Cursor::new(row, col))
+        })
+    }
+}
+
+/// Returns the identifier at a position, if any.
+#[must_use]
+pub fn identifier(buffer: &Buffer, cursor: Cursor) -> Option<String> {
+    match Object::Word.span(buffer, cursor, false)? {
+        Span::Chars(start, end) => Some(buffer.slice(start..=end))
+            .filter(|word| !word.is_empty() && word.chars().all(|ch| buffer.is_keyword(ch))),
+
+        Span::Lines(..) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{identifier, FirstOccurrence, Resolver};
+    use crate::{Buffer, Cursor};
+
+    #[test]
+    fn test_first_occurrence() {
+        let buffer = Buffer::from("let foo_bar = 1;\nlet foo = 2;\nfoo + foo_bar");
+
+        let definition = |word| FirstOccurrence.definition(&buffer, word, Cursor::new(2, 0));
+
+        assert_eq!(definition("foo"), Some(Cursor::new(1, 4)));
+        assert_eq!(definition("foo_bar"), Some(Cursor::new(0, 4)));
+        assert_eq!(definition("baz"), None);
+    }
+
+    #[test]
+    fn test_identifier() {
+        let buffer = Buffer::from("foo + bar_baz");
+
+        assert_eq!(identifier(&buffer, Cursor::new(0, 9)), Some("bar_baz".to_owned()));
+        assert_eq!(identifier(&buffer, Cursor::new(0, 4)), None);
+    }
+}
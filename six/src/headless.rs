@@ -0,0 +1,32 @@
+//! Running the editor without a terminal, as for batch transforms of text.
+
+use crate::{Editor, Event};
+
+/// Edits a text with a sequence of events, returning the resulting text.
+#[must_use]
+pub fn run(text: &str, events: impl IntoIterator<Item = Event>) -> String {
+    let mut editor = Editor::with_text(text);
+
+    for event in events {
+        editor.advance(&[event]);
+    }
+
+    editor.buffer().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::run;
+    use crate::event::parse;
+
+    #[test]
+    fn test_run() {
+        let events = parse("wiBig<Esc>:lua buffer:insert('!')<CR>");
+        assert_eq!(run("hello world", events), "hello Bi!gworld");
+    }
+
+    #[test]
+    fn test_run_without_events() {
+        assert_eq!(run("foo\nbar", None), "foo\nbar");
+    }
+}
@@ -0,0 +1,79 @@
+use std::collections::VecDeque;
+
+/// The entries previously submitted to a prompt, such as command lines.
+#[derive(Debug, Default)]
+pub struct History(VecDeque<String>);
+
+impl History {
+    /// The maximum number of entries kept, as in Vim's `history`.
+    pub const CAPACITY: usize = 50;
+
+    /// Returns the number of entries.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns whether there are no entries.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the entry submitted `age` entries before the newest one, if any.
+    #[must_use]
+    pub fn get(&self, age: usize) -> Option<&str> {
+        let idx = self.0.len().checked_sub(age + 1)?;
+        self.0.get(idx).map(String::as_str)
+    }
+
+    /// Adds an entry, dropping the oldest one if there are too many.
+    ///
+    /// Empty entries and entries equal to the newest one are ignored.
+    pub fn push(&mut self, entry: impl Into<String>) {
+        let entry = entry.into();
+
+        if entry.is_empty() || self.get(0) == Some(entry.as_str()) {
+            return;
+        }
+
+        if self.0.len() == Self::CAPACITY {
+            self.0.pop_front();
+        }
+
+        self.0.push_back(entry);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::History;
+
+    #[test]
+    fn test_push() {
+        let mut history = History::default();
+
+        history.push("foo");
+        history.push("bar");
+        history.push("bar");
+        history.push("");
+
+        assert_eq!(history.len(), 2);
+        assert_eq!(history.get(0), Some("bar"));
+        assert_eq!(history.get(1), Some("foo"));
+        assert_eq!(history.get(2), None);
+    }
+
+    #[test]
+    fn test_capacity() {
+        let mut history = History::default();
+
+        for idx in 0..=History::CAPACITY {
+            history.push(idx.to_string());
+        }
+
+        assert_eq!(history.len(), History::CAPACITY);
+        assert_eq!(history.get(0), Some("50"));
+        assert_eq!(history.get(History::CAPACITY - 1), Some("1"));
+    }
+}
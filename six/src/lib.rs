@@ -9,13 +9,24 @@ extern crate derivative;
 extern crate bitflags;
 
 pub mod buffer;
+pub mod command;
 pub mod cursor;
 pub mod event;
+pub mod fold;
+pub mod format;
+pub mod goto;
+pub mod headless;
+pub mod history;
 pub mod mode;
+pub mod options;
+pub mod register;
+pub mod script;
 pub mod state;
+pub mod undo;
 
-pub use buffer::{Buffer, Content};
+pub use buffer::{Buffer, Content, LineEnding};
 pub use cursor::Cursor;
 pub use event::{Event, Key, Modifiers};
 pub use mode::Mode;
-pub use state::Editor;
+pub use register::{Register, Registers};
+pub use state::{Editor, Sign};
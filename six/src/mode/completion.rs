@@ -0,0 +1,87 @@
+/// A cycle through the completions of some typed text, as in the command line or `Ctrl-n`.
+#[derive(Debug)]
+pub struct Completion {
+    /// The text as typed before completing it.
+    typed: String,
+
+    /// The completions of the typed text.
+    candidates: Vec<String>,
+
+    /// The index of the current completion, or the number of completions for the typed text.
+    selected: usize,
+}
+
+impl Completion {
+    /// Starts a cycle at the first completion, unless there are none.
+    pub(super) fn new(typed: String, candidates: Vec<String>) -> Option<Self> {
+        Self::start(typed, candidates).map(Self::next)
+    }
+
+    /// Starts a cycle at the typed text, unless there are no completions.
+    pub(super) fn start(typed: String, candidates: Vec<String>) -> Option<Self> {
+        if candidates.is_empty() {
+            None
+        } else {
+            let selected = candidates.len();
+            Some(Self { typed, candidates, selected })
+        }
+    }
+
+    /// Advances to the next completion, going back to the typed text after the last one.
+    pub(super) fn next(self) -> Self {
+        let selected = (self.selected + 1) % (self.candidates.len() + 1);
+        Self { selected, ..self }
+    }
+
+    /// Goes back to the previous completion, going back to the typed text before the first one.
+    pub(super) fn previous(self) -> Self {
+        let selected = (self.selected + self.candidates.len()) % (self.candidates.len() + 1);
+        Self { selected, ..self }
+    }
+
+    /// Returns the current completion.
+    pub(super) fn current(&self) -> &str {
+        self.candidates.get(self.selected).map_or(&self.typed, String::as_str)
+    }
+
+    /// Returns the text as typed before completing it.
+    #[must_use]
+    pub fn typed(&self) -> &str {
+        &self.typed
+    }
+
+    /// Returns the completions of the typed text, as shown in a popup.
+    #[must_use]
+    pub fn candidates(&self) -> &[String] {
+        &self.candidates
+    }
+
+    /// Returns the index of the current completion, or `None` while the typed text is shown.
+    #[must_use]
+    pub fn selected(&self) -> Option<usize> {
+        Some(self.selected).filter(|&selected| selected < self.candidates.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Completion;
+
+    #[test]
+    fn test_cycle() {
+        let candidates = vec!["foo".to_owned(), "fob".to_owned()];
+        let completion = Completion::start("fo".to_owned(), candidates).unwrap();
+
+        assert_eq!(completion.current(), "fo");
+        assert_eq!(completion.selected(), None);
+
+        let completion = completion.previous();
+        assert_eq!(completion.current(), "fob");
+
+        let completion = completion.next().next();
+        assert_eq!(completion.current(), "foo");
+        assert_eq!(completion.selected(), Some(0));
+
+        assert!(Completion::new("fo".to_owned(), Vec::new()).is_none());
+    }
+}
@@ -1,66 +1,758 @@
+use itertools::Itertools;
+
+use crate::buffer::Buffer;
+use crate::cursor::{Cells, Cursor, Head, Metric, Tail};
 use crate::event::{Event, Key, Modifiers};
-use crate::mode::Mode;
+use crate::mode::{Completion, Mode};
+use crate::register::{Register, Registers};
 use crate::state::Context;
 
 use crate::mode::normal::Normal;
 
+/// The characters paired by auto-pairs, as the opening and the closing one.
+const PAIRS: &[(char, char)] = &[('(', ')'), ('[', ']'), ('{', '}'), ('"', '"'), ('\'', '\'')];
+
 /// The text insertion mode.
 #[derive(Derivative)]
 #[derivative(Debug)]
-pub struct Insert;
+pub struct Insert {
+    /// The number of times the inserted text should be repeated when leaving the mode.
+    count: usize,
+
+    /// The text inserted since entering the mode.
+    text: String,
+
+    /// The closing characters inserted by auto-pairs that are still ahead of the cursor, innermost
+    /// last.
+    pairs: Vec<char>,
+
+    /// The completions being cycled through with `Ctrl-n` and `Ctrl-p`, if any.
+    completion: Option<Completion>,
+}
 
 impl Insert {
-    pub fn new() -> Box<Self> {
-        Box::new(Self)
+    /// Returns a new instance of this mode, which repeats the inserted text `count` times.
+    #[must_use]
+    pub fn new(count: usize) -> Box<Self> {
+        Box::new(Self { count, text: String::new(), pairs: Vec::new(), completion: None })
+    }
+
+    /// Replaces the keyword just typed before the cursor with its abbreviation, if it has one.
+    fn expand_abbreviation(&mut self, context: &mut Context) {
+        let buffer = &mut context.buffer;
+        let cursor = buffer.cursor();
+
+        let start = match keyword_start(buffer) {
+            Some(start) => start,
+            None => return,
+        };
+
+        let keyword = buffer.slice(start..cursor);
+
+        // Only keywords typed whole since entering the mode are expanded.
+        if !self.text.ends_with(&keyword) {
+            return;
+        }
+
+        if let Some(expansion) = context.abbreviations.get(&keyword) {
+            let end = buffer.edit(expansion, start..cursor);
+
+            buffer.set_cursor(end);
+
+            self.text.truncate(self.text.len() - keyword.len());
+            self.text.push_str(expansion);
+        }
+    }
+
+    /// Returns the closing character to insert along with a typed one, if any.
+    ///
+    /// Quotes are only paired when they don't follow a keyword, so that apostrophes within words are
+    /// left alone.
+    fn closing(buffer: &Buffer, ch: char) -> Option<char> {
+        if !buffer.auto_pairs() {
+            return None;
+        }
+
+        let &(open, close) = PAIRS.iter().find(|&&(open, _)| open == ch)?;
+
+        let cursor = buffer.cursor();
+        let previous =
+            cursor.col().checked_sub(1).and_then(|col| char_at(buffer, cursor.row(), col));
+
+        if open == close && previous.map_or(false, |ch| buffer.is_keyword(ch)) {
+            None
+        } else {
+            Some(close)
+        }
+    }
+
+    /// Moves the cursor to the start of the word before it, as in readline's `Alt-b`, or right
+    /// past the end of the word after it if `forward` is set, as in `Alt-f`.
+    fn word(&mut self, buffer: &mut Buffer, forward: bool) {
+        let cursor = buffer.cursor();
+
+        let target = if forward {
+            // Starts a step back, so that a word ending right at the cursor still counts.
+            let from = buffer.cells(cursor).next_back().unwrap_or(cursor);
+            Tail::after(from, buffer).map(|tail| Cursor::new(tail.row(), tail.col() + 1))
+        } else {
+            Head::before(cursor, buffer)
+        };
+
+        if let Some(target) = target {
+            // The cursor is moving away from the closing characters.
+            self.pairs.clear();
+            buffer.set_cursor(target);
+        }
+    }
+
+    /// Deletes an empty pair around the cursor if its closing character was inserted
+    /// automatically, returning whether it did.
+    fn delete_pair(&mut self, buffer: &mut Buffer) -> bool {
+        let cursor = buffer.cursor();
+
+        let close = match self.pairs.last() {
+            Some(&close) if char_at(buffer, cursor.row(), cursor.col()) == Some(close) => close,
+            _ => return false,
+        };
+
+        let open = PAIRS.iter().find(|&&(_, other)| other == close).map(|&(open, _)| open);
+
+        match cursor.col().checked_sub(1) {
+            Some(col) if char_at(buffer, cursor.row(), col) == open => {
+                let start = Cursor::new(cursor.row(), col);
+                buffer.edit("", start..Cursor::new(cursor.row(), cursor.col() + 1));
+                buffer.set_cursor(start);
+
+                self.pairs.pop();
+                self.text.pop();
+                true
+            },
+
+            _ => false,
+        }
+    }
+
+    /// Replaces the keyword before the cursor with its next completion, or its previous one unless
+    /// `forward` is set, as in `Ctrl-n` and `Ctrl-p`.
+    ///
+    /// Cycling past either end goes back to the keyword as typed.
+    fn complete(&mut self, context: &mut Context, completion: Option<Completion>, forward: bool) {
+        let buffer = &context.buffer;
+        let cursor = buffer.cursor();
+
+        let start = match &completion {
+            Some(completion) => {
+                let len = completion.current().chars().count();
+                Cursor::new(cursor.row(), cursor.col().saturating_sub(len))
+            },
+
+            None => keyword_start(buffer).unwrap_or(cursor),
+        };
+
+        let completion = completion.or_else(|| {
+            let typed = buffer.slice(start..cursor);
+            let candidates = (context.complete)(context, &typed);
+
+            Completion::start(typed, candidates)
+        });
+
+        let completion = match completion {
+            Some(completion) if forward => completion.next(),
+            Some(completion) => completion.previous(),
+            None => return,
+        };
+
+        // Only the end of the keyword may have been typed since entering the mode, in which case
+        // the start it shares with its completions is left out of the inserted text.
+        let old = buffer.slice(start..cursor);
+        let typed = old.chars().rev().zip(self.text.chars().rev()).take_while(|(a, b)| a == b);
+        let typed = typed.count();
+
+        for _ in 0..typed {
+            self.text.pop();
+        }
+
+        self.text.extend(completion.current().chars().skip(old.chars().count() - typed));
+
+        let end = context.buffer.edit(completion.current(), start..cursor);
+        context.buffer.set_cursor(end);
+
+        self.completion = Some(completion);
+    }
+}
+
+/// Runs a single command of normal mode from insert mode, as in `Ctrl-o`, then goes back to
+/// inserting where the command left the cursor.
+#[derive(Debug)]
+struct Resume {
+    /// The insert mode to go back to.
+    insert: Box<Insert>,
+
+    /// The mode running the command.
+    mode: Box<dyn Mode>,
+}
+
+impl Mode for Resume {
+    fn name(&self) -> &str {
+        if self.mode.is_idle() {
+            "(Insert)"
+        } else {
+            self.mode.name()
+        }
+    }
+
+    fn input(&self) -> Option<String> {
+        self.mode.input()
+    }
+
+    fn advance(self: Box<Self>, context: &mut Context, event: Event) -> Box<dyn Mode> {
+        let Self { insert, mode } = *self;
+        let mode = mode.advance(context, event);
+
+        match event {
+            Event::Key(..) if mode.is_idle() => insert,
+            _ => Box::new(Self { insert, mode }),
+        }
     }
 }
 
+/// Returns the start of the keyword right before the cursor, if any.
+fn keyword_start(buffer: &Buffer) -> Option<Cursor> {
+    let cursor = buffer.cursor();
+    let row = buffer.line(cursor.row())?;
+
+    let start = (0..cursor.col())
+        .rev()
+        .take_while(|&col| row.get(col).map_or(false, |ch| buffer.is_keyword(ch)))
+        .last()?;
+
+    Some(Cursor::new(cursor.row(), start))
+}
+
+/// Returns the keywords of the buffer that complete a prefix, in the order they first appear, as
+/// in `Ctrl-n`.
+///
+/// The prefix itself is left out, since completing it would change nothing.
+#[must_use]
+pub fn keywords(context: &Context, prefix: &str) -> Vec<String> {
+    let buffer = &context.buffer;
+    let text = buffer.to_string();
+
+    text.split(|ch| !buffer.is_keyword(ch))
+        .filter(|word| word.starts_with(prefix) && word.len() > prefix.len())
+        .unique()
+        .map(str::to_owned)
+        .collect()
+}
+
+/// Returns the number of spaces right before the cursor that Backspace removes at once to reach
+/// the previous soft tab stop, if any.
+fn soft_tab_spaces(buffer: &Buffer) -> usize {
+    let width = buffer.soft_tab_stop();
+    let cursor = buffer.cursor();
+
+    if width == 0 || cursor.col() == 0 {
+        return 0;
+    }
+
+    let col = buffer.display_col(cursor);
+    let stop = (col - 1) / width * width;
+
+    let spaces = buffer.line(cursor.row()).map_or(0, |row| {
+        (0..cursor.col()).rev().take_while(|&col| row.get(col) == Some(' ')).count()
+    });
+
+    spaces.min(col - stop)
+}
+
+/// Returns the character at the specified position.
+fn char_at(buffer: &Buffer, row: usize, col: usize) -> Option<char> {
+    buffer.line(row)?.get(col)
+}
+
 impl Mode for Insert {
     fn name(&self) -> &str {
         "Insert"
     }
 
-    fn advance(self: Box<Self>, context: &mut Context, event: Event) -> Box<dyn Mode> {
-        unimplemented!()
-        // match event {
-        //     Event::Key(Key::Esc, _) => Normal::new(),
-
-        //     Event::Key(Key::Char(ch), Modifiers::NONE) => {
-        //         context.buffer.append(ch);
-        //         self
-        //     },
-
-        //     Event::Key(Key::Left, Modifiers::NONE) => {
-        //         context.buffer.backward::<Codepoint>();
-        //         self
-        //     },
-
-        //     Event::Key(Key::Up, Modifiers::NONE) => {
-        //         context.buffer.backward::<Line>();
-        //         self
-        //     },
-
-        //     Event::Key(Key::Left, Modifiers::CTRL) => {
-        //         context.buffer.backward::<Head>();
-        //         self
-        //     },
-
-        //     Event::Key(Key::Right, Modifiers::NONE) => {
-        //         context.buffer.forward::<Codepoint>();
-        //         self
-        //     },
-
-        //     Event::Key(Key::Down, Modifiers::NONE) => {
-        //         context.buffer.forward::<Line>();
-        //         self
-        //     },
-
-        //     Event::Key(Key::Right, Modifiers::CTRL) => {
-        //         context.buffer.forward::<Head>();
-        //         self
-        //     },
-
-        //     _ => self,
-        // }
+    fn completion(&self) -> Option<&Completion> {
+        self.completion.as_ref()
+    }
+
+    fn advance(mut self: Box<Self>, context: &mut Context, event: Event) -> Box<dyn Mode> {
+        let completion = self.completion.take();
+        let buffer = &mut context.buffer;
+
+        match event {
+            Event::Key(Key::Esc, _) => {
+                let cursor = buffer.cursor();
+
+                let repeated = self.text.repeat(self.count.saturating_sub(1));
+                let cursor = buffer.edit(&repeated, cursor..cursor);
+
+                buffer.set_cursor(Cursor::new(cursor.row(), cursor.col().saturating_sub(1)));
+                context.registers.set(Registers::INSERTED, Register::new(self.text, false));
+
+                Normal::new()
+            },
+
+            Event::Key(Key::Char('\t'), Modifiers::NONE) if buffer.expand_tab() => {
+                let width = match buffer.soft_tab_stop() {
+                    0 => buffer.tab_stop(),
+                    width => width,
+                };
+
+                let col = buffer.display_col(buffer.cursor());
+
+                for _ in 0..width - col % width {
+                    buffer.append(' ');
+                    self.text.push(' ');
+                }
+
+                self
+            },
+
+            Event::Key(Key::Char(ch @ 't'), Modifiers::CTRL)
+            | Event::Key(Key::Char(ch @ 'd'), Modifiers::CTRL) => {
+                buffer.shift(buffer.cursor().row(), ch == 't');
+                self
+            },
+
+            Event::Key(Key::Char('o'), Modifiers::CTRL) => {
+                // The cursor is about to move away from the closing characters.
+                self.pairs.clear();
+
+                Box::new(Resume { insert: self, mode: Normal::new() })
+            },
+
+            Event::Key(Key::Char('a'), Modifiers::CTRL) => {
+                let text = context.registers.get(Registers::INSERTED).map(|reg| reg.text.clone());
+
+                if let Some(text) = text {
+                    let buffer = &mut context.buffer;
+                    let cursor = buffer.cursor();
+
+                    let end = buffer.edit(&text, cursor..cursor);
+
+                    buffer.set_cursor(end);
+                    self.text.push_str(&text);
+                }
+
+                self
+            },
+
+            Event::Key(Key::Char(ch @ 'b'), Modifiers::META)
+            | Event::Key(Key::Char(ch @ 'f'), Modifiers::META) => {
+                self.word(buffer, ch == 'f');
+                self
+            },
+
+            Event::Key(Key::Char(ch @ 'n'), Modifiers::CTRL)
+            | Event::Key(Key::Char(ch @ 'p'), Modifiers::CTRL) => {
+                self.complete(context, completion, ch == 'n');
+                self
+            },
+
+            Event::Key(Key::Char(ch), Modifiers::NONE) => {
+                if !buffer.is_keyword(ch) {
+                    self.expand_abbreviation(context);
+                }
+
+                let buffer = &mut context.buffer;
+                let cursor = buffer.cursor();
+                let next = char_at(buffer, cursor.row(), cursor.col());
+
+                if self.pairs.last() == Some(&ch) && next == Some(ch) {
+                    // Types over the closing character inserted along with the opening one.
+                    self.pairs.pop();
+                    buffer.set_cursor(Cursor::new(cursor.row(), cursor.col() + 1));
+                } else {
+                    let close = Self::closing(buffer, ch);
+                    buffer.append(ch);
+
+                    if let Some(close) = close {
+                        buffer.insert(close, buffer.cursor());
+                        self.pairs.push(close);
+                    }
+                }
+
+                self.text.push(ch);
+                self
+            },
+
+            Event::Key(Key::Backspace, Modifiers::NONE) => {
+                if self.delete_pair(buffer) {
+                    return self;
+                }
+
+                for _ in 0..soft_tab_spaces(buffer).max(1) {
+                    let cursor = buffer.cursor();
+
+                    // At the start of a line, this removes the preceding line break, joining the
+                    // lines.
+                    if let Some(previous) = buffer.backward::<Cells>() {
+                        buffer.edit("", previous..cursor);
+                        self.text.pop();
+                    }
+                }
+
+                self
+            },
+
+            Event::Key(Key::Delete, Modifiers::NONE) => {
+                let cursor = buffer.cursor();
+
+                // At the end of a line, this removes the following line break, joining the lines.
+                if let Some(next) = buffer.cells(cursor).next() {
+                    buffer.edit("", cursor..next);
+                }
+
+                self
+            },
+
+            Event::Idle(_) => {
+                self.completion = completion;
+                self
+            },
+
+            Event::Key(..) => self,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::event::{Event, Key, Modifiers};
+    use crate::mode::{Mode, Normal};
+    use crate::state::Context;
+    use crate::{Buffer, Cursor, Editor};
+
+    fn run(text: &str, keys: &[Key]) -> Context {
+        run_with(Buffer::from(text), keys)
+    }
+
+    fn run_with(buffer: Buffer, keys: &[Key]) -> Context {
+        let events = keys.iter().map(|&key| Event::Key(key, Modifiers::NONE)).collect::<Vec<_>>();
+        run_events(buffer, &events)
+    }
+
+    fn run_events(buffer: Buffer, events: &[Event]) -> Context {
+        let mut context = Context { buffer, ..Context::default() };
+
+        events
+            .iter()
+            .fold(Normal::new() as Box<dyn Mode>, |mode, &event| mode.advance(&mut context, event));
+
+        context
+    }
+
+    #[test]
+    fn test_insert_count() {
+        let context = run("", &[Key::Char('3'), Key::Char('i'), Key::Char('x'), Key::Esc]);
+
+        assert_eq!(context.buffer.to_string(), "xxx");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 2));
+    }
+
+    #[test]
+    fn test_insert_count_newline() {
+        let keys = [Key::Char('2'), Key::Char('i'), Key::Char('a'), Key::Char('\n'), Key::Esc];
+        let context = run("b", &keys);
+
+        assert_eq!(context.buffer.to_string(), "a\na\nb");
+        assert_eq!(context.buffer.cursor(), Cursor::new(2, 0));
+    }
+
+    #[test]
+    fn test_backspace_joins_lines() {
+        let context = run("foo\nbar", &[Key::Char('w'), Key::Char('i'), Key::Backspace]);
+
+        assert_eq!(context.buffer.to_string(), "foobar");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 3));
+    }
+
+    #[test]
+    fn test_backspace_count() {
+        let keys = [
+            Key::Char('2'),
+            Key::Char('i'),
+            Key::Char('a'),
+            Key::Char('b'),
+            Key::Backspace,
+            Key::Esc,
+        ];
+        let context = run("", &keys);
+
+        assert_eq!(context.buffer.to_string(), "aa");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 1));
+    }
+
+    #[test]
+    fn test_delete_joins_lines() {
+        let context = run("foo\nbar", &[Key::Char('e'), Key::Char('a'), Key::Delete]);
+
+        assert_eq!(context.buffer.to_string(), "foobar");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 3));
+    }
+
+    #[test]
+    fn test_esc_one_char_line() {
+        let context = run("x", &[Key::Char('a'), Key::Esc]);
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 0));
+
+        let context = run("x", &[Key::Char('i'), Key::Esc]);
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 0));
+    }
+
+    #[test]
+    fn test_esc_empty_line() {
+        let context = run("foo\n", &[Key::Char('w'), Key::Char('i'), Key::Esc]);
+        assert_eq!(context.buffer.cursor(), Cursor::new(1, 0));
+
+        let context = run("", &[Key::Char('i'), Key::Char('\n'), Key::Esc]);
+        assert_eq!(context.buffer.cursor(), Cursor::new(1, 0));
+    }
+
+    #[test]
+    fn test_insert_mid_line() {
+        let context = run("fobar", &[Key::Char('e'), Key::Char('i'), Key::Char('!')]);
+        assert_eq!(context.buffer.to_string(), "foba!r");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 5));
+
+        let context = run("foobar", &[Key::Char('a'), Key::Char('\n')]);
+        assert_eq!(context.buffer.to_string(), "f\noobar");
+        assert_eq!(context.buffer.cursor(), Cursor::new(1, 0));
+    }
+
+    #[test]
+    fn test_tab() {
+        let context = run("ab", &[Key::Char('a'), Key::Char('\t'), Key::Esc]);
+        assert_eq!(context.buffer.to_string(), "a\tb");
+
+        for &(col, spaces) in &[(0, 4), (1, 3), (3, 1), (4, 4), (6, 2)] {
+            let mut buffer = Buffer::from("x".repeat(col).as_str());
+            buffer.set_cursor(Cursor::new(0, col));
+            buffer.set_expand_tab(true);
+            buffer.set_tab_stop(4);
+
+            let context = run_with(buffer, &[Key::Char('i'), Key::Char('\t'), Key::Esc]);
+
+            assert_eq!(context.buffer.to_string(), "x".repeat(col) + &" ".repeat(spaces));
+            assert_eq!(context.buffer.display_col(context.buffer.cursor()), col + spaces - 1);
+        }
+    }
+
+    #[test]
+    fn test_tab_after_tab() {
+        let mut buffer = Buffer::from("\tx");
+        buffer.set_cursor(Cursor::new(0, 2));
+        buffer.set_expand_tab(true);
+        buffer.set_tab_stop(4);
+
+        let context = run_with(buffer, &[Key::Char('i'), Key::Char('\t'), Key::Esc]);
+        assert_eq!(context.buffer.to_string(), "\tx   ");
+    }
+
+    #[test]
+    fn test_soft_tab_stop() {
+        let mut buffer = Buffer::from("");
+        buffer.set_expand_tab(true);
+        buffer.set_soft_tab_stop(4);
+
+        let keys = [Key::Char('i'), Key::Char('\t'), Key::Char('\t'), Key::Backspace, Key::Esc];
+        let context = run_with(buffer, &keys);
+        assert_eq!(context.buffer.to_string(), "    ");
+
+        let mut buffer = Buffer::from("ab     ");
+        buffer.set_cursor(Cursor::new(0, 7));
+        buffer.set_soft_tab_stop(4);
+
+        let keys = [Key::Char('i'), Key::Backspace, Key::Backspace, Key::Backspace, Key::Esc];
+        let context = run_with(buffer, &keys);
+        assert_eq!(context.buffer.to_string(), "a");
+    }
+
+    #[test]
+    fn test_shift() {
+        let ctrl = |ch| Event::Key(Key::Char(ch), Modifiers::CTRL);
+        let key = |ch| Event::Key(Key::Char(ch), Modifiers::NONE);
+        let esc = Event::Key(Key::Esc, Modifiers::NONE);
+
+        let mut buffer = Buffer::from("foo");
+        buffer.set_expand_tab(true);
+        buffer.set_shift_width(2);
+
+        let context = run_events(buffer, &[key('a'), ctrl('t'), ctrl('t'), key('x'), esc]);
+
+        assert_eq!(context.buffer.to_string(), "    fxoo");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 5));
+
+        let mut buffer = Buffer::from("\tfoo");
+        buffer.set_cursor(Cursor::new(0, 4));
+        buffer.set_shift_width(4);
+
+        let context = run_events(buffer, &[key('i'), ctrl('d'), key('x'), ctrl('d'), ctrl('d')]);
+
+        assert_eq!(context.buffer.to_string(), "foox");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 4));
+    }
+
+    #[test]
+    fn test_abbreviation() {
+        let mut context = Context::default();
+        context.abbreviations.insert("teh".to_owned(), "the".to_owned());
+
+        let keys = "ateh tehx teh".chars().map(|ch| Event::Key(Key::Char(ch), Modifiers::NONE));
+        let mode = keys
+            .fold(Normal::new() as Box<dyn Mode>, |mode, event| mode.advance(&mut context, event));
+
+        assert_eq!(context.buffer.to_string(), "the tehx teh");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 12));
+
+        let _ = mode.advance(&mut context, Event::Key(Key::Char(' '), Modifiers::NONE));
+
+        assert_eq!(context.buffer.to_string(), "the tehx the ");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 13));
+    }
+
+    #[test]
+    fn test_auto_pairs() {
+        let mut buffer = Buffer::from("");
+        buffer.set_auto_pairs(true);
+
+        let context = run_with(buffer, &[Key::Char('i'), Key::Char('('), Key::Char('[')]);
+
+        assert_eq!(context.buffer.to_string(), "([])");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 2));
+
+        let context = run("", &[Key::Char('i'), Key::Char('(')]);
+        assert_eq!(context.buffer.to_string(), "(");
+    }
+
+    #[test]
+    fn test_auto_pairs_close() {
+        let mut buffer = Buffer::from("");
+        buffer.set_auto_pairs(true);
+
+        let keys = "i(x)\"y\")".chars().map(Key::Char).collect::<Vec<_>>();
+        let context = run_with(buffer, &keys);
+
+        assert_eq!(context.buffer.to_string(), "(x)\"y\")");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 7));
+
+        let mut buffer = Buffer::from(")");
+        buffer.set_auto_pairs(true);
+
+        let context = run_with(buffer, &[Key::Char('i'), Key::Char(')')]);
+        assert_eq!(context.buffer.to_string(), "))");
+    }
+
+    #[test]
+    fn test_auto_pairs_backspace() {
+        let mut buffer = Buffer::from("");
+        buffer.set_auto_pairs(true);
+
+        let keys = [
+            Key::Char('i'),
+            Key::Char('{'),
+            Key::Char('('),
+            Key::Backspace,
+            Key::Char('x'),
+            Key::Backspace,
+            Key::Backspace,
+        ];
+        let context = run_with(buffer, &keys);
+
+        assert_eq!(context.buffer.to_string(), "");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 0));
+
+        let mut buffer = Buffer::from("don");
+        buffer.set_cursor(Cursor::new(0, 3));
+        buffer.set_auto_pairs(true);
+
+        let context = run_with(buffer, &[Key::Char('i'), Key::Char('\''), Key::Char('t')]);
+        assert_eq!(context.buffer.to_string(), "don't");
+    }
+
+    #[test]
+    fn test_complete() {
+        let mut editor = Editor::with_text("foobar foobaz\nbar\n");
+
+        editor.feed("jjifo<C-n>");
+        assert_eq!(editor.buffer().to_string(), "foobar foobaz\nbar\nfoobar");
+
+        let completion = editor.completion().unwrap();
+        assert_eq!(completion.candidates(), ["foobar", "foobaz"]);
+        assert_eq!(completion.selected(), Some(0));
+
+        editor.feed("<C-n>");
+        assert_eq!(editor.buffer().to_string(), "foobar foobaz\nbar\nfoobaz");
+
+        editor.feed("<C-n>");
+        assert_eq!(editor.buffer().to_string(), "foobar foobaz\nbar\nfo");
+        assert_eq!(editor.completion().unwrap().selected(), None);
+
+        editor.feed("<C-p>!");
+        assert_eq!(editor.buffer().to_string(), "foobar foobaz\nbar\nfoobaz!");
+        assert!(editor.completion().is_none());
+    }
+
+    #[test]
+    fn test_complete_repeated() {
+        let mut editor = Editor::with_text("foobar\n");
+
+        editor.feed("j2ifo<C-p> <Esc>");
+        assert_eq!(editor.buffer().to_string(), "foobar\nfoobar foobar ");
+    }
+
+    #[test]
+    fn test_inserted_register() {
+        let mut editor = Editor::with_text("bar");
+
+        editor.feed("ifoo<Esc>\".p");
+        assert_eq!(editor.buffer().to_string(), "foofoobar");
+
+        let mut editor = Editor::with_text("bar");
+
+        editor.feed("ifoo<Esc>i<C-a>!<Esc>");
+        assert_eq!(editor.buffer().to_string(), "fofoo!obar");
+
+        editor.feed("\".P");
+        assert_eq!(editor.buffer().to_string(), "fofoofoo!!obar");
+    }
+
+    #[test]
+    fn test_word_motions() {
+        let mut editor = Editor::with_text("foo bar baz");
+
+        editor.feed("A<M-b>x<M-b><M-b>y");
+        assert_eq!(editor.buffer().to_string(), "foo ybar xbaz");
+
+        editor.feed("<M-f>z<M-f><M-f>!");
+        assert_eq!(editor.buffer().to_string(), "foo ybarz xbaz!");
+        assert_eq!(editor.mode(), "Insert");
+    }
+
+    #[test]
+    fn test_resume() {
+        let mut editor = Editor::with_text("bar baz");
+
+        editor.feed("wifoo<C-o>");
+        assert_eq!(editor.mode(), "(Insert)");
+
+        editor.feed("0");
+        assert_eq!(editor.mode(), "Insert");
+
+        editor.feed("x<Esc>");
+        assert_eq!(editor.buffer().to_string(), "xbar foobaz");
+
+        let mut editor = Editor::with_text("foo\nbar");
+
+        editor.feed("ix<C-o>d");
+        assert_eq!(editor.mode(), "Delete");
+
+        editor.feed("dy<Esc>");
+        assert_eq!(editor.buffer().to_string(), "ybar");
     }
 }
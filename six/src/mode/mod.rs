@@ -1,25 +1,87 @@
 use std::fmt::Debug;
+use std::time::Duration;
 
 use crate::event::Event;
 use crate::state::Context;
 
+mod completion;
 mod insert;
 mod normal;
 mod operator;
 mod query;
 mod select;
 
-pub use insert::Insert;
+pub use completion::Completion;
+pub use insert::{keywords, Insert};
 pub use normal::Normal;
 pub use operator::Operator;
-pub use query::Query;
-pub use select::Select;
+pub use query::{Complete, Query};
+pub use select::{Kind, Select, Selection};
+
+/// Returns whether a pending command of several keys, such as `gg` or an operator, is given up after
+/// going without input for `idle`, as with Vim's `timeoutlen`.
+fn timed_out(idle: Duration, timeout_len: Duration) -> bool {
+    idle >= timeout_len
+}
 
 pub trait Mode: Debug + Send + Sync {
     /// Returns an user-friendly name for the mode.
     fn name(&self) -> &str;
 
+    /// Returns the text typed so far, if the mode queries the user for any.
+    fn input(&self) -> Option<String> {
+        None
+    }
+
+    /// Returns the completions being cycled through, if any.
+    fn completion(&self) -> Option<&Completion> {
+        None
+    }
+
+    /// Returns whether the mode is between commands, with nothing typed towards the next one, as
+    /// normal mode after a command.
+    fn is_idle(&self) -> bool {
+        false
+    }
+
     /// Advances the state state by handling an event.
     #[must_use]
     fn advance(self: Box<Self>, context: &mut Context, event: Event) -> Box<dyn Mode>;
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::timed_out;
+    use crate::{Editor, Event};
+
+    #[test]
+    fn test_timed_out() {
+        let second = Duration::from_secs(1);
+
+        assert!(!timed_out(Duration::from_millis(250), second));
+        assert!(timed_out(Duration::from_millis(1000), second));
+        assert!(timed_out(Duration::from_millis(250), Duration::from_millis(100)));
+        assert!(timed_out(Duration::from_secs(0), Duration::from_secs(0)));
+    }
+
+    #[test]
+    fn test_pending_timeout() {
+        let mut editor = Editor::with_text("foo\nbar");
+
+        editor.feed("jg");
+        editor.advance(&[Event::Idle(Duration::from_millis(500))]);
+        editor.feed("g");
+        assert_eq!(editor.cursor().row(), 0);
+
+        editor.feed("d");
+        editor.advance(&[Event::Idle(Duration::from_millis(1500))]);
+        assert_eq!(editor.mode(), "Normal");
+
+        editor.feed(":set tm=200<CR>jg");
+        editor.advance(&[Event::Idle(Duration::from_millis(500))]);
+        editor.feed("g");
+        assert_eq!(editor.cursor().row(), 1);
+    }
+}
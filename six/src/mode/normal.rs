@@ -1,102 +1,1503 @@
-use std::ops::Bound;
+use std::convert::TryFrom;
+use std::ops::{Bound, Range};
+use std::sync::Arc;
 
-use crate::cursor::Cursor;
-use crate::event::{Event, Key, Modifiers};
-use crate::mode::{Insert, Mode, Operator, Query};
+use itertools::Itertools;
+
+use crate::buffer::{Buffer, Row};
+use crate::command;
+use crate::cursor::{Cursor, Find, Head, Paragraphs, Tail};
+use crate::event::{self, Event, Key, Modifiers};
+use crate::format;
+use crate::goto;
+use crate::mode::operator::lines;
+use crate::mode::{timed_out, Insert, Kind, Mode, Operator, Query, Select};
+use crate::register::{Recording, Register, Registers};
 use crate::state::Context;
 
 /// The default editor mode.
 #[derive(Derivative)]
 #[derivative(Debug)]
-pub struct Normal;
+pub struct Normal {
+    /// The number of times the next command should be repeated, if given.
+    count: Option<usize>,
+
+    /// The first key of a pending multi-key command, such as `g`.
+    prefix: Option<char>,
+
+    /// The register used by the next command, as chosen with `"`.
+    register: Option<char>,
+}
+
+/// The side of the cursor in which text is put.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Side {
+    Before,
+    After,
+}
 
 impl Normal {
     /// Returns a new instance of this mode.
+    #[must_use]
     pub fn new() -> Box<Self> {
-        Box::new(Self)
+        Box::new(Self { count: None, prefix: None, register: None })
+    }
+
+    /// Returns the number of times the next command should be repeated.
+    fn count(&self) -> usize {
+        self.count.unwrap_or(1)
+    }
+
+    /// Returns the register used by the next command.
+    fn register(&self) -> char {
+        self.register.unwrap_or(Registers::UNNAMED)
+    }
+}
+
+/// Returns the contents of a register, where the path register holds the path of the buffer and
+/// the clipboard registers hold the text of the clipboard of the context.
+///
+/// Text from the clipboard is taken as whole lines if it ends with a line break.
+pub(super) fn register(context: &Context, name: char) -> Option<Register> {
+    match name {
+        Registers::PATH => {
+            let path = context.buffer.path()?;
+            Some(Register::new(path.to_string_lossy(), false))
+        },
+
+        Registers::CLIPBOARD | Registers::SELECTION => {
+            let text = context.clipboard.get()?;
+            let linewise = text.ends_with('\n');
+
+            Some(Register::new(text, linewise))
+        },
+
+        _ => context.registers.get(name).cloned(),
+    }
+}
+
+/// Puts the contents of a register `count` times next to the cursor.
+///
+/// Linewise text is put in the lines above or below the cursor, while characterwise text is put
+/// right before or after it. Returns the start of the new text and the position right after it.
+fn put(context: &mut Context, name: char, count: usize, side: Side) -> Option<(Cursor, Cursor)> {
+    let register = register(context, name)?;
+    let buffer = &mut context.buffer;
+
+    let cursor = buffer.cursor();
+
+    if register.linewise {
+        let lines = register.text.strip_suffix('\n').unwrap_or(&register.text);
+        let text = std::iter::repeat(lines).take(count).join("\n");
+
+        Some(match side {
+            Side::Before => {
+                let start = Cursor::new(cursor.row(), 0);
+                (start, buffer.edit(&format!("{}\n", text), start..start))
+            },
+
+            Side::After => {
+                let eol = buffer.clamp(Cursor::new(cursor.row(), usize::MAX));
+                let end = buffer.edit(&format!("\n{}", text), eol..eol);
+
+                (Cursor::new(cursor.row() + 1, 0), Cursor::new(end.row() + 1, 0))
+            },
+        })
+    } else {
+        let text = register.text.repeat(count);
+
+        let start = match side {
+            Side::Before => cursor,
+            Side::After => buffer.clamp(Cursor::new(cursor.row(), cursor.col() + 1)),
+        };
+
+        Some((start, buffer.edit(&text, start..start)))
+    }
+}
+
+/// Moves the cursor to the `count`th match of a character search, as in `f`, or as in `;` if
+/// `repeat` is set.
+///
+/// Unless `repeat` is set, the search is remembered for `;` and `,`.
+fn jump(context: &mut Context, find: Find, count: usize, repeat: bool) {
+    let buffer = &mut context.buffer;
+
+    if let Some(cursor) = find.locate(buffer, buffer.cursor(), count, repeat) {
+        buffer.set_cursor(cursor);
+    }
+
+    if !repeat {
+        context.find = Some(find);
     }
 }
 
-// fn surround(start: Bound<Cursor>, end: Bound<Cursor>) -> Box<dyn Mode> {
-//     // use Bound::{Excluded, Included, Unbounded};
+/// Moves the cursor `count` times, over words as in `w`, `b` and `e`, over paragraphs as in `}` and
+/// `{`, over lines as in `j` and `k`, or to the matches of the last character search as in `;` and
+/// `,`, or to a line as in `G`, or within the line as in `0`, `^` and `$`.
+fn motion(context: &mut Context, ch: char, count: Option<usize>) {
+    match ch {
+        'G' => goto_line(context, count),
+
+        '0' | '^' | '$' => {
+            let buffer = &mut context.buffer;
+            let row = buffer.cursor().row();
+
+            let col = match ch {
+                '0' => 0,
+                '^' => buffer.line(row).map_or(0, Row::first_non_blank),
+                _ => buffer.line(row).map_or(0, |line| line.len().saturating_sub(1)),
+            };
 
-//     let surround = move |context: &mut Context, sandwich: &str| -> Box<dyn Mode> {
-//         let mut sandwich = sandwich.chars();
+            buffer.set_cursor(Cursor::new(row, col));
+        },
 
-//         let prefix = sandwich.next().expect("prefix");
-//         let suffix = sandwich.next().expect("suffix");
+        'j' | 'k' => vertical(context, count.unwrap_or(1), ch == 'j'),
+
+        ';' | ',' => {
+            if let Some(find) = context.find {
+                let find = if ch == ';' { find } else { find.reversed() };
+                jump(context, find, count.unwrap_or(1), true);
+            }
+        },
+
+        _ => text_motion(&mut context.buffer, ch, count.unwrap_or(1)),
+    }
+}
+
+/// Returns whether a key enters insert mode, which is refused up front if the buffer is not
+/// modifiable, as in Vim.
+///
+/// Other changes are refused by the buffer itself, and reported by the editor.
+fn inserts(key: Key, modifiers: Modifiers) -> bool {
+    match (key, modifiers) {
+        (Key::Char(ch), Modifiers::NONE) => "iaIAc".contains(ch),
+        _ => false,
+    }
+}
 
-//         // context.buffer.insert(suffix, end);
-//         // context.buffer.insert(prefix, start);
+/// Enters insert mode before the cursor, as in `i`, after it, as in `a`, before the first non-blank
+/// character of the line, as in `I`, or at its end, as in `A`.
+///
+/// On lines that are empty or only made of blanks, `I` inserts at the end of the line, like `A`.
+fn insert(context: &mut Context, ch: char, count: usize) -> Box<dyn Mode> {
+    let buffer = &mut context.buffer;
+
+    let cursor = buffer.cursor();
+    let line = buffer.line(cursor.row());
+
+    let col = match ch {
+        'a' => cursor.col() + 1,
+        'I' => line.map_or(0, Row::first_non_blank),
+        'A' => line.map_or(0, Row::len),
+        _ => cursor.col(),
+    };
+
+    buffer.set_cursor(buffer.clamp(Cursor::new(cursor.row(), col)));
+    Insert::new(count)
+}
+
+/// Returns the prompt for command lines, as in `:`.
+fn command_line() -> Box<dyn Mode> {
+    Query::new("Command", None, |context, line| {
+        if let Err(message) = command::execute(context, line) {
+            context.message = Some(message);
+        }
+
+        Normal::new()
+    })
+    .with_completion(command::complete)
+}
+
+/// Moves the cursor over words `count` times, as in `w`, `b` and `e`, or over paragraphs, as in `}`
+/// and `{`.
+pub(super) fn text_motion(buffer: &mut Buffer, ch: char, count: usize) {
+    for _ in 0..count {
+        let moved = match ch {
+            'w' => buffer.forward::<Head>(),
+            'b' => buffer.backward::<Head>(),
+            '}' => buffer.forward::<Paragraphs>(),
+            '{' => buffer.backward::<Paragraphs>(),
+            _ => buffer.forward::<Tail>(),
+        };
+
+        if moved.is_none() {
+            break;
+        }
+    }
+}
 
-//         // context.buffer.set_cursor(start);
+/// Returns the first and last rows touched by a range given to an operator.
+///
+/// A range ending at the start of a line, as with line-wise motions, doesn't touch that line.
+fn rows(buffer: &Buffer, start: Bound<Cursor>, end: Bound<Cursor>) -> (usize, usize) {
+    let first = match start {
+        Bound::Included(cursor) | Bound::Excluded(cursor) => cursor.row(),
+        Bound::Unbounded => 0,
+    };
 
-//         Normal::new()
-//     };
+    let last = match end {
+        Bound::Excluded(cursor) if cursor.col() == 0 => cursor.row().saturating_sub(1),
+        Bound::Included(cursor) | Bound::Excluded(cursor) => cursor.row(),
+        Bound::Unbounded => buffer.content().len() - 1,
+    };
+
+    (first, last.max(first))
+}
+
+/// Returns the operator started by a key, such as `d`, which copies text into the register of
+/// the given name.
+fn operator(key: char, count: usize, register: char) -> Box<dyn Mode> {
+    match key {
+        '=' => indent(count),
+        '>' | '<' => shift(key, count),
+        'c' => change(count, register),
+        _ => delete(key, count, register),
+    }
+}
+
+/// Returns the operator that copies text into a register, and deletes it as in `d` unless `key`
+/// is `y`.
+fn delete(key: char, count: usize, register: char) -> Box<dyn Mode> {
+    let name = if key == 'y' { "Yank" } else { "Delete" };
+
+    Operator::new(name, key, count, move |context, start, end, kind| {
+        cut(context, key, register, start, end, kind);
+        Normal::new()
+    })
+}
+
+/// Returns the operator that copies text into a register and deletes it, then enters insert mode
+/// in its place, as in `c`.
+///
+/// Whole lines are emptied rather than deleted, as in `cc`, so that the first of them is left to
+/// insert into, keeping its indentation as with Vim's `autoindent`.
+fn change(count: usize, register: char) -> Box<dyn Mode> {
+    Operator::new("Change", 'c', count, move |context, start, end, kind| {
+        if kind == Kind::Line {
+            let (first, last) = rows(&context.buffer, start, end);
+            let mut text = context.buffer.slice((start, end));
+
+            if !text.ends_with('\n') {
+                text.push('\n');
+            }
+
+            context.store(register, Register::new(text, true));
+
+            let buffer = &mut context.buffer;
+            let indent = buffer.line(first).map_or(0, Row::first_non_blank);
+            let eol = buffer.clamp(Cursor::new(last, usize::MAX));
+
+            buffer.edit("", Cursor::new(first, indent)..eol);
+            buffer.set_cursor(Cursor::new(first, indent));
+        } else {
+            let cursor = match start {
+                Bound::Included(cursor) | Bound::Excluded(cursor) => cursor,
+                Bound::Unbounded => Cursor::origin(),
+            };
+
+            cut(context, 'c', register, start, end, kind);
+            context.buffer.set_cursor(cursor);
+        }
+
+        Insert::new(1)
+    })
+    .with_change()
+}
+
+/// Repeats the last substitution in the line of the cursor, as in `&`, or with its flags in every
+/// line, as in `g&`.
+fn resubstitute(context: &mut Context, everywhere: bool) -> Box<dyn Mode> {
+    let row = context.buffer.cursor().row();
+    let last = context.buffer.content().len() - 1;
+
+    let (first, last) = if everywhere { (0, last) } else { (row, row) };
+
+    if let Err(message) = command::resubstitute(context, first, last, everywhere) {
+        context.message = Some(message);
+    }
+
+    Normal::new()
+}
+
+/// Repeats the last change, as in `.`, with the count given instead of its own, if any, as in `3.`,
+/// which is then kept for later repeats.
+///
+/// The keys of the change are run through normal mode again, where a command left pending once
+/// they run out is cancelled.
+fn repeat(context: &mut Context, count: Option<usize>) -> Box<dyn Mode> {
+    let change = match &mut context.change {
+        Some(change) => {
+            change.count = count.or(change.count);
+            change.clone()
+        },
+
+        None => return Normal::new(),
+    };
+
+    let count = change.count.map(|count| count.to_string()).unwrap_or_default();
+    let events = event::parse(&format!("{}{}", count, change.keys));
+
+    let mode = events
+        .into_iter()
+        .fold(Normal::new() as Box<dyn Mode>, |mode, event| mode.advance(context, event));
+
+    let _ = mode.advance(context, Event::Key(Key::Esc, Modifiers::NONE));
+    Normal::new()
+}
+
+/// Moves through the undo history `count` times, undoing or redoing changes along a branch of the
+/// undo tree, as in `u` and `Ctrl-r`, or going to earlier or later states in time if
+/// `chronological` is set, as in `g-` and `g+`.
+fn undo(context: &mut Context, count: usize, forward: bool, chronological: bool) -> Box<dyn Mode> {
+    let step = match (forward, chronological) {
+        (false, false) => Buffer::undo,
+        (true, false) => Buffer::redo,
+        (false, true) => Buffer::earlier,
+        (true, true) => Buffer::later,
+    };
+
+    if !step(&mut context.buffer) {
+        let limit = if forward { "newest" } else { "oldest" };
+        context.message = Some(format!("Already at {} change", limit));
+    }
+
+    for _ in 1..count {
+        step(&mut context.buffer);
+    }
+
+    Normal::new()
+}
+
+/// Stops the recording in progress, as in `q`, storing the keys typed before it, or otherwise
+/// starts recording typed keys into a register, as in `qa`.
+///
+/// Only registers named by lowercase letters can be recorded into.
+fn record(context: &mut Context, register: char) -> Box<dyn Mode> {
+    match context.recording.take() {
+        Some(Recording { register, keys }) => {
+            let keys = keys.strip_suffix('q').unwrap_or(&keys);
+            context.registers.set(register, Register::new(keys, false));
+        },
+
+        None if register.is_ascii_lowercase() => {
+            context.recording = Some(Recording { register, keys: String::new() });
+        },
+
+        None => {},
+    }
+
+    Normal::new()
+}
+
+/// Copies `count` lines starting at the cursor's into a register, as in `Y`.
+///
+/// With the `yank_to_end` option, only the text from the cursor to the end of the last line is
+/// copied, as in `y$`, and otherwise whole lines are, as in `yy`.
+fn yank_lines(context: &mut Context, count: usize, register: char) -> Box<dyn Mode> {
+    let buffer = &context.buffer;
+    let cursor = buffer.cursor();
+
+    let last = (cursor.row() + count - 1).min(buffer.content().len() - 1);
+
+    let (start, end, kind) = if context.options.yank_to_end {
+        let end = buffer.clamp(Cursor::new(last, usize::MAX));
+        (Bound::Included(cursor), Bound::Excluded(end), Kind::Char)
+    } else {
+        lines(buffer, cursor.row(), last)
+    };
+
+    cut(context, 'y', register, start, end, kind);
+    context.buffer.set_cursor(cursor);
+
+    Normal::new()
+}
+
+/// Copies a range into a register, as in [`store`], and deletes it unless `key` is `y`, as in `d`
+/// and `y`.
+///
+/// Deleting whole lines leaves the cursor on the first non-blank of the line after them, while
+/// otherwise the cursor goes to the start of the text.
+pub(super) fn cut(
+    context: &mut Context,
+    key: char,
+    register: char,
+    start: Bound<Cursor>,
+    end: Bound<Cursor>,
+    kind: Kind,
+) {
+    let buffer = &mut context.buffer;
+    let linewise = kind == Kind::Line;
+
+    let (first, _) = rows(buffer, start, end);
+    let cursor = buffer.cursor();
+
+    let mut text = buffer.slice((start, end));
+
+    if linewise && !text.ends_with('\n') {
+        text.push('\n');
+    }
 
-//     Query::new("Surround", Some(2), surround)
-// }
+    context.store(register, Register::new(text, linewise));
+
+    let buffer = &mut context.buffer;
+    let range = (start, end);
+    let start = match start {
+        Bound::Included(cursor) | Bound::Excluded(cursor) => cursor,
+        Bound::Unbounded => Cursor::origin(),
+    };
+
+    let cursor = match (key, linewise) {
+        ('y', false) => start,
+        ('y', true) => Cursor::new(first, cursor.col()),
+
+        (_, false) => {
+            buffer.edit("", range);
+            start
+        },
+
+        (_, true) => {
+            if end == Bound::Unbounded && first > 0 {
+                // Take the line break before the lines, as there is none after them.
+                let eol = buffer.clamp(Cursor::new(first - 1, usize::MAX));
+                buffer.edit("", eol..);
+            } else {
+                buffer.edit("", range);
+            }
+
+            let row = first.min(buffer.content().len() - 1);
+            Cursor::new(row, buffer.line(row).map_or(0, Row::first_non_blank))
+        },
+    };
+
+    buffer.set_cursor(cursor.clamp_to_line(buffer));
+}
+
+/// Returns the operator that toggles the comment of each line, as in `gc`.
+fn comment(count: usize) -> Box<dyn Mode> {
+    Operator::new("Comment", 'c', count, |context, start, end, _| {
+        comment_lines(context, start, end);
+        Normal::new()
+    })
+}
+
+/// Toggles the comment of each line in a range, as in `gc`.
+///
+/// Lines without the comment string of the buffer get it, followed by a space, after their
+/// indentation, while lines with it lose it along with a space after it. Blank lines are left
+/// alone. Leaves the cursor on the first non-blank of the first line.
+pub(super) fn comment_lines(context: &mut Context, start: Bound<Cursor>, end: Bound<Cursor>) {
+    let buffer = &mut context.buffer;
+    let (first, last) = rows(buffer, start, end);
+
+    let comment = buffer.comment().to_owned();
+
+    for row in first..=last {
+        let (col, text) = match buffer.line(row) {
+            Some(line) if !buffer.is_blank_line(row) => {
+                let col = line.first_non_blank();
+                (col, line.chars().skip(col).collect::<String>())
+            },
+
+            _ => continue,
+        };
+
+        let at = Cursor::new(row, col);
+
+        if let Some(rest) = text.strip_prefix(comment.as_str()) {
+            let len = comment.chars().count() + usize::from(rest.starts_with(' '));
+            buffer.edit("", at..Cursor::new(row, col + len));
+        } else {
+            buffer.edit(&format!("{} ", comment), at..at);
+        }
+    }
+
+    let col = buffer.line(first).map_or(0, Row::first_non_blank);
+    buffer.set_cursor(Cursor::new(first, col).clamp_to_line(buffer));
+}
+
+/// Returns the operator that shifts the indentation of lines, as in `>` and `<`.
+fn shift(key: char, count: usize) -> Box<dyn Mode> {
+    Operator::new("Shift", key, count, move |context, start, end, _| {
+        shift_lines(context, start, end, key == '>');
+        Normal::new()
+    })
+}
+
+/// Shifts the indentation of the lines in a range to the right, as in `>`, or to the left, as in
+/// `<`.
+///
+/// Blank lines are left alone. Leaves the cursor on the first non-blank of the first line.
+pub(super) fn shift_lines(
+    context: &mut Context,
+    start: Bound<Cursor>,
+    end: Bound<Cursor>,
+    right: bool,
+) {
+    let buffer = &mut context.buffer;
+    let (first, last) = rows(buffer, start, end);
+
+    for row in first..=last {
+        if !buffer.is_blank_line(row) {
+            buffer.shift(row, right);
+        }
+    }
+
+    let col = buffer.line(first).map_or(0, Row::first_non_blank);
+    buffer.set_cursor(Cursor::new(first, col).clamp_to_line(buffer));
+}
+
+/// Returns the operator that re-indents lines with the indenter of the context, as in `=`.
+fn indent(count: usize) -> Box<dyn Mode> {
+    Operator::new("Indent", '=', count, |context, start, end, _| {
+        indent_lines(context, start, end);
+        Normal::new()
+    })
+}
+
+/// Re-indents the lines in a range with the indenter of the context, as in `=`.
+///
+/// Blank lines are left alone. Leaves the cursor on the first non-blank of the first line.
+pub(super) fn indent_lines(context: &mut Context, start: Bound<Cursor>, end: Bound<Cursor>) {
+    let indenter = Arc::clone(&context.indenter);
+
+    let buffer = &mut context.buffer;
+    let (first, last) = rows(buffer, start, end);
+
+    for row in first..=last {
+        let current = match buffer.line(row) {
+            Some(line) if !buffer.is_blank_line(row) => line.first_non_blank(),
+            _ => continue,
+        };
+
+        let indent = indenter.indent(buffer, row);
+        buffer.edit(&indent, Cursor::new(row, 0)..Cursor::new(row, current));
+    }
+
+    let col = buffer.line(first).map_or(0, Row::first_non_blank);
+    buffer.set_cursor(Cursor::new(first, col).clamp_to_line(buffer));
+}
+
+/// Returns the operator that reflows lines to the text width, as in `gq`.
+fn reflow(count: usize) -> Box<dyn Mode> {
+    Operator::new("Format", 'q', count, |context, start, end, _| {
+        reflow_lines(context, start, end);
+        Normal::new()
+    })
+}
+
+/// Reflows the lines in a range to the text width, as in `gq`.
+///
+/// Each paragraph is joined into a single line, as in `J`, and then broken again at the text
+/// width. Leaves the cursor on the first non-blank of the last line.
+pub(super) fn reflow_lines(context: &mut Context, start: Bound<Cursor>, end: Bound<Cursor>) {
+    let buffer = &mut context.buffer;
+    let (first, mut last) = rows(buffer, start, end);
+
+    let mut end = last + 1;
+    for row in (first..=last).rev() {
+        if buffer.is_blank_line(row) {
+            end = row;
+        } else if row == first || buffer.is_blank_line(row - 1) {
+            buffer.join_lines(row..end, " ");
+            last -= end - row - 1;
+        }
+    }
+
+    let range = Cursor::new(first, 0)..buffer.clamp(Cursor::new(last, usize::MAX));
+    let text = buffer.slice(range.clone());
+    let text = format::reflow(&text, buffer.text_width(), buffer.tab_stop());
+
+    let end = buffer.edit(&text, range);
+
+    let col = buffer.line(end.row()).map_or(0, Row::first_non_blank);
+    buffer.set_cursor(Cursor::new(end.row(), col).clamp_to_line(buffer));
+}
+
+/// Handles the commands following `g` that aren't variants of other commands, such as `gv`.
+fn extended(context: &mut Context, ch: char, count: usize) -> Box<dyn Mode> {
+    match ch {
+        'd' => {
+            definition(context);
+            Normal::new()
+        },
+
+        'g' => {
+            goto_line(context, Some(count));
+            Normal::new()
+        },
+
+        '&' => resubstitute(context, true),
+        '-' => undo(context, count, false, true),
+        '+' => undo(context, count, true, true),
+
+        'c' => comment(count),
+        'q' => reflow(count),
+        'v' => Select::reselect(context),
+        _ => Normal::new(),
+    }
+}
+
+/// Moves the cursor to the definition of the identifier under it, as found by the resolver of the
+/// context, as in `gd`.
+fn definition(context: &mut Context) {
+    let buffer = &context.buffer;
+    let cursor = buffer.cursor();
+
+    let target = goto::identifier(buffer, cursor)
+        .and_then(|word| context.resolver.definition(buffer, &word, cursor));
+
+    if let Some(target) = target {
+        context.buffer.set_cursor(target);
+    }
+}
+
+/// Handles the fold commands following `z`: `zf` folds the lines of a motion, while `zo` and `zc`
+/// open and close the fold at the cursor.
+fn fold(context: &mut Context, ch: char, count: usize) -> Box<dyn Mode> {
+    let row = context.buffer.cursor().row();
+
+    match ch {
+        'f' => {
+            return Operator::new("Fold", ch, count, |context, start, end, _| {
+                let (first, last) = rows(&context.buffer, start, end);
+                context.folds.create(first, last);
+
+                let buffer = &mut context.buffer;
+                buffer.set_cursor(Cursor::new(first, 0));
+
+                Normal::new()
+            });
+        },
+
+        'o' | 'c' => {
+            context.folds.toggle(row, ch == 'c');
+        },
+
+        _ => {},
+    }
+
+    Normal::new()
+}
+
+/// Moves the cursor to the first non-blank of a line, counting from one, or of the last line if not
+/// given, as in `gg` and `G`.
+pub(super) fn goto_line(context: &mut Context, number: Option<usize>) {
+    let buffer = &mut context.buffer;
+
+    let last = buffer.content().len() - 1;
+    let row = number.map_or(last, |number| number.saturating_sub(1).min(last));
+
+    let col = buffer.line(row).map_or(0, Row::first_non_blank);
+    buffer.set_cursor(Cursor::new(row, col));
+}
+
+/// Moves the cursor `count` lines down, or up if `forward` is not set, as in `j` and `k`.
+///
+/// The screen column is kept where possible, and each closed fold counts as a single line.
+pub(super) fn vertical(context: &mut Context, count: usize, forward: bool) {
+    let buffer = &mut context.buffer;
+    let cursor = buffer.cursor();
+
+    if let Some(row) = context.folds.travel(cursor.row(), buffer.content().len(), count, forward) {
+        buffer.set_cursor(buffer.cursor_at(row, buffer.display_col(cursor)));
+    }
+}
+
+/// Puts text from a register as in `p` and `P`, or as in `gp` and `gP` if `after` is set, and
+/// places the cursor accordingly.
+fn paste(context: &mut Context, name: char, count: usize, side: Side, after: bool) {
+    let linewise = register(context, name).map_or(false, |reg| reg.linewise);
+
+    if let Some((start, end)) = put(context, name, count, side) {
+        let buffer = &mut context.buffer;
+
+        let cursor = if after {
+            // Leave the cursor right after the new text, but never past the end of a line.
+            let end = if linewise { Cursor::new(end.row(), 0) } else { end };
+            end.clamp_to_line(buffer)
+        } else if linewise {
+            Cursor::new(start.row(), buffer.line(start.row()).map_or(0, Row::first_non_blank))
+        } else if start.row() == end.row() {
+            Cursor::new(end.row(), end.col().saturating_sub(1))
+        } else {
+            start
+        };
+
+        buffer.set_cursor(cursor);
+    }
+}
+
+/// Joins `count` lines starting at the cursor's, but at least two, as in `J`, or as in `gJ` if
+/// `exact` is set.
+///
+/// Unless `exact` is set, the indentation of each joined line is replaced by a single space, as in
+/// [`Buffer::join_lines`]. Leaves the cursor where the last lines were joined.
+pub(super) fn join(context: &mut Context, count: usize, exact: bool) {
+    let buffer = &mut context.buffer;
+    let row = buffer.cursor().row();
+
+    let separator = if exact { "" } else { " " };
+
+    if let Some(cursor) = buffer.join_lines(row..row + count.max(2), separator) {
+        buffer.set_cursor(cursor.clamp_to_line(buffer));
+    }
+}
+
+/// Adds `delta` to the number at or after the cursor in the current line, only looking at the
+/// columns in `cols`.
+///
+/// Negative numbers are recognized by a leading `-`, and numbers written with leading zeros keep
+/// their width. Leaves the cursor on the last digit of the result.
+pub(super) fn increment(context: &mut Context, delta: i64, cols: Range<usize>) -> Option<Cursor> {
+    let buffer = &mut context.buffer;
+
+    let cursor = buffer.cursor();
+    let row = buffer.line(cursor.row())?;
+    let last = cols.end.min(row.len());
+
+    let is_digit = |col: usize| row.get(col).map_or(false, |ch| ch.is_ascii_digit());
+
+    let mut start = (cursor.col().max(cols.start)..last).find(|&col| is_digit(col))?;
+    while start > cols.start && is_digit(start - 1) {
+        start -= 1;
+    }
+
+    let end = (start..last).find(|&col| !is_digit(col)).unwrap_or(last);
+    let digits = (start..end).filter_map(|col| row.get(col)).collect::<String>();
+
+    let negative = start > cols.start && row.get(start - 1) == Some('-');
+    if negative {
+        start -= 1;
+    }
+
+    let value = digits.parse::<i64>().ok()?;
+    let value = if negative { -value } else { value }.saturating_add(delta);
+
+    let width = if digits.starts_with('0') { digits.len() } else { 0 };
+    let sign = if value < 0 { "-" } else { "" };
+
+    let text = format!("{}{:0width$}", sign, value.saturating_abs(), width = width);
+    let end = buffer.edit(&text, Cursor::new(cursor.row(), start)..Cursor::new(cursor.row(), end));
+
+    let cursor = Cursor::new(end.row(), end.col() - 1);
+    buffer.set_cursor(cursor);
+
+    Some(cursor)
+}
 
 impl Mode for Normal {
     fn name(&self) -> &str {
         "Normal"
     }
 
-    fn advance(self: Box<Self>, context: &mut Context, event: Event) -> Box<dyn Mode> {
-        // use crate::cursor::{Head, Line, Tail};
-
-        match event {
-            Event::Key(Key::Char('i'), Modifiers::NONE) => Insert::new(),
-            Event::Key(Key::Char('a'), Modifiers::NONE) => Insert::new(),
-
-        //     Event::Key(Key::Char('h'), Modifiers::NONE)
-        //     | Event::Key(Key::Left, Modifiers::NONE) => {
-        //         context.buffer.backward::<Codepoint>();
-        //         self
-        //     }
-
-        //     Event::Key(Key::Char('k'), Modifiers::NONE) | Event::Key(Key::Up, Modifiers::NONE) =>
-        // {         context.buffer.backward::<Line>();
-        //         self
-        //     }
-
-        //     Event::Key(Key::Char('W'), Modifiers::NONE) => {
-        //         context.buffer.backward::<Head>();
-        //         self
-        //     }
-
-        //     Event::Key(Key::Char('E'), Modifiers::NONE) => {
-        //         context.buffer.backward::<Tail>();
-        //         self
-        //     }
-
-        //     Event::Key(Key::Char('l'), Modifiers::NONE)
-        //     | Event::Key(Key::Right, Modifiers::NONE) => {
-        //         context.buffer.forward::<Codepoint>();
-        //         self
-        //     }
-
-        //     Event::Key(Key::Char('j'), Modifiers::NONE)
-        //     | Event::Key(Key::Down, Modifiers::NONE) => {
-        //         context.buffer.forward::<Line>();
-        //         self
-        //     }
-
-        //     Event::Key(Key::Char('w'), Modifiers::NONE) => {
-        //         context.buffer.forward::<Head>();
-        //         self
-        //     }
-
-        //     Event::Key(Key::Char('e'), Modifiers::NONE) => {
-        //         context.buffer.forward::<Tail>();
-        //         self
-        //     }
-
-        //     Event::Key(Key::Char('s'), Modifiers::NONE) => {
-        //         Operator::new("Surround", |_, start, end| surround(start, end))
-        //     }
-
-            _ => self,
+    fn is_idle(&self) -> bool {
+        self.count.is_none() && self.prefix.is_none() && self.register.is_none()
+    }
+
+    fn advance(mut self: Box<Self>, context: &mut Context, event: Event) -> Box<dyn Mode> {
+        match (self.prefix.take(), event) {
+            (None, Event::Key(Key::Char(ch @ '0'..='9'), Modifiers::NONE))
+                if ch != '0' || self.count.is_some() =>
+            {
+                let digit = ch.to_digit(10).unwrap_or_default() as usize;
+                self.count = Some(self.count.unwrap_or(0).saturating_mul(10).saturating_add(digit));
+
+                self
+            },
+
+            (None, Event::Key(key, modifiers))
+                if !context.buffer.modifiable() && inserts(key, modifiers) =>
+            {
+                context.message = Some("Cannot make changes, 'modifiable' is off".to_owned());
+                Normal::new()
+            },
+
+            (prefix, Event::Key(Key::Char(ch), Modifiers::NONE))
+                if prefix == Some('q')
+                    || (prefix.is_none() && ch == 'q' && context.recording.is_some()) =>
+            {
+                record(context, ch)
+            },
+
+            (None, Event::Key(Key::Char(ch), Modifiers::NONE)) if "gzfFtTq\"".contains(ch) => {
+                self.prefix = Some(ch);
+                self
+            },
+
+            (Some('"'), Event::Key(Key::Char(name), Modifiers::NONE)) => {
+                self.register = Some(name);
+                self
+            },
+
+            (Some(kind), Event::Key(Key::Char(target), Modifiers::NONE))
+                if "fFtT".contains(kind) =>
+            {
+                let find = Find {
+                    target,
+                    forward: kind.is_lowercase(),
+                    till: kind.eq_ignore_ascii_case(&'t'),
+                };
+
+                jump(context, find, self.count(), false);
+                Normal::new()
+            },
+
+            (None, Event::Key(Key::Char(ch), Modifiers::NONE)) if "wbe{}jk;,G0^$".contains(ch) => {
+                motion(context, ch, self.count);
+                Normal::new()
+            },
+
+            (None, Event::Key(Key::Char(':'), Modifiers::NONE)) => command_line(),
+
+            (None, Event::Key(Key::Char(ch), Modifiers::NONE)) if "iaIA".contains(ch) => {
+                insert(context, ch, self.count())
+            },
+
+            (prefix, Event::Key(Key::Char(ch @ 'p'), Modifiers::NONE))
+            | (prefix, Event::Key(Key::Char(ch @ 'P'), Modifiers::NONE))
+                if prefix.map_or(true, |prefix| prefix == 'g') =>
+            {
+                let side = if ch == 'p' { Side::After } else { Side::Before };
+                paste(context, self.register(), self.count(), side, prefix.is_some());
+
+                Normal::new()
+            },
+
+            (None, Event::Key(Key::Char(ch), Modifiers::NONE)) if "cdy=<>".contains(ch) => {
+                operator(ch, self.count(), self.register())
+            },
+
+            (None, Event::Key(Key::Char('&'), Modifiers::NONE)) => resubstitute(context, false),
+            (None, Event::Key(Key::Char('.'), Modifiers::NONE)) => repeat(context, self.count),
+
+            (None, Event::Key(Key::Char(ch @ 'u'), Modifiers::NONE))
+            | (None, Event::Key(Key::Char(ch @ 'r'), Modifiers::CTRL)) => {
+                undo(context, self.count(), ch == 'r', false)
+            },
+
+            (None, Event::Key(Key::Char('Y'), Modifiers::NONE)) => {
+                yank_lines(context, self.count(), self.register())
+            },
+
+            (prefix, Event::Key(Key::Char('J'), Modifiers::NONE))
+                if prefix.map_or(true, |prefix| prefix == 'g') =>
+            {
+                join(context, self.count(), prefix.is_some());
+                Normal::new()
+            },
+
+            (Some('g'), Event::Key(Key::Char(ch), Modifiers::NONE)) => {
+                extended(context, ch, self.count())
+            },
+
+            (Some('z'), Event::Key(Key::Char(ch), Modifiers::NONE)) => {
+                fold(context, ch, self.count())
+            },
+
+            (None, Event::Key(key, modifiers)) if Kind::from_key(key, modifiers).is_some() => {
+                let kind = Kind::from_key(key, modifiers).unwrap_or(Kind::Char);
+                Select::new(context.buffer.cursor(), kind)
+            },
+
+            (None, Event::Key(Key::Char(ch @ 'a'), Modifiers::CTRL))
+            | (None, Event::Key(Key::Char(ch @ 'x'), Modifiers::CTRL)) => {
+                let delta = i64::try_from(self.count()).unwrap_or(i64::MAX);
+                increment(context, if ch == 'a' { delta } else { -delta }, 0..usize::MAX);
+
+                Normal::new()
+            },
+
+            (Some(_), Event::Idle(idle)) if timed_out(idle, context.options.timeout_len) => {
+                Normal::new()
+            },
+
+            (prefix, Event::Idle(_)) => {
+                self.prefix = prefix;
+                self
+            },
+
+            _ => Normal::new(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Normal;
+    use crate::event::{Event, Key, Modifiers};
+    use crate::mode::Mode;
+    use crate::register::{Register, Registers};
+    use crate::state::Context;
+    use crate::{Buffer, Cursor};
+
+    fn run(text: &str, register: Register, keys: &str) -> Context {
+        let mut context = Context { buffer: Buffer::from(text), ..Context::default() };
+        context.registers.set(Registers::UNNAMED, register);
+
+        keys.chars().fold(Normal::new() as Box<dyn Mode>, |mode, ch| {
+            mode.advance(&mut context, Event::Key(Key::Char(ch), Modifiers::NONE))
+        });
+
+        context
+    }
+
+    #[test]
+    fn test_shift_lines() {
+        let context = run("a\nb\nc", Register::default(), "2>>w>>");
+        assert_eq!(context.buffer.to_string(), "\ta\n\t\tb\nc");
+
+        let context = run("\t\ta\n\tb", Register::default(), "<<w<<<<");
+        assert_eq!(context.buffer.to_string(), "\ta\nb");
+    }
+
+    #[test]
+    fn test_shift_motion() {
+        let context = run("foo\n  bar\nbaz", Register::default(), ">j");
+
+        assert_eq!(context.buffer.to_string(), "\tfoo\n\t  bar\nbaz");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 1));
+
+        let context = run("foo\nbar\nbaz", Register::default(), "w>2k");
+        assert_eq!(context.buffer.to_string(), "\tfoo\n\tbar\nbaz");
+
+        let context = run("foo\n  \n\nbar", Register::default(), ">3j");
+        assert_eq!(context.buffer.to_string(), "\tfoo\n  \n\n\tbar");
+    }
+
+    #[test]
+    fn test_shift_paragraph() {
+        let text = "foo\n  bar\n\n\nbaz";
+
+        let context = run(text, Register::default(), ">ip");
+        assert_eq!(context.buffer.to_string(), "\tfoo\n\t  bar\n\n\nbaz");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 1));
+
+        let context = run(text, Register::default(), ">ap");
+        assert_eq!(context.buffer.to_string(), "\tfoo\n\t  bar\n\n\nbaz");
+    }
+
+    #[test]
+    fn test_not_modifiable() {
+        let mut context = Context { buffer: Buffer::from("foo"), ..Context::default() };
+        context.buffer.set_modifiable(false);
+
+        let mode = Normal::new().advance(&mut context, Event::Key(Key::Char('i'), Modifiers::NONE));
+
+        assert_eq!(mode.name(), "Normal");
+        assert_eq!(context.message.as_deref(), Some("Cannot make changes, 'modifiable' is off"));
+
+        let _ = mode.advance(&mut context, Event::Key(Key::Char('a'), Modifiers::CTRL));
+        assert_eq!(context.buffer.to_string(), "foo");
+    }
+
+    #[test]
+    fn test_append_line() {
+        let context = run("foo bar", Register::default(), "A!");
+        assert_eq!(context.buffer.to_string(), "foo bar!");
+
+        let context = run("foo\n\nbar", Register::default(), "jAx");
+        assert_eq!(context.buffer.to_string(), "foo\nx\nbar");
+
+        let context = run("foo\n  \nbar", Register::default(), "jAx");
+        assert_eq!(context.buffer.to_string(), "foo\n  x\nbar");
+
+        let context = run("foo\nbar", Register::default(), "j2Ax");
+        assert_eq!(context.buffer.to_string(), "foo\nbarx");
+        assert_eq!(context.buffer.cursor(), Cursor::new(1, 4));
+    }
+
+    #[test]
+    fn test_insert_line() {
+        let context = run("  foo bar", Register::default(), "wI!");
+        assert_eq!(context.buffer.to_string(), "  !foo bar");
+
+        let context = run("foo\n\nbar", Register::default(), "jIx");
+        assert_eq!(context.buffer.to_string(), "foo\nx\nbar");
+
+        let context = run("foo\n  \nbar", Register::default(), "jIx");
+        assert_eq!(context.buffer.to_string(), "foo\n  x\nbar");
+
+        let context = run("foo\n\tbar", Register::default(), "jIx");
+        assert_eq!(context.buffer.to_string(), "foo\n\txbar");
+    }
+
+    #[test]
+    fn test_vertical() {
+        let context = run("foo\n\tbar\nbaz\nqux", Register::default(), "lj");
+        assert_eq!(context.buffer.cursor(), Cursor::new(1, 0));
+
+        let context = run("foo\nbar\nbaz\nqux", Register::default(), "9jk2k");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 0));
+    }
+
+    #[test]
+    fn test_fold() {
+        let context = run("a\nb\nc\nd\ne", Register::default(), "jzfjkjj");
+
+        assert_eq!(context.folds.closed(2), Some((1, 2)));
+        assert_eq!(context.buffer.cursor(), Cursor::new(3, 0));
+
+        let context = run("a\nb\nc\nd\ne", Register::default(), "jzfjzokjj");
+
+        assert_eq!(context.folds.closed(2), None);
+        assert_eq!(context.buffer.cursor(), Cursor::new(2, 0));
+
+        let context = run("a\nb\nc\nd\ne", Register::default(), "jzfjzojzck");
+
+        assert_eq!(context.folds.closed(1), Some((1, 2)));
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 0));
+    }
+
+    #[test]
+    fn test_delete() {
+        let context = run("foo bar\nbaz\nqux", Register::default(), "wdj");
+
+        assert_eq!(context.buffer.to_string(), "qux");
+        assert_eq!(
+            context.registers.get(Registers::UNNAMED),
+            Some(&Register::new("foo bar\nbaz\n", true))
+        );
+
+        let context = run("foo bar\nbaz\nqux", Register::default(), "wde");
+
+        assert_eq!(context.buffer.to_string(), "foo \nbaz\nqux");
+        assert_eq!(context.registers.get(Registers::UNNAMED), Some(&Register::new("bar", false)));
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 3));
+    }
+
+    #[test]
+    fn test_change_word() {
+        let context = run("foobar baz", Register::default(), "fbcwX");
+
+        assert_eq!(context.buffer.to_string(), "fooX baz");
+        assert_eq!(context.registers.get(Registers::UNNAMED), Some(&Register::new("bar", false)));
+
+        let context = run("foo bar\nbaz", Register::default(), "wcwX");
+        assert_eq!(context.buffer.to_string(), "foo X\nbaz");
+
+        let context = run("foo bar baz", Register::default(), "ecwX");
+        assert_eq!(context.buffer.to_string(), "foX bar baz");
+
+        let context = run("foo bar baz", Register::default(), "c2wX");
+        assert_eq!(context.buffer.to_string(), "X baz");
+    }
+
+    #[test]
+    fn test_change_lines() {
+        let context = run("foo\nbar\nbaz", Register::default(), "jccX");
+
+        assert_eq!(context.buffer.to_string(), "foo\nX\nbaz");
+        assert_eq!(context.registers.get(Registers::UNNAMED), Some(&Register::new("bar\n", true)));
+
+        let context = run("foo\n    bar\n  baz", Register::default(), "j2ccX");
+
+        assert_eq!(context.buffer.to_string(), "foo\n    X");
+        assert_eq!(
+            context.registers.get(Registers::UNNAMED),
+            Some(&Register::new("    bar\n  baz\n", true))
+        );
+    }
+
+    #[test]
+    fn test_delete_around_word() {
+        let context = run("foo bar baz", Register::default(), "wdaw");
+
+        assert_eq!(context.buffer.to_string(), "foo baz");
+        assert_eq!(context.registers.get(Registers::UNNAMED), Some(&Register::new("bar ", false)));
+
+        let context = run("foo bar\nbaz", Register::default(), "wdaw");
+
+        assert_eq!(context.buffer.to_string(), "foo\nbaz");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 2));
+
+        let context = run("foo   bar baz", Register::default(), "wdaw");
+        assert_eq!(context.buffer.to_string(), "foo   baz");
+
+        let context = run("foo   bar", Register::default(), "wdaw");
+        assert_eq!(context.buffer.to_string(), "foo");
+    }
+
+    #[test]
+    fn test_delete_paragraph() {
+        let text = "foo\nbar\nbaz\n\n\nqux\nquux";
+
+        let context = run(text, Register::default(), "jwdip");
+        assert_eq!(context.buffer.to_string(), "\n\nqux\nquux");
+        assert_eq!(
+            context.registers.get(Registers::UNNAMED),
+            Some(&Register::new("foo\nbar\nbaz\n", true))
+        );
+
+        let context = run(text, Register::default(), "jwdap");
+        assert_eq!(context.buffer.to_string(), "qux\nquux");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 0));
+
+        let context = run(text, Register::default(), "Gdap");
+        assert_eq!(context.buffer.to_string(), "foo\nbar\nbaz");
+    }
+
+    #[test]
+    fn test_delete_entire() {
+        let context = run("foo\n\nbar baz\n", Register::default(), "jjwdae");
+
+        assert_eq!(context.buffer.to_string(), "");
+        assert_eq!(
+            context.registers.get(Registers::UNNAMED),
+            Some(&Register::new("foo\n\nbar baz\n", true))
+        );
+
+        let context = run("\nfoo\nbar\n", Register::default(), "jdie");
+        assert_eq!(context.buffer.to_string(), "\n");
+
+        let context = run("foo\nbar", Register::default(), "jyae");
+        assert_eq!(context.buffer.to_string(), "foo\nbar");
+        assert_eq!(
+            context.registers.get(Registers::UNNAMED),
+            Some(&Register::new("foo\nbar\n", true))
+        );
+    }
+
+    #[test]
+    fn test_delete_tag() {
+        let context = run("<div><span>x</span></div>", Register::default(), "fxdit");
+        assert_eq!(context.buffer.to_string(), "<div><span></span></div>");
+
+        let context = run("<div><span>x</span></div>", Register::default(), "fxdat");
+        assert_eq!(context.buffer.to_string(), "<div></div>");
+
+        let context = run("<div><span>x</span></div>", Register::default(), "fxdatdit");
+        assert_eq!(context.buffer.to_string(), "<div></div>");
+    }
+
+    #[test]
+    fn test_delete_last_lines() {
+        let context = run("foo\n  bar\nbaz", Register::default(), "jjdk");
+
+        assert_eq!(context.buffer.to_string(), "foo");
+        assert_eq!(
+            context.registers.get(Registers::UNNAMED),
+            Some(&Register::new("  bar\nbaz\n", true))
+        );
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 0));
+
+        let context = run("foo\n  bar\nbaz", Register::default(), "ddp");
+        assert_eq!(context.buffer.to_string(), "  bar\nfoo\nbaz");
+    }
+
+    #[test]
+    fn test_yank() {
+        let context = run("foo bar\nbaz", Register::default(), "wyeP");
+        assert_eq!(context.buffer.to_string(), "foo barbar\nbaz");
+
+        let context = run("foo bar\nbaz", Register::default(), "jykp");
+
+        assert_eq!(context.buffer.to_string(), "foo bar\nfoo bar\nbaz\nbaz");
+        assert_eq!(context.buffer.cursor(), Cursor::new(1, 0));
+    }
+
+    #[test]
+    fn test_indent() {
+        let context = run("  foo\nbar\n\n\t baz\nqux", Register::default(), "j=2j");
+
+        assert_eq!(context.buffer.to_string(), "  foo\n  bar\n\n  baz\nqux");
+        assert_eq!(context.buffer.cursor(), Cursor::new(1, 2));
+    }
+
+    #[test]
+    fn test_definition() {
+        let text = "fn foo() {}\nfn bar() {\n    foo();\n}";
+
+        let context = run(text, Register::default(), "jjwgd");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 3));
+
+        let context = run(text, Register::default(), "jjwwgd");
+        assert_eq!(context.buffer.cursor(), Cursor::new(2, 7));
+    }
+
+    #[test]
+    fn test_comment() {
+        let context = run("fn foo() {\n    bar();\n}", Register::default(), "gcj");
+
+        assert_eq!(context.buffer.to_string(), "// fn foo() {\n    // bar();\n}");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 0));
+
+        let context = run("// fn foo() {\n    //bar();\n}", Register::default(), "gc2c");
+        assert_eq!(context.buffer.to_string(), "fn foo() {\n    bar();\n}");
+
+        let context = run("  foo\n\n  // bar", Register::default(), "3gcc");
+        assert_eq!(context.buffer.to_string(), "  // foo\n\n  bar");
+
+        let context = run("foo\nbar baz", Register::default(), "gc2w");
+        assert_eq!(context.buffer.to_string(), "// foo\nbar baz");
+    }
+
+    #[test]
+    fn test_reflow() {
+        let text = "a\n  foo bar\n  baz qux\n\nb";
+
+        let mut context = Context { buffer: Buffer::from(text), ..Context::default() };
+        context.buffer.set_text_width(10);
+
+        let mode = "jgqip".chars().fold(Normal::new() as Box<dyn Mode>, |mode, ch| {
+            mode.advance(&mut context, Event::Key(Key::Char(ch), Modifiers::NONE))
+        });
+
+        assert_eq!(mode.name(), "Normal");
+        assert_eq!(context.buffer.to_string(), "a foo bar\nbaz qux\n\nb");
+        assert_eq!(context.buffer.cursor(), Cursor::new(1, 0));
+
+        let context = run("  foo bar baz\nqux", Register::default(), "gqq");
+        assert_eq!(context.buffer.to_string(), "  foo bar baz\nqux");
+    }
+
+    #[test]
+    fn test_join() {
+        let context = run("foo\n    bar\n\n)\nbaz", Register::default(), "4J");
+
+        assert_eq!(context.buffer.to_string(), "foo bar)\nbaz");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 7));
+    }
+
+    #[test]
+    fn test_join_exact() {
+        let context = run("foo\n    bar\nbaz", Register::default(), "gJ");
+
+        assert_eq!(context.buffer.to_string(), "foo    bar\nbaz");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 3));
+
+        let context = run("foo\n    bar\nbaz", Register::default(), "J");
+        assert_eq!(context.buffer.to_string(), "foo bar\nbaz");
+
+        let context = run("foo \n bar\n baz", Register::default(), "3gJ");
+        assert_eq!(context.buffer.to_string(), "foo  bar baz");
+    }
+
+    #[test]
+    fn test_yank_to_end() {
+        let context = run("foo bar\nbaz\nqux", Register::default(), "wY");
+
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 4));
+        assert_eq!(context.registers.get(Registers::UNNAMED), Some(&Register::new("bar", false)));
+
+        let context = run("foo bar\nbaz\nqux", Register::default(), "w2Y");
+        assert_eq!(
+            context.registers.get(Registers::UNNAMED),
+            Some(&Register::new("bar\nbaz", false))
+        );
+    }
+
+    #[test]
+    fn test_yank_whole_lines() {
+        use crate::Editor;
+
+        let mut editor = Editor::with_text("foo bar\nbaz\nqux");
+
+        editor.feed(":set noyanktoend<CR>w2YGp");
+        assert_eq!(editor.buffer().to_string(), "foo bar\nbaz\nqux\nfoo bar\nbaz");
+        assert!(!editor.options().yank_to_end);
+    }
+
+    #[test]
+    fn test_put_characterwise_count() {
+        let context = run("abc", Register::new("xy", false), "2p");
+
+        assert_eq!(context.buffer.to_string(), "axyxybc");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 4));
+    }
+
+    #[test]
+    fn test_put_characterwise_before() {
+        let context = run("abc", Register::new("xy", false), "P");
+
+        assert_eq!(context.buffer.to_string(), "xyabc");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 1));
+    }
+
+    #[test]
+    #[cfg(feature = "io")]
+    fn test_put_path() {
+        use crate::Editor;
+
+        let name = format!("six-{}-put-path.txt", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, "foo").unwrap();
+
+        let mut editor = Editor::open(&path).unwrap();
+        editor.feed("\"%P");
+
+        assert_eq!(editor.buffer().to_string(), format!("{}foo", path.display()));
+        std::fs::remove_file(&path).unwrap();
+
+        let mut editor = Editor::with_text("foo");
+        editor.feed("\"%P");
+
+        assert_eq!(editor.buffer().to_string(), "foo");
+    }
+
+    #[test]
+    fn test_put_linewise() {
+        let context = run("one\ntwo", Register::new("  new\n", true), "p");
+
+        assert_eq!(context.buffer.to_string(), "one\n  new\ntwo");
+        assert_eq!(context.buffer.cursor(), Cursor::new(1, 2));
+    }
+
+    #[test]
+    fn test_put_linewise_count_before() {
+        let context = run("one\ntwo", Register::new("new", true), "3P");
+
+        assert_eq!(context.buffer.to_string(), "new\nnew\nnew\none\ntwo");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 0));
+    }
+
+    #[test]
+    fn test_put_linewise_at_eof() {
+        let context = run("one", Register::new("new", true), "p");
+
+        assert_eq!(context.buffer.to_string(), "one\nnew");
+        assert_eq!(context.buffer.cursor(), Cursor::new(1, 0));
+    }
+
+    #[test]
+    fn test_put_characterwise_after_text() {
+        let context = run("abc", Register::new("xy", false), "gp");
+
+        assert_eq!(context.buffer.to_string(), "axybc");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 3));
+    }
+
+    #[test]
+    fn test_put_characterwise_after_text_before() {
+        let context = run("abc", Register::new("xy", false), "2gP");
+
+        assert_eq!(context.buffer.to_string(), "xyxyabc");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 4));
+    }
+
+    #[test]
+    fn test_put_characterwise_after_text_at_eol() {
+        let context = run("a", Register::new("xy", false), "gp");
+
+        assert_eq!(context.buffer.to_string(), "axy");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 2));
+    }
+
+    #[test]
+    fn test_put_linewise_after_text() {
+        let context = run("one\ntwo", Register::new("new", true), "gp");
+
+        assert_eq!(context.buffer.to_string(), "one\nnew\ntwo");
+        assert_eq!(context.buffer.cursor(), Cursor::new(2, 0));
+    }
+
+    #[test]
+    fn test_put_linewise_after_text_before() {
+        let context = run("one\ntwo", Register::new("new", true), "gP");
+
+        assert_eq!(context.buffer.to_string(), "new\none\ntwo");
+        assert_eq!(context.buffer.cursor(), Cursor::new(1, 0));
+    }
+
+    #[test]
+    fn test_increment_count() {
+        let mut context = Context { buffer: Buffer::from("x 5 y"), ..Context::default() };
+
+        let events = [
+            Event::Key(Key::Char('1'), Modifiers::NONE),
+            Event::Key(Key::Char('0'), Modifiers::NONE),
+            Event::Key(Key::Char('a'), Modifiers::CTRL),
+        ];
+
+        events
+            .iter()
+            .fold(Normal::new() as Box<dyn Mode>, |mode, &event| mode.advance(&mut context, event));
+
+        assert_eq!(context.buffer.to_string(), "x 15 y");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 3));
+    }
+
+    #[test]
+    fn test_increment_negative() {
+        let mut context = Context { buffer: Buffer::from("-3"), ..Context::default() };
+
+        let mode = Normal::new().advance(&mut context, Event::Key(Key::Char('a'), Modifiers::CTRL));
+        assert_eq!(context.buffer.to_string(), "-2");
+
+        let mode = mode.advance(&mut context, Event::Key(Key::Char('5'), Modifiers::NONE));
+        let _ = mode.advance(&mut context, Event::Key(Key::Char('a'), Modifiers::CTRL));
+        assert_eq!(context.buffer.to_string(), "3");
+    }
+
+    #[test]
+    fn test_decrement_leading_zeros() {
+        let mut context = Context { buffer: Buffer::from("007"), ..Context::default() };
+
+        let mode = Normal::new().advance(&mut context, Event::Key(Key::Char('a'), Modifiers::CTRL));
+        assert_eq!(context.buffer.to_string(), "008");
+
+        let _ = mode.advance(&mut context, Event::Key(Key::Char('x'), Modifiers::CTRL));
+        assert_eq!(context.buffer.to_string(), "007");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 2));
+    }
+
+    #[test]
+    fn test_paragraph_motion() {
+        let context = run("foo\n\nbar\nbaz\n\nqux", Register::default(), "G{{");
+        assert_eq!(context.buffer.cursor(), Cursor::new(1, 0));
+
+        let context = run("foo\n\nbar\nbaz\n\nqux", Register::default(), "2}");
+        assert_eq!(context.buffer.cursor(), Cursor::new(4, 0));
+
+        let context = run("foo bar\nbaz", Register::default(), "w{");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 0));
+
+        let context = run("foo bar\nbaz", Register::default(), "{");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 0));
+
+        let context = run("\n\nfoo\nbar", Register::default(), "G{");
+        assert_eq!(context.buffer.cursor(), Cursor::new(1, 0));
+
+        let context = run("\n\nfoo\nbar", Register::default(), "G9{");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 0));
+    }
+
+    #[test]
+    fn test_word_keyword() {
+        let mut context = Context { buffer: Buffer::from("foo_bar baz"), ..Context::default() };
+
+        let _ = Normal::new().advance(&mut context, Event::Key(Key::Char('w'), Modifiers::NONE));
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 8));
+    }
+
+    #[test]
+    fn test_word_custom_keyword() {
+        let mut context = Context { buffer: Buffer::from("foo_bar baz"), ..Context::default() };
+        context.buffer.set_keyword(char::is_alphanumeric);
+
+        let mode = Normal::new().advance(&mut context, Event::Key(Key::Char('w'), Modifiers::NONE));
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 3));
+
+        let mode = mode.advance(&mut context, Event::Key(Key::Char('w'), Modifiers::NONE));
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 4));
+
+        let _ = mode.advance(&mut context, Event::Key(Key::Char('e'), Modifiers::NONE));
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 6));
+    }
+
+    #[test]
+    fn test_find() {
+        assert_eq!(run("axbxc", Register::default(), "fx").buffer.cursor(), Cursor::new(0, 1));
+        assert_eq!(run("axbxc", Register::default(), "2fx").buffer.cursor(), Cursor::new(0, 3));
+        assert_eq!(run("axbxc", Register::default(), "fx;").buffer.cursor(), Cursor::new(0, 3));
+        assert_eq!(run("axbxc", Register::default(), "fx;,").buffer.cursor(), Cursor::new(0, 1));
+        assert_eq!(run("axbxc", Register::default(), "fz").buffer.cursor(), Cursor::new(0, 0));
+    }
+
+    #[test]
+    fn test_find_till_repeat() {
+        assert_eq!(run("axbxc", Register::default(), "tx").buffer.cursor(), Cursor::new(0, 0));
+        assert_eq!(run("axbxc", Register::default(), "tx;").buffer.cursor(), Cursor::new(0, 2));
+        assert_eq!(run("axbxc", Register::default(), "fcTx").buffer.cursor(), Cursor::new(0, 4));
+        assert_eq!(run("axbxc", Register::default(), "fcTx;").buffer.cursor(), Cursor::new(0, 2));
+    }
+}
@@ -1,21 +1,48 @@
 use std::ops::Bound;
 
-use crate::cursor::Cursor;
+use crate::buffer::{Buffer, Row};
+use crate::cursor::{Cursor, Head, Line, Metric, Object, Span, Tail};
 use crate::event::{Event, Key, Modifiers};
-use crate::mode::Mode;
+use crate::mode::{timed_out, Kind, Mode, Normal};
 use crate::state::Context;
 
 /// Queries the user for a text object and applies an operation.
+///
+/// Motions that move across lines, such as `j`, and repeating the operator key, as in `>>`, select
+/// whole lines. Those ranges start at the beginning of their first line and end right before the
+/// line after their last one, or are unbounded at the end of the buffer. The operation is told
+/// whether the range is made of whole lines or of characters.
+///
+/// `Esc` cancels the operator, along with its counts, while keys it doesn't know are ignored and
+/// leave it pending. A motion that can't move also cancels it, and so does going without input for
+/// longer than the `timeoutlen` of the context.
 #[derive(Derivative)]
 #[derivative(Debug)]
 pub struct Operator<Callback>
 where
-    Callback:
-        'static + Send + Sync + FnOnce(&mut Context, Bound<Cursor>, Bound<Cursor>) -> Box<dyn Mode>,
+    Callback: 'static
+        + Send
+        + Sync
+        + FnOnce(&mut Context, Bound<Cursor>, Bound<Cursor>, Kind) -> Box<dyn Mode>,
 {
     /// The operator name.
     name: &'static str,
 
+    /// The key that started the operator, which selects whole lines when typed again.
+    key: char,
+
+    /// Whether the operator changes text, as in `c`, which makes `cw` act like `ce`.
+    change: bool,
+
+    /// The number of times the motion should be repeated, as given before the operator.
+    count: usize,
+
+    /// The number of times the motion should be repeated, as given after the operator.
+    motion_count: Option<usize>,
+
+    /// The first key of a pending text object, such as `i` in `ip`.
+    prefix: Option<char>,
+
     /// Operator to be executed.
     #[derivative(Debug = "ignore")]
     and_then: Callback,
@@ -23,103 +50,269 @@ where
 
 impl<Callback> Operator<Callback>
 where
-    Callback:
-        'static + Send + Sync + FnOnce(&mut Context, Bound<Cursor>, Bound<Cursor>) -> Box<dyn Mode>,
+    Callback: 'static
+        + Send
+        + Sync
+        + FnOnce(&mut Context, Bound<Cursor>, Bound<Cursor>, Kind) -> Box<dyn Mode>,
 {
-    pub fn new(name: &'static str, and_then: Callback) -> Box<Self> {
-        Box::new(Self { name, and_then })
+    pub fn new(name: &'static str, key: char, count: usize, and_then: Callback) -> Box<Self> {
+        Box::new(Self {
+            name,
+            key,
+            change: false,
+            count,
+            motion_count: None,
+            prefix: None,
+            and_then,
+        })
+    }
+
+    /// Marks the operator as changing text, as in `c`.
+    #[must_use]
+    pub fn with_change(mut self: Box<Self>) -> Box<Self> {
+        self.change = true;
+        self
     }
 }
 
+/// Returns the range spanning the whole lines from `first` to `last`.
+pub(super) fn lines(
+    buffer: &Buffer,
+    first: usize,
+    last: usize,
+) -> (Bound<Cursor>, Bound<Cursor>, Kind) {
+    let end = if last + 1 < buffer.content().len() {
+        Bound::Excluded(Cursor::new(last + 1, 0))
+    } else {
+        Bound::Unbounded
+    };
+
+    (Bound::Included(Cursor::new(first, 0)), end, Kind::Line)
+}
+
+/// Returns whether the character at a position is a blank, or there is none.
+fn is_blank(buffer: &Buffer, cursor: Cursor) -> bool {
+    buffer.char_indices_from(cursor).next().map_or(true, |(_, ch)| ch.is_whitespace())
+}
+
+/// Returns the position reached by moving over a metric `count` times, as long as it moves at
+/// least once.
+fn travel<M: Metric>(
+    buffer: &Buffer,
+    cursor: Cursor,
+    count: usize,
+    forward: bool,
+) -> Option<Cursor> {
+    std::iter::successors(Some(cursor), |&cursor| {
+        if forward {
+            M::after(cursor, buffer)
+        } else {
+            M::before(cursor, buffer)
+        }
+    })
+    .take(count + 1)
+    .skip(1)
+    .last()
+}
+
+/// Returns the end of the text moved over by `count` words forward, as in `dw`.
+///
+/// As in Vim, a motion that stops on a later line ends with the line before it instead, and one
+/// running out of words ends with the buffer.
+fn words(buffer: &Buffer, cursor: Cursor, count: usize) -> Option<Cursor> {
+    let end_of = |row| Cursor::new(row, buffer.line(row).map_or(0, Row::len));
+
+    let (moves, head) = std::iter::successors(Some(cursor), |&cursor| Head::after(cursor, buffer))
+        .take(count + 1)
+        .enumerate()
+        .last()?;
+
+    let end = if moves < count {
+        end_of(buffer.content().len().saturating_sub(1))
+    } else if head.row() > cursor.row() {
+        end_of(head.row() - 1)
+    } else {
+        head
+    };
+
+    Some(end).filter(|&end| end > cursor)
+}
+
 impl<Callback> Mode for Operator<Callback>
 where
-    Callback:
-        'static + Send + Sync + FnOnce(&mut Context, Bound<Cursor>, Bound<Cursor>) -> Box<dyn Mode>,
+    Callback: 'static
+        + Send
+        + Sync
+        + FnOnce(&mut Context, Bound<Cursor>, Bound<Cursor>, Kind) -> Box<dyn Mode>,
 {
     fn name(&self) -> &str {
         self.name
     }
 
-    fn advance(self: Box<Self>, context: &mut Context, event: Event) -> Box<dyn Mode> {
-        unimplemented!()
-        // use crate::cursor::{Codepoint, Head, Line, Tail};
-        // use Bound::{Excluded, Included, Unbounded};
-
-        // match event {
-        //     Event::Key(Key::Char('h'), Modifiers::NONE)
-        //     | Event::Key(Key::Left, Modifiers::NONE) => {
-        //         let end = Excluded(context.buffer.cursor());
-        //         let start = context.buffer.backward::<Codepoint>().map_or(Unbounded, Included);
-
-        //         (self.and_then)(context, start, end)
-        //     },
-
-        //     Event::Key(Key::Char('k'), Modifiers::NONE) | Event::Key(Key::Up, Modifiers::NONE) => {
-        //         let end = context.buffer.cursor();
-        //         if let Some(start) = context.buffer.backward::<Line>() {
-        //             (self.and_then)(context, Included(start), Excluded(end))
-        //         } else {
-        //             self
-        //         }
-        //     },
-
-        //     Event::Key(Key::Char('W'), Modifiers::NONE) => {
-        //         let end = context.buffer.cursor();
-        //         if let Some(start) = context.buffer.backward::<Head>() {
-        //             (self.and_then)(context, Included(start), Excluded(end))
-        //         } else {
-        //             self
-        //         }
-        //     },
-
-        //     Event::Key(Key::Char('E'), Modifiers::NONE) => {
-        //         let end = context.buffer.cursor();
-        //         if let Some(start) = context.buffer.backward::<Tail>() {
-        //             (self.and_then)(context, Included(start), Excluded(end))
-        //         } else {
-        //             self
-        //         }
-        //     },
-
-        //     Event::Key(Key::Char('l'), Modifiers::NONE)
-        //     | Event::Key(Key::Right, Modifiers::NONE) => {
-        //         let end = context.buffer.cursor();
-        //         if let Some(start) = context.buffer.forward::<Codepoint>() {
-        //             (self.and_then)(context, Included(start), Excluded(end))
-        //         } else {
-        //             self
-        //         }
-        //     },
-
-        //     Event::Key(Key::Char('j'), Modifiers::NONE)
-        //     | Event::Key(Key::Down, Modifiers::NONE) => {
-        //         let end = context.buffer.cursor();
-        //         if let Some(start) = context.buffer.forward::<Line>() {
-        //             (self.and_then)(context, Included(start), Excluded(end))
-        //         } else {
-        //             self
-        //         }
-        //     },
-
-        //     Event::Key(Key::Char('w'), Modifiers::NONE) => {
-        //         let start = context.buffer.cursor();
-        //         if let Some(end) = context.buffer.forward::<Head>() {
-        //             (self.and_then)(context, Included(start), Excluded(end))
-        //         } else {
-        //             self
-        //         }
-        //     },
-
-        //     Event::Key(Key::Char('e'), Modifiers::NONE) => {
-        //         let start = context.buffer.cursor();
-        //         if let Some(end) = context.buffer.forward::<Tail>() {
-        //             (self.and_then)(context, Included(start), Included(end))
-        //         } else {
-        //             self
-        //         }
-        //     },
-
-        //     _ => self,
-        // }
+    fn advance(mut self: Box<Self>, context: &mut Context, event: Event) -> Box<dyn Mode> {
+        use Bound::{Excluded, Included};
+
+        let buffer = &context.buffer;
+        let cursor = buffer.cursor();
+        let count = self.count.saturating_mul(self.motion_count.unwrap_or(1));
+
+        let range = match (self.prefix.take(), event) {
+            (None, Event::Key(Key::Char(ch @ '0'..='9'), Modifiers::NONE))
+                if ch != '0' || self.motion_count.is_some() =>
+            {
+                let digit = ch.to_digit(10).unwrap_or_default() as usize;
+                let motion_count = self.motion_count.unwrap_or(0).saturating_mul(10);
+
+                self.motion_count = Some(motion_count.saturating_add(digit));
+                return self;
+            },
+
+            (None, Event::Key(Key::Char(ch @ 'i'), Modifiers::NONE))
+            | (None, Event::Key(Key::Char(ch @ 'a'), Modifiers::NONE)) => {
+                self.prefix = Some(ch);
+                return self;
+            },
+
+            (None, Event::Key(Key::Char(ch), Modifiers::NONE)) if ch == self.key => {
+                let last = (cursor.row() + count - 1).min(buffer.content().len() - 1);
+                Some(lines(buffer, cursor.row(), last))
+            },
+
+            (None, Event::Key(Key::Char('j'), Modifiers::NONE))
+            | (None, Event::Key(Key::Down, Modifiers::NONE)) => {
+                travel::<Line>(buffer, cursor, count, true)
+                    .map(|end| lines(buffer, cursor.row(), end.row()))
+            },
+
+            (None, Event::Key(Key::Char('k'), Modifiers::NONE))
+            | (None, Event::Key(Key::Up, Modifiers::NONE)) => {
+                travel::<Line>(buffer, cursor, count, false)
+                    .map(|start| lines(buffer, start.row(), cursor.row()))
+            },
+
+            (None, Event::Key(Key::Char('h'), Modifiers::NONE))
+            | (None, Event::Key(Key::Left, Modifiers::NONE)) => match cursor.col() {
+                0 => None,
+                col => Some((
+                    Included(Cursor::new(cursor.row(), col.saturating_sub(count))),
+                    Excluded(cursor),
+                    Kind::Char,
+                )),
+            },
+
+            (None, Event::Key(Key::Char('l'), Modifiers::NONE))
+            | (None, Event::Key(Key::Right, Modifiers::NONE)) => {
+                let len = buffer.line(cursor.row()).map_or(0, Row::len);
+                let end = Cursor::new(cursor.row(), cursor.col().saturating_add(count).min(len));
+
+                Some((Included(cursor), Excluded(end), Kind::Char)).filter(|_| end > cursor)
+            },
+
+            (None, Event::Key(Key::Char('w'), Modifiers::NONE))
+                if self.change && !is_blank(buffer, cursor) =>
+            {
+                // As in Vim, `cw` on a word changes up to its end like `ce`, but counts the word of
+                // the cursor even when it ends there.
+                match count - usize::from(Tail::ends_word(cursor, buffer)) {
+                    0 => Some(cursor),
+                    count => travel::<Tail>(buffer, cursor, count, true),
+                }
+                .map(|end| (Included(cursor), Included(end), Kind::Char))
+            },
+
+            (None, Event::Key(Key::Char('w'), Modifiers::NONE)) => words(buffer, cursor, count)
+                .map(|end| (Included(cursor), Excluded(end), Kind::Char)),
+
+            (None, Event::Key(Key::Char('b'), Modifiers::NONE)) => {
+                travel::<Head>(buffer, cursor, count, false)
+                    .map(|start| (Included(start), Excluded(cursor), Kind::Char))
+            },
+
+            (None, Event::Key(Key::Char('e'), Modifiers::NONE)) => {
+                travel::<Tail>(buffer, cursor, count, true)
+                    .map(|end| (Included(cursor), Included(end), Kind::Char))
+            },
+
+            (Some(prefix), Event::Key(Key::Char(ch), Modifiers::NONE))
+                if Object::from_char(ch).is_some() =>
+            {
+                Object::from_char(ch)
+                    .and_then(|object| object.span(buffer, cursor, prefix == 'a'))
+                    .map(|span| match span {
+                        Span::Chars(start, end) => (Included(start), Included(end), Kind::Char),
+                        Span::Lines(first, last) => lines(buffer, first, last),
+                    })
+            },
+
+            (_, Event::Key(Key::Esc, _)) => None,
+            (_, Event::Idle(idle)) if timed_out(idle, context.options.timeout_len) => None,
+
+            (prefix, _) => {
+                self.prefix = prefix;
+                return self;
+            },
+        };
+
+        match range {
+            Some((start, end, kind)) => (self.and_then)(context, start, end, kind),
+            None => Normal::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::words;
+    use crate::{Buffer, Cursor, Editor};
+
+    #[test]
+    fn test_abort() {
+        let mut editor = Editor::with_text("foo bar baz");
+
+        editor.feed("w2d3<Esc>");
+        assert_eq!(editor.mode(), "Normal");
+
+        editor.feed("w");
+        assert_eq!(editor.buffer().to_string(), "foo bar baz");
+        assert_eq!(editor.cursor(), Cursor::new(0, 8));
+    }
+
+    #[test]
+    fn test_unknown_key() {
+        let mut editor = Editor::with_text("foo bar baz");
+
+        editor.feed("dQ");
+        assert_eq!(editor.mode(), "Delete");
+
+        editor.feed("iQw");
+        assert_eq!(editor.buffer().to_string(), " bar baz");
+    }
+
+    #[test]
+    fn test_words() {
+        let buffer = Buffer::from("foo bar\nbaz");
+
+        assert_eq!(words(&buffer, Cursor::new(0, 0), 1), Some(Cursor::new(0, 4)));
+        assert_eq!(words(&buffer, Cursor::new(0, 4), 1), Some(Cursor::new(0, 7)));
+        assert_eq!(words(&buffer, Cursor::new(0, 4), 2), Some(Cursor::new(1, 3)));
+        assert_eq!(words(&buffer, Cursor::new(1, 3), 1), None);
+    }
+
+    #[test]
+    fn test_delete_words() {
+        let mut editor = Editor::with_text("foo bar\nbaz");
+
+        editor.feed("wdw");
+        assert_eq!(editor.buffer().to_string(), "foo \nbaz");
+
+        let mut editor = Editor::with_text("foo bar\nbaz");
+
+        editor.feed("jdw");
+        assert_eq!(editor.buffer().to_string(), "foo bar\n");
+
+        let mut editor = Editor::with_text("foo bar");
+
+        editor.feed("w5dw");
+        assert_eq!(editor.buffer().to_string(), "foo ");
     }
 }
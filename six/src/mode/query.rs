@@ -1,9 +1,12 @@
 use crate::buffer::Buffer;
-use crate::cursor::{ Cursor};
+use crate::cursor::{Cells, Cursor, Head};
 use crate::event::{Event, Key, Modifiers};
-use crate::mode::{Mode, Normal};
+use crate::mode::{Completion, Mode, Normal};
 use crate::state::Context;
 
+/// Returns the inputs that complete a partially typed one.
+pub type Complete = fn(&Context, &str) -> Vec<String>;
+
 /// Queries the user for a text input and applies an operation.
 #[derive(Derivative)]
 #[derivative(Debug)]
@@ -20,6 +23,19 @@ where
     /// The maximum length of the input.
     length: Option<usize>,
 
+    /// How far back in the history of the prompt the input was recalled from, if it was.
+    recall: Option<usize>,
+
+    /// The input typed before recalling the history, restored when going past the newest entry.
+    draft: String,
+
+    /// Completes the input when pressing Tab, if given.
+    #[derivative(Debug = "ignore")]
+    complete: Option<Complete>,
+
+    /// The completion being cycled through by repeatedly pressing Tab, if any.
+    completion: Option<Completion>,
+
     /// Function to be called after the input is submitted.
     #[derivative(Debug = "ignore")]
     and_then: Callback,
@@ -29,8 +45,31 @@ impl<Callback> Query<Callback>
 where
     Callback: 'static + Send + Sync + FnOnce(&mut Context, &str) -> Box<dyn Mode>,
 {
+    #[must_use]
     pub fn new(name: &'static str, length: Option<usize>, and_then: Callback) -> Box<Self> {
-        Box::new(Self { name, length, and_then, buffer: Buffer::default() })
+        Box::new(Self {
+            name,
+            length,
+            and_then,
+            buffer: Buffer::default(),
+            recall: None,
+            draft: String::new(),
+            complete: None,
+            completion: None,
+        })
+    }
+
+    /// Completes the input with the specified function when pressing Tab.
+    #[must_use]
+    pub fn with_completion(mut self: Box<Self>, complete: Complete) -> Box<Self> {
+        self.complete = Some(complete);
+        self
+    }
+
+    /// Replaces the input, moving the caret to its end.
+    fn replace(&mut self, input: &str) {
+        self.buffer = Buffer::from(input);
+        self.buffer.set_cursor(self.buffer.eof());
     }
 }
 
@@ -42,40 +81,205 @@ where
         self.name
     }
 
+    fn input(&self) -> Option<String> {
+        Some(self.buffer.to_string())
+    }
+
+    fn completion(&self) -> Option<&Completion> {
+        self.completion.as_ref()
+    }
+
     fn advance(mut self: Box<Self>, context: &mut Context, event: Event) -> Box<dyn Mode> {
-        unimplemented!()
-
-        // match event {
-        //     Event::Key(Key::Char(ch), Modifiers::NONE) => {
-        //         self.buffer.append(ch);
-
-        //         if ch == '\n' || self.length.map_or(false, |len| self.buffer.len() == len) {
-        //             (self.and_then)(context, self.buffer.as_str())
-        //         } else {
-        //             self
-        //         }
-        //     },
-
-        //     Event::Key(Key::Delete, Modifiers::NONE) => {
-        //         let end = self
-        //             .buffer
-        //             .cursor()
-        //             .forward::<Codepoint>(self.buffer.as_str())
-        //             .unwrap_or_else(|| Cursor::eof(self.buffer.as_str()));
-        //         self.buffer.edit("", self.buffer.cursor()..end);
-
-        //         self
-        //     },
-
-        //     Event::Key(Key::Left, Modifiers::NONE) => {
-        //         if self.buffer.backward::<Codepoint>().is_some() {
-        //             self
-        //         } else {
-        //             Normal::new()
-        //         }
-        //     },
-
-        //     _ => Normal::new(),
-        // }
+        let completion = self.completion.take();
+        let buffer = &mut self.buffer;
+
+        match event {
+            Event::Key(Key::Esc, _) => Normal::new(),
+
+            Event::Key(Key::Char('\t'), Modifiers::NONE) if self.complete.is_some() => {
+                let completion = completion.map(Completion::next).or_else(|| {
+                    let typed = self.buffer.to_string();
+                    let candidates = self.complete.map(|complete| complete(context, &typed));
+
+                    Completion::new(typed, candidates.unwrap_or_default())
+                });
+
+                if let Some(completion) = completion {
+                    let input = completion.current().to_owned();
+
+                    self.replace(&input);
+                    self.completion = Some(completion);
+                }
+
+                self
+            },
+
+            Event::Key(Key::Char('\n'), Modifiers::NONE) => {
+                let input = self.buffer.to_string();
+                context.histories.entry(self.name).or_default().push(input.as_str());
+
+                (self.and_then)(context, &input)
+            },
+
+            Event::Key(Key::Up, Modifiers::NONE) => {
+                let age = self.recall.map_or(0, |age| age + 1);
+                let history = context.histories.entry(self.name).or_default();
+
+                if let Some(entry) = history.get(age) {
+                    if self.recall.is_none() {
+                        self.draft = self.buffer.to_string();
+                    }
+
+                    self.recall = Some(age);
+                    self.replace(entry);
+                }
+
+                self
+            },
+
+            Event::Key(Key::Down, Modifiers::NONE) => {
+                match self.recall {
+                    Some(0) => {
+                        let draft = std::mem::take(&mut self.draft);
+
+                        self.recall = None;
+                        self.replace(&draft);
+                    },
+
+                    Some(age) => {
+                        let history = context.histories.entry(self.name).or_default();
+                        let entry = history.get(age - 1);
+
+                        self.recall = Some(age - 1);
+                        self.replace(entry.unwrap_or_default());
+                    },
+
+                    None => {},
+                }
+
+                self
+            },
+
+            Event::Key(Key::Char('w'), Modifiers::CTRL) => {
+                let end = buffer.cursor();
+                let start = buffer.backward::<Head>().unwrap_or_else(Cursor::origin);
+
+                buffer.edit("", start..end);
+                buffer.set_cursor(start);
+
+                self
+            },
+
+            Event::Key(Key::Char('u'), Modifiers::CTRL) => {
+                let end = buffer.cursor();
+
+                buffer.edit("", ..end);
+                buffer.set_cursor(Cursor::origin());
+
+                self
+            },
+
+            Event::Key(Key::Char(ch), Modifiers::NONE) => {
+                buffer.append(ch);
+
+                if self.length.map_or(false, |len| buffer.eof().col() >= len) {
+                    let input = self.buffer.to_string();
+                    (self.and_then)(context, &input)
+                } else {
+                    self
+                }
+            },
+
+            Event::Key(Key::Backspace, Modifiers::NONE) => {
+                let end = buffer.cursor();
+
+                if let Some(start) = buffer.backward::<Cells>() {
+                    buffer.edit("", start..end);
+                    self
+                } else {
+                    Normal::new()
+                }
+            },
+
+            Event::Key(Key::Left, Modifiers::NONE) => {
+                buffer.backward::<Cells>();
+                self
+            },
+
+            Event::Key(Key::Right, Modifiers::NONE) => {
+                buffer.forward::<Cells>();
+                self
+            },
+
+            _ => self,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Editor;
+
+    #[test]
+    fn test_delete_word() {
+        let mut editor = Editor::new();
+
+        editor.feed(":call foo.bar <C-w>");
+        assert_eq!(editor.input().as_deref(), Some("call foo."));
+
+        editor.feed("<C-w><C-w>x");
+        assert_eq!(editor.input().as_deref(), Some("call x"));
+
+        editor.feed("<C-w><C-w><C-w>");
+        assert_eq!(editor.input().as_deref(), Some(""));
+        assert_eq!(editor.mode(), "Command");
+    }
+
+    #[test]
+    fn test_move_and_insert() {
+        let mut editor = Editor::new();
+
+        editor.feed(":cal foo<Left><Left><Left><Left>l");
+        assert_eq!(editor.input().as_deref(), Some("call foo"));
+
+        editor.feed("<Left><Left><Left><Left><Left>x");
+        assert_eq!(editor.input().as_deref(), Some("xcall foo"));
+
+        editor.feed(&"<Right>".repeat(9));
+        editor.feed("!");
+        assert_eq!(editor.input().as_deref(), Some("xcall foo!"));
+    }
+
+    #[test]
+    fn test_history() {
+        let mut editor = Editor::new();
+
+        editor.feed(":set ff=dos<CR>:set ff=unix<CR>:set ff=unix<CR>");
+        editor.feed(":set<Up><Up>");
+        assert_eq!(editor.input().as_deref(), Some("set ff=dos"));
+
+        editor.feed("<Up>");
+        assert_eq!(editor.input().as_deref(), Some("set ff=dos"));
+
+        editor.feed("<Down>");
+        assert_eq!(editor.input().as_deref(), Some("set ff=unix"));
+
+        editor.feed("<Down>");
+        assert_eq!(editor.input().as_deref(), Some("set"));
+
+        editor.feed("<Up><BS>x");
+        assert_eq!(editor.input().as_deref(), Some("set ff=unix"));
+    }
+
+    #[test]
+    fn test_delete_line() {
+        let mut editor = Editor::new();
+
+        editor.feed(":call foo<C-u>");
+        assert_eq!(editor.input().as_deref(), Some(""));
+
+        editor.feed("set ff=dos<CR>");
+        assert_eq!(editor.mode(), "Normal");
+        assert_eq!(editor.message(), None);
     }
 }
@@ -1,8 +1,899 @@
-use crate::cursor::Cursor;
+use std::convert::TryFrom;
+use std::ops::{Bound, Range};
+
+use itertools::Itertools;
+
+use crate::buffer::{Buffer, Edit, Row};
+use crate::cursor::{Cursor, Object, Span};
+use crate::event::{Event, Key, Modifiers};
+use crate::mode::normal::{
+    comment_lines, cut, goto_line, increment, indent_lines, join, reflow_lines, register,
+    shift_lines, text_motion, vertical,
+};
+use crate::mode::operator::lines;
+use crate::mode::{timed_out, Mode, Normal};
+use crate::register::{Register, Registers};
+use crate::state::Context;
+
+/// The shape of a selection.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Kind {
+    /// Every character between the ends, as in `v`.
+    Char,
+
+    /// Every line between the ends, as in `V`.
+    Line,
+
+    /// The rectangle with the ends as its corners, as in `Ctrl-v`.
+    Block,
+}
+
+/// A text range selected in visual mode.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Selection {
+    /// The end of the selection that stays in place.
+    pub anchor: Cursor,
+
+    /// The end of the selection that follows the cursor.
+    pub cursor: Cursor,
+
+    /// The shape of the selection.
+    pub kind: Kind,
+}
 
 /// Selects a text range.
 #[derive(Derivative)]
 #[derivative(Debug)]
 pub struct Select {
+    /// The end of the selection that stays in place.
     anchor: Cursor,
+
+    /// The shape of the selection.
+    kind: Kind,
+
+    /// The number of times the next motion should be repeated, if given.
+    count: Option<usize>,
+
+    /// The first key of a pending text object, such as `i` in `iw`.
+    prefix: Option<char>,
+
+    /// The register that the next cut goes into, as given after `"`.
+    register: Option<char>,
+}
+
+impl Kind {
+    /// Returns the shape of selection started by a key, as in `v`, `V` and `Ctrl-v`.
+    pub(super) fn from_key(key: Key, modifiers: Modifiers) -> Option<Self> {
+        match (key, modifiers) {
+            (Key::Char('v'), Modifiers::NONE) => Some(Self::Char),
+            (Key::Char('V'), Modifiers::NONE) => Some(Self::Line),
+            (Key::Char('v'), Modifiers::CTRL) => Some(Self::Block),
+            _ => None,
+        }
+    }
+}
+
+/// Returns some text with the case of each letter swapped, as in `~`.
+fn toggle_case(text: &str) -> String {
+    text.chars()
+        .flat_map(|ch| {
+            let swapped: Vec<_> = if ch.is_lowercase() {
+                ch.to_uppercase().collect()
+            } else {
+                ch.to_lowercase().collect()
+            };
+
+            swapped
+        })
+        .collect()
+}
+
+/// Moves the cursor `count` times over the motion of a key, if it is one.
+fn motion(context: &mut Context, key: Key, count: usize) {
+    match key {
+        Key::Char('h') | Key::Left => {
+            let buffer = &mut context.buffer;
+            let cursor = buffer.cursor();
+
+            buffer.set_cursor(Cursor::new(cursor.row(), cursor.col().saturating_sub(count)));
+        },
+
+        Key::Char('l') | Key::Right => {
+            let buffer = &mut context.buffer;
+            let cursor = buffer.cursor();
+            let cursor = Cursor::new(cursor.row(), cursor.col().saturating_add(count));
+
+            buffer.set_cursor(cursor.clamp_to_line(buffer));
+        },
+
+        Key::Char('j') | Key::Down => vertical(context, count, true),
+        Key::Char('k') | Key::Up => vertical(context, count, false),
+
+        Key::Char(ch @ 'w')
+        | Key::Char(ch @ 'b')
+        | Key::Char(ch @ 'e')
+        | Key::Char(ch @ '{')
+        | Key::Char(ch @ '}') => {
+            text_motion(&mut context.buffer, ch, count);
+        },
+
+        _ => {},
+    }
+}
+
+impl Selection {
+    /// Moves both ends of the selection along with the text after a change to it.
+    pub fn adjust(&mut self, edit: &Edit) {
+        let (anchor, cursor) = edit.adjust_range(self.anchor, self.cursor);
+
+        self.anchor = anchor;
+        self.cursor = cursor;
+    }
+
+    /// Returns the first selected position.
+    #[must_use]
+    pub fn start(&self) -> Cursor {
+        let start = self.anchor.min(self.cursor);
+
+        match self.kind {
+            Kind::Char => start,
+            Kind::Line => Cursor::new(start.row(), 0),
+            Kind::Block => Cursor::new(start.row(), self.anchor.col().min(self.cursor.col())),
+        }
+    }
+
+    /// Returns the columns covered by the selection in each of its lines, in order.
+    ///
+    /// Line breaks are not covered, and blockwise selections cover the same columns in every line,
+    /// as far as the line goes.
+    #[must_use]
+    pub fn spans(&self, buffer: &Buffer) -> Vec<(usize, Range<usize>)> {
+        let (start, end) = (self.anchor.min(self.cursor), self.anchor.max(self.cursor));
+
+        let left = self.anchor.col().min(self.cursor.col());
+        let right = self.anchor.col().max(self.cursor.col()) + 1;
+
+        (start.row()..=end.row())
+            .filter_map(|row| Some((row, buffer.line(row)?.len())))
+            .map(|(row, len)| {
+                let (first, last) = match self.kind {
+                    Kind::Char => (
+                        if row == start.row() { start.col() } else { 0 },
+                        if row == end.row() { end.col() + 1 } else { len },
+                    ),
+                    Kind::Line => (0, len),
+                    Kind::Block => (left, right),
+                };
+
+                (row, first.min(len)..last.min(len))
+            })
+            .collect()
+    }
+}
+
+impl Select {
+    /// Returns a new instance of this mode, selecting from `anchor` to the cursor.
+    #[must_use]
+    pub fn new(anchor: Cursor, kind: Kind) -> Box<Self> {
+        Box::new(Self { anchor, kind, count: None, prefix: None, register: None })
+    }
+
+    /// Restores the last selection, as in `gv`, or stays in normal mode if there was none.
+    ///
+    /// Ends that no longer exist after later edits are moved to the closest positions that do.
+    pub fn reselect(context: &mut Context) -> Box<dyn Mode> {
+        match context.selection {
+            Some(selection) => {
+                let buffer = &mut context.buffer;
+                buffer.set_cursor(selection.cursor.clamp_to_line(buffer));
+
+                Self::new(selection.anchor.clamp_to_line(buffer), selection.kind)
+            },
+
+            None => Normal::new(),
+        }
+    }
+
+    /// Returns the first and last ends of the selection, given the cursor.
+    fn ends(&self, cursor: Cursor) -> (Cursor, Cursor) {
+        (self.anchor.min(cursor), self.anchor.max(cursor))
+    }
+
+    /// Returns the number of times the next motion should be repeated.
+    fn count(&self) -> usize {
+        self.count.unwrap_or(1)
+    }
+
+    /// Selects a text object, as in `iw`, or grows the selection if it spans more than one
+    /// character.
+    ///
+    /// Selections grow to the innermost pair of brackets around them, or to the end of the next
+    /// object of other kinds, as when repeating `iw`.
+    fn select(&mut self, context: &mut Context, object: Object, around: bool) {
+        let buffer = &mut context.buffer;
+        let cursor = buffer.cursor();
+
+        let (start, end) = self.ends(cursor);
+
+        let span = if start == end || object == Object::Entire {
+            object.span(buffer, cursor, around)
+        } else if let Object::Bracket(..) | Object::Tag = object {
+            object.enclosing(buffer, start, end, around)
+        } else {
+            let next = match object {
+                Object::Paragraph => Some(Cursor::new(end.row() + 1, 0)),
+                _ => buffer
+                    .char_indices_from(end)
+                    .skip(1)
+                    .find(|&(_, ch)| ch != '\n')
+                    .map(|(at, _)| at),
+            };
+
+            next.and_then(|next| object.span(buffer, next, around)).map(|span| match span {
+                Span::Chars(_, last) => Span::Chars(start, last),
+                Span::Lines(_, last) => Span::Lines(start.row(), last),
+            })
+        };
+
+        match span {
+            Some(Span::Chars(first, last)) => {
+                self.anchor = first;
+                self.kind = Kind::Char;
+
+                buffer.set_cursor(last.clamp_to_line(buffer));
+            },
+
+            Some(Span::Lines(first, last)) => {
+                self.anchor = Cursor::new(first, 0);
+                self.kind = Kind::Line;
+
+                buffer.set_cursor(Cursor::new(last, 0));
+            },
+
+            None => {},
+        }
+    }
+
+    /// Adds `delta` to the first number of each selected line, as in `Ctrl-a`, or as in `g Ctrl-a`
+    /// if `progressive` is set, where each line with a number gets a further multiple of `delta`.
+    ///
+    /// Only the selected part of each line is searched for a number. Leaves the cursor at the start
+    /// of the selection.
+    fn increment(&self, context: &mut Context, delta: i64, progressive: bool) {
+        let selection = self.selection(context.buffer.cursor());
+        let mut step = delta;
+
+        for (row, cols) in selection.spans(&context.buffer) {
+            context.buffer.set_cursor(Cursor::new(row, cols.start));
+
+            if increment(context, step, cols).is_some() && progressive {
+                step = step.saturating_add(delta);
+            }
+        }
+
+        let buffer = &mut context.buffer;
+        buffer.set_cursor(selection.start().clamp_to_line(buffer));
+    }
+
+    /// Replaces the text of each selected line with the result of a function, leaving the cursor at
+    /// the start of the selection.
+    fn transform(&self, context: &mut Context, f: impl Fn(&str) -> String) {
+        let buffer = &mut context.buffer;
+        let selection = self.selection(buffer.cursor());
+
+        for (row, cols) in selection.spans(buffer) {
+            let range = Cursor::new(row, cols.start)..Cursor::new(row, cols.end);
+            buffer.edit(&f(&buffer.slice(range.clone())), range);
+        }
+
+        buffer.set_cursor(selection.start().clamp_to_line(buffer));
+    }
+
+    /// Copies the selected text into a register, and deletes it unless `key` is `y`, as in `d` and
+    /// `y`.
+    ///
+    /// Blockwise selections are copied as one line of text for each selected line.
+    fn cut(&self, context: &mut Context, key: char, register: char) {
+        let buffer = &mut context.buffer;
+        let (start, end) = self.ends(buffer.cursor());
+
+        match self.kind {
+            Kind::Char => {
+                let (start, end) = (Bound::Included(start), Bound::Included(end));
+                cut(context, key, register, start, end, Kind::Char);
+            },
+
+            Kind::Line => {
+                let (start, end, kind) = lines(buffer, start.row(), end.row());
+                cut(context, key, register, start, end, kind);
+            },
+
+            Kind::Block => {
+                let selection = self.selection(buffer.cursor());
+                let spans = selection.spans(buffer);
+
+                let text = spans
+                    .iter()
+                    .map(|(row, cols)| {
+                        buffer.slice(Cursor::new(*row, cols.start)..Cursor::new(*row, cols.end))
+                    })
+                    .join("\n");
+
+                context.store(register, Register::new(text, false));
+
+                if key == 'y' {
+                    let buffer = &mut context.buffer;
+                    buffer.set_cursor(selection.start().clamp_to_line(buffer));
+                } else {
+                    self.transform(context, |_| String::new());
+                }
+            },
+        }
+    }
+
+    /// Replaces the selected text with the contents of the register given after `"`, or the
+    /// unnamed one, put `count` times, as in `p`, and copies the replaced text into the unnamed
+    /// register.
+    ///
+    /// Linewise text replacing characters is put in lines of its own, and characterwise text
+    /// replacing lines becomes a line of its own. Blockwise selections are deleted and the text is
+    /// put where they started.
+    fn replace(&self, context: &mut Context, count: usize) {
+        let register = match register(context, self.register.unwrap_or(Registers::UNNAMED)) {
+            Some(register) => register,
+            None => return,
+        };
+
+        let (start, end) = self.ends(context.buffer.cursor());
+        let mut text = register.text.repeat(count);
+
+        let range = match self.kind {
+            Kind::Char => (Bound::Included(start), Bound::Included(end)),
+            Kind::Line => {
+                let (start, end, _) = lines(&context.buffer, start.row(), end.row());
+                (start, end)
+            },
+
+            Kind::Block => {
+                let start = self.selection(context.buffer.cursor()).start();
+                self.cut(context, 'd', Registers::UNNAMED);
+
+                let buffer = &mut context.buffer;
+                let start = buffer.clamp(start);
+                let end = buffer.edit(text.trim_end_matches('\n'), start..start);
+
+                buffer.set_cursor(Cursor::new(end.row(), end.col().saturating_sub(1)));
+                return;
+            },
+        };
+
+        let linewise = self.kind == Kind::Line;
+        let mut replaced = context.buffer.slice(range);
+
+        if linewise && !replaced.ends_with('\n') {
+            replaced.push('\n');
+        }
+
+        match (register.linewise, linewise) {
+            (true, false) => text.insert(0, '\n'),
+            (false, true) => text.push('\n'),
+            _ => {},
+        }
+
+        if linewise && range.1 == Bound::Unbounded {
+            // The last line has no line break after it.
+            text.pop();
+        }
+
+        context.store(Registers::UNNAMED, Register::new(replaced, linewise));
+
+        let buffer = &mut context.buffer;
+        let end = buffer.edit(&text, range);
+
+        let cursor = if register.linewise {
+            let row = start.row() + usize::from(!linewise);
+            Cursor::new(row, buffer.line(row).map_or(0, Row::first_non_blank))
+        } else {
+            Cursor::new(end.row(), end.col().saturating_sub(1))
+        };
+
+        buffer.set_cursor(cursor.clamp_to_line(buffer));
+    }
+
+    /// Applies the operator of a key to the selection right away and leaves visual mode, as in `d`
+    /// or `gq`, or replaces the selection, as in `p`.
+    ///
+    /// Operators other than `d`, `x` and `y` work on the whole selected lines, and shifting them
+    /// with `>` or `<` repeats `count` times.
+    fn operate(&self, context: &mut Context, key: char, count: usize) -> Box<dyn Mode> {
+        let (start, end) = self.ends(context.buffer.cursor());
+        let (start, end, _) = lines(&context.buffer, start.row(), end.row());
+
+        let mode = self.leave(context);
+
+        match key {
+            'd' | 'x' | 'y' => self.cut(context, key, self.register.unwrap_or(Registers::UNNAMED)),
+            'p' => self.replace(context, count),
+            '=' => indent_lines(context, start, end),
+            'c' => comment_lines(context, start, end),
+            'q' => reflow_lines(context, start, end),
+            _ => (0..count).for_each(|_| shift_lines(context, start, end, key == '>')),
+        }
+
+        mode
+    }
+
+    /// Returns the current selection, given the cursor.
+    fn selection(&self, cursor: Cursor) -> Selection {
+        Selection { anchor: self.anchor, cursor, kind: self.kind }
+    }
+
+    /// Leaves visual mode, remembering the selection for `gv`.
+    fn leave(&self, context: &mut Context) -> Box<dyn Mode> {
+        context.selection = Some(self.selection(context.buffer.cursor()));
+
+        Normal::new()
+    }
+}
+
+impl Mode for Select {
+    fn name(&self) -> &str {
+        match self.kind {
+            Kind::Char => "Visual",
+            Kind::Line => "Visual Line",
+            Kind::Block => "Visual Block",
+        }
+    }
+
+    fn advance(mut self: Box<Self>, context: &mut Context, event: Event) -> Box<dyn Mode> {
+        let count = self.count();
+
+        match (self.prefix.take(), event) {
+            (None, Event::Key(Key::Char(ch @ '0'..='9'), Modifiers::NONE))
+                if ch != '0' || self.count.is_some() =>
+            {
+                let digit = ch.to_digit(10).unwrap_or_default() as usize;
+                self.count = Some(self.count.unwrap_or(0).saturating_mul(10).saturating_add(digit));
+
+                return self;
+            },
+
+            (None, Event::Key(key, modifiers)) if Kind::from_key(key, modifiers).is_some() => {
+                match Kind::from_key(key, modifiers) {
+                    Some(kind) if kind != self.kind => self.kind = kind,
+                    _ => return self.leave(context),
+                }
+            },
+
+            (_, Event::Key(Key::Esc, _)) => return self.leave(context),
+
+            (None, Event::Key(Key::Char(ch @ 'i'), Modifiers::NONE))
+            | (None, Event::Key(Key::Char(ch @ 'a'), Modifiers::NONE))
+            | (None, Event::Key(Key::Char(ch @ 'g'), Modifiers::NONE))
+            | (None, Event::Key(Key::Char(ch @ 'r'), Modifiers::NONE))
+            | (None, Event::Key(Key::Char(ch @ '"'), Modifiers::NONE)) => {
+                self.prefix = Some(ch);
+                return self;
+            },
+
+            (Some('"'), Event::Key(Key::Char(name), Modifiers::NONE)) => self.register = Some(name),
+
+            (Some('r'), Event::Key(Key::Char(ch), Modifiers::NONE)) if ch != '\n' => {
+                let mode = self.leave(context);
+                self.transform(context, |text| {
+                    std::iter::repeat(ch).take(text.chars().count()).collect()
+                });
+
+                return mode;
+            },
+
+            (None, Event::Key(Key::Char(ch @ 'u'), Modifiers::NONE))
+            | (None, Event::Key(Key::Char(ch @ 'U'), Modifiers::NONE))
+            | (None, Event::Key(Key::Char(ch @ '~'), Modifiers::NONE)) => {
+                let mode = self.leave(context);
+
+                match ch {
+                    'u' => self.transform(context, str::to_lowercase),
+                    'U' => self.transform(context, str::to_uppercase),
+                    _ => self.transform(context, toggle_case),
+                }
+
+                return mode;
+            },
+
+            (Some(prefix @ 'i'), Event::Key(Key::Char(ch), Modifiers::NONE))
+            | (Some(prefix @ 'a'), Event::Key(Key::Char(ch), Modifiers::NONE)) => {
+                if let Some(object) = Object::from_char(ch) {
+                    self.select(context, object, prefix == 'a');
+                }
+            },
+
+            (prefix, Event::Key(Key::Char('J'), Modifiers::NONE))
+                if prefix.map_or(true, |prefix| prefix == 'g') =>
+            {
+                let (start, end) = self.ends(context.buffer.cursor());
+                let mode = self.leave(context);
+
+                context.buffer.set_cursor(start);
+                join(context, end.row() - start.row() + 1, prefix.is_some());
+
+                return mode;
+            },
+
+            (prefix, Event::Key(Key::Char(ch @ 'a'), Modifiers::CTRL))
+            | (prefix, Event::Key(Key::Char(ch @ 'x'), Modifiers::CTRL))
+                if prefix.map_or(true, |prefix| prefix == 'g') =>
+            {
+                let delta = i64::try_from(count).unwrap_or(i64::MAX);
+                let mode = self.leave(context);
+
+                self.increment(context, if ch == 'a' { delta } else { -delta }, prefix.is_some());
+                return mode;
+            },
+
+            (None, Event::Key(Key::Char(ch), Modifiers::NONE)) if "dxyp<>=".contains(ch) => {
+                return self.operate(context, ch, count);
+            },
+
+            (Some('g'), Event::Key(Key::Char(ch), Modifiers::NONE)) if "cq".contains(ch) => {
+                return self.operate(context, ch, count);
+            },
+
+            (None, Event::Key(Key::Char('G'), Modifiers::NONE)) => goto_line(context, self.count),
+
+            (Some('g'), Event::Key(Key::Char('g'), Modifiers::NONE)) => {
+                goto_line(context, Some(count));
+            },
+
+            (None, Event::Key(Key::Char(ch @ 'o'), Modifiers::NONE))
+            | (None, Event::Key(Key::Char(ch @ 'O'), Modifiers::NONE)) => {
+                let buffer = &mut context.buffer;
+                let cursor = buffer.cursor();
+
+                if ch == 'O' && self.kind == Kind::Block {
+                    // Only move to the other side of the same line.
+                    buffer.set_cursor(Cursor::new(cursor.row(), self.anchor.col()));
+                    self.anchor = Cursor::new(self.anchor.row(), cursor.col());
+                } else {
+                    buffer.set_cursor(self.anchor);
+                    self.anchor = cursor;
+                }
+            },
+
+            (None, Event::Key(key, Modifiers::NONE)) => motion(context, key, count),
+
+            (prefix, Event::Idle(idle)) => {
+                if !timed_out(idle, context.options.timeout_len) {
+                    self.prefix = prefix;
+                }
+
+                return self;
+            },
+
+            _ => return self,
+        }
+
+        self.count = None;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Kind, Selection};
+    use crate::event;
+    use crate::mode::{Mode, Normal};
+    use crate::register::{Register, Registers};
+    use crate::state::Context;
+    use crate::{Buffer, Cursor};
+
+    fn run(text: &str, keys: &str) -> (Context, Box<dyn Mode>) {
+        let mut context = Context { buffer: Buffer::from(text), ..Context::default() };
+
+        let mode = event::parse(keys)
+            .into_iter()
+            .fold(Normal::new() as Box<dyn Mode>, |mode, event| mode.advance(&mut context, event));
+
+        (context, mode)
+    }
+
+    #[test]
+    fn test_leave() {
+        let (context, mode) = run("foo bar\nbaz", "wvj<Esc>");
+
+        let selection =
+            Selection { anchor: Cursor::new(0, 4), cursor: Cursor::new(1, 2), kind: Kind::Char };
+
+        assert_eq!(mode.name(), "Normal");
+        assert_eq!(context.selection, Some(selection));
+
+        let (context, mode) = run("foo bar\nbaz", "VwvVV");
+
+        assert_eq!(mode.name(), "Normal");
+        assert_eq!(context.selection.map(|selection| selection.kind), Some(Kind::Line));
+    }
+
+    #[test]
+    fn test_other_end() {
+        let (context, _) = run("foo bar\nbaz", "wvjoh<Esc>");
+
+        let selection =
+            Selection { anchor: Cursor::new(1, 2), cursor: Cursor::new(0, 3), kind: Kind::Char };
+
+        assert_eq!(context.selection, Some(selection));
+
+        let (context, _) = run("foo bar\nbaz qux", "w<C-v>jbOh<Esc>");
+
+        let selection =
+            Selection { anchor: Cursor::new(0, 0), cursor: Cursor::new(1, 3), kind: Kind::Block };
+
+        assert_eq!(context.selection, Some(selection));
+
+        let (context, _) = run("foo bar\nbaz qux", "wVjbOh<Esc>");
+
+        let selection =
+            Selection { anchor: Cursor::new(1, 0), cursor: Cursor::new(0, 3), kind: Kind::Line };
+
+        assert_eq!(context.selection, Some(selection));
+    }
+
+    #[test]
+    fn test_object() {
+        let (context, mode) = run("foo bar baz", "wviw");
+
+        assert_eq!(mode.name(), "Visual");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 6));
+
+        let (context, _) = run("foo bar baz", "wviw<Esc>");
+
+        let selection =
+            Selection { anchor: Cursor::new(0, 4), cursor: Cursor::new(0, 6), kind: Kind::Char };
+
+        assert_eq!(context.selection, Some(selection));
+
+        let (context, _) = run("foo bar baz", "wviwiwiw<Esc>");
+        assert_eq!(context.selection.map(|selection| selection.cursor), Some(Cursor::new(0, 10)));
+
+        let (context, _) = run("f(a, (b))", "wwwwvi(a(i(<Esc>");
+
+        let selection =
+            Selection { anchor: Cursor::new(0, 2), cursor: Cursor::new(0, 7), kind: Kind::Char };
+
+        assert_eq!(context.selection, Some(selection));
+
+        let (context, _) = run("<a><b>xy</b></a>", "fxvitit<Esc>");
+
+        let selection =
+            Selection { anchor: Cursor::new(0, 3), cursor: Cursor::new(0, 11), kind: Kind::Char };
+
+        assert_eq!(context.selection, Some(selection));
+
+        let (context, _) = run("<a><b>xy</b></a>", "fxvititat<Esc>");
+        assert_eq!(context.selection.map(|selection| selection.cursor), Some(Cursor::new(0, 15)));
+    }
+
+    #[test]
+    fn test_increment() {
+        let (context, mode) = run("0\n0\n0", "Vjjg<C-a>");
+
+        assert_eq!(mode.name(), "Normal");
+        assert_eq!(context.buffer.to_string(), "1\n2\n3");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 0));
+
+        let (context, _) = run("x 1\n\n1 y 1\n1", "vjj2g<C-x>");
+        assert_eq!(context.buffer.to_string(), "x -1\n\n-3 y 1\n1");
+
+        let (context, _) = run("1 1\n1 1", "w<C-v>j<C-a>");
+        assert_eq!(context.buffer.to_string(), "1 2\n1 2");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 2));
+
+        let (context, _) = run("12 4\n-3", "f2vj<C-a>");
+        assert_eq!(context.buffer.to_string(), "13 4\n-2");
+
+        let (context, _) = run("x 1\n-1 1", "<C-v>j<C-a>");
+        assert_eq!(context.buffer.to_string(), "x 1\n-1 1");
+    }
+
+    #[test]
+    fn test_join() {
+        let (context, mode) = run("foo\n  bar\nbaz\nqux", "VjjJ");
+
+        assert_eq!(mode.name(), "Normal");
+        assert_eq!(context.buffer.to_string(), "foo bar baz\nqux");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 7));
+
+        let (context, _) = run("foo\n  bar\nbaz\nqux", "jjvkkJ");
+        assert_eq!(context.buffer.to_string(), "foo bar baz\nqux");
+
+        let (context, _) = run("foo\n  bar\nbaz\nqux", "vJ");
+        assert_eq!(context.buffer.to_string(), "foo bar\nbaz\nqux");
+
+        let (context, _) = run("foo\n  bar\nbaz\nqux", "vjjgJ");
+        assert_eq!(context.buffer.to_string(), "foo  barbaz\nqux");
+    }
+
+    #[test]
+    fn test_spans() {
+        let buffer = Buffer::from("foo bar\nba\nbaz qux");
+        let (anchor, cursor) = (Cursor::new(2, 1), Cursor::new(0, 4));
+
+        let selection = Selection { anchor, cursor, kind: Kind::Char };
+        assert_eq!(selection.spans(&buffer), vec![(0, 4..7), (1, 0..2), (2, 0..2)]);
+
+        let selection = Selection { anchor, cursor, kind: Kind::Line };
+        assert_eq!(selection.spans(&buffer), vec![(0, 0..7), (1, 0..2), (2, 0..7)]);
+
+        let selection = Selection { anchor, cursor, kind: Kind::Block };
+        assert_eq!(selection.spans(&buffer), vec![(0, 1..5), (1, 1..2), (2, 1..5)]);
+    }
+
+    #[test]
+    fn test_replace() {
+        let (context, mode) = run("foo bar\nbaz qux", "wvjr-");
+
+        assert_eq!(mode.name(), "Normal");
+        assert_eq!(context.buffer.to_string(), "foo ---\n-----ux");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 4));
+
+        let (context, _) = run("foo bar\nbaz qux", "jVkrx");
+        assert_eq!(context.buffer.to_string(), "xxxxxxx\nxxxxxxx");
+    }
+
+    #[test]
+    fn test_replace_block() {
+        let (context, _) = run("foo bar\nbaz qux\n\nquux", "w<C-v>j3hr.");
+
+        assert_eq!(context.buffer.to_string(), "f....ar\nb....ux\n\nquux");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 1));
+    }
+
+    #[test]
+    fn test_case() {
+        let (context, mode) = run("foo bar\nbaz qux", "wvjU");
+
+        assert_eq!(mode.name(), "Normal");
+        assert_eq!(context.buffer.to_string(), "foo BAR\nBAZ Qux");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 4));
+
+        let (context, _) = run("Foo Bar\nBaz", "Vu");
+        assert_eq!(context.buffer.to_string(), "foo bar\nBaz");
+
+        let (context, _) = run("Foo Bar\nBaz", "ve~");
+        assert_eq!(context.buffer.to_string(), "fOO Bar\nBaz");
+    }
+
+    #[test]
+    fn test_reselect() {
+        let (context, mode) = run("foo bar\nbaz", "wv2h<Esc>jgv");
+
+        assert_eq!(mode.name(), "Visual");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 2));
+
+        let (context, mode) = run("foo bar\nbaz", "wv2h<Esc>gv<Esc>");
+
+        let selection =
+            Selection { anchor: Cursor::new(0, 4), cursor: Cursor::new(0, 2), kind: Kind::Char };
+
+        assert_eq!(mode.name(), "Normal");
+        assert_eq!(context.selection, Some(selection));
+    }
+
+    #[test]
+    fn test_reselect_clamped() {
+        let (mut context, _) = run("foo bar\nbaz", "wvj<Esc>");
+        context.buffer.edit("", Cursor::new(0, 1)..);
+
+        let mode = event::parse("gv")
+            .into_iter()
+            .fold(Normal::new() as Box<dyn Mode>, |mode, event| mode.advance(&mut context, event));
+
+        assert_eq!(mode.name(), "Visual");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 0));
+    }
+
+    #[test]
+    fn test_whole_buffer() {
+        let (context, _) = run("foo\n  bar\nbaz", "jggVGyGp");
+
+        assert_eq!(context.buffer.to_string(), "foo\n  bar\nbaz\nfoo\n  bar\nbaz");
+        assert_eq!(context.buffer.cursor(), Cursor::new(3, 0));
+
+        let (context, _) = run("foo\n  bar\nbaz", "GVggd");
+
+        assert_eq!(context.buffer.to_string(), "");
+        assert_eq!(
+            context.registers.get(Registers::UNNAMED),
+            Some(&Register::new("foo\n  bar\nbaz\n", true))
+        );
+
+        let (context, _) = run("foo\n  bar\nbaz", "Vj2Gd");
+        assert_eq!(context.buffer.to_string(), "baz");
+    }
+
+    #[test]
+    fn test_cut_paragraph() {
+        let (context, _) = run("foo\nbar\n\nbaz", "jvipd");
+        assert_eq!(context.buffer.to_string(), "\nbaz");
+
+        let (context, _) = run("foo\nbar\n\nbaz", "jvapd");
+        assert_eq!(context.buffer.to_string(), "baz");
+    }
+
+    #[test]
+    fn test_operators() {
+        let (context, mode) = run("foo bar\nbaz", "vlld");
+
+        assert_eq!(mode.name(), "Normal");
+        assert_eq!(context.buffer.to_string(), " bar\nbaz");
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 0));
+
+        let (context, mode) = run("foo\nbar\nbaz", "jVd");
+
+        assert_eq!(mode.name(), "Normal");
+        assert_eq!(context.buffer.to_string(), "foo\nbaz");
+        assert_eq!(context.buffer.cursor(), Cursor::new(1, 0));
+
+        let (context, _) = run("foo\nbar\nbaz", "vj2>");
+        assert_eq!(context.buffer.to_string(), "\t\tfoo\n\t\tbar\nbaz");
+
+        let (context, _) = run("  foo\n  bar", "Vj<");
+        assert_eq!(context.buffer.to_string(), "foo\nbar");
+
+        let (context, _) = run("foo\nbar\nbaz", "jVjgc");
+        assert_eq!(context.buffer.to_string(), "foo\n// bar\n// baz");
+        assert_eq!(context.buffer.cursor(), Cursor::new(1, 0));
+
+        let (context, _) = run("foo\nbar\n\nbaz", "Vjgq");
+        assert_eq!(context.buffer.to_string(), "foo bar\n\nbaz");
+
+        let (context, _) = run("foo\n   bar", "Vj=");
+        assert_eq!(context.buffer.to_string(), "foo\nbar");
+    }
+
+    #[test]
+    fn test_replace_with_register() {
+        let (context, _) = run("foo bar", "yiwwviwp");
+
+        assert_eq!(context.buffer.to_string(), "foo foo");
+        assert_eq!(context.registers.get(Registers::UNNAMED), Some(&Register::new("bar", false)));
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 6));
+
+        let (context, _) = run("foo bar baz", "yywviwp");
+        assert_eq!(context.buffer.to_string(), "foo \nfoo bar baz\n baz");
+
+        let (context, _) = run("foo\nbar\nbaz", "yiwjVjp");
+
+        assert_eq!(context.buffer.to_string(), "foo\nfoo");
+        assert_eq!(
+            context.registers.get(Registers::UNNAMED),
+            Some(&Register::new("bar\nbaz\n", true))
+        );
+
+        let (context, _) = run("foo\nbar\nbaz", "yyjVp");
+        assert_eq!(context.buffer.to_string(), "foo\nfoo\nbaz");
+    }
+
+    #[test]
+    fn test_cut() {
+        let (context, mode) = run("foo bar\nbaz qux", "wvjd");
+
+        assert_eq!(mode.name(), "Normal");
+        assert_eq!(context.buffer.to_string(), "foo ux");
+        assert_eq!(
+            context.registers.get(Registers::UNNAMED),
+            Some(&Register::new("bar\nbaz q", false))
+        );
+
+        let (context, _) = run("foo bar\nbaz qux", "w<C-v>jey");
+
+        assert_eq!(context.buffer.to_string(), "foo bar\nbaz qux");
+        assert_eq!(
+            context.registers.get(Registers::UNNAMED),
+            Some(&Register::new("bar\nqux", false))
+        );
+        assert_eq!(context.buffer.cursor(), Cursor::new(0, 4));
+
+        let (context, _) = run("foo bar\nbaz qux", "w<C-v>jex");
+        assert_eq!(context.buffer.to_string(), "foo \nbaz ");
+    }
 }
@@ -0,0 +1,40 @@
+//! Editor-wide options, as set with `:set`.
+//!
+//! Options that belong to a buffer, such as `tabstop`, are kept by the buffer itself.
+
+use std::time::Duration;
+
+/// The options that apply to the whole editor.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Options {
+    /// The number of lines kept above and below the cursor when scrolling, as in Vim's
+    /// `scrolloff`.
+    pub scroll_off: usize,
+
+    /// Whether line numbers are shown relative to the line of the cursor, as in Vim's
+    /// `relativenumber`.
+    pub relative_number: bool,
+
+    /// Whether the patterns of `:s` and `:g` ignore the case of letters, as in Vim's `ignorecase`.
+    pub ignore_case: bool,
+
+    /// Whether `Y` yanks up to the end of the line, as in `y$`, rather than the whole line, as in
+    /// `yy`.
+    pub yank_to_end: bool,
+
+    /// How long a command of several keys, such as `gg`, waits for its next key before it is given
+    /// up, as in Vim's `timeoutlen`.
+    pub timeout_len: Duration,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            scroll_off: 0,
+            relative_number: false,
+            ignore_case: false,
+            yank_to_end: true,
+            timeout_len: Duration::from_secs(1),
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+
+/// A piece of text stored for later use.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Register {
+    /// The stored text.
+    pub text: String,
+
+    /// Whether the text is made of whole lines.
+    pub linewise: bool,
+}
+
+impl Register {
+    /// Creates a new register holding the specified text.
+    #[must_use]
+    pub fn new(text: impl Into<String>, linewise: bool) -> Self {
+        Self { text: text.into(), linewise }
+    }
+}
+
+/// The keys typed while recording into a register, as started with `qa`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Recording {
+    /// The register that receives the keys once the recording stops.
+    pub register: char,
+
+    /// The keys typed so far, in the notation accepted by [`crate::event::parse`].
+    pub keys: String,
+}
+
+/// Reads and writes the system clipboard, as with the `"+` and `"*` registers.
+///
+/// Hosts can provide their own to share text with other programs.
+pub trait Clipboard: Send + Sync {
+    /// Returns the text in the clipboard, if any.
+    fn get(&self) -> Option<String>;
+
+    /// Replaces the text in the clipboard.
+    fn set(&self, text: &str);
+}
+
+/// A clipboard that is always empty, for hosts without access to the system clipboard.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoClipboard;
+
+impl Clipboard for NoClipboard {
+    fn get(&self) -> Option<String> {
+        None
+    }
+
+    fn set(&self, _text: &str) {}
+}
+
+/// The named registers of an editor.
+#[derive(Debug, Default)]
+pub struct Registers(HashMap<char, Register>);
+
+impl Registers {
+    /// The register used when none is specified.
+    pub const UNNAMED: char = '"';
+
+    /// The register holding the text inserted last, as in Vim's `".`.
+    pub const INSERTED: char = '.';
+
+    /// The read-only register holding the path of the buffer, as in Vim's `"%`.
+    pub const PATH: char = '%';
+
+    /// The register backed by the system clipboard, as in Vim's `"+`.
+    pub const CLIPBOARD: char = '+';
+
+    /// The register backed by the primary selection of the system, as in Vim's `"*`, which shares
+    /// the clipboard here.
+    pub const SELECTION: char = '*';
+
+    /// Returns the contents of the specified register, if any.
+    #[must_use]
+    pub fn get(&self, name: char) -> Option<&Register> {
+        self.0.get(&name)
+    }
+
+    /// Stores a value in the specified register, returning the old value.
+    pub fn set(&mut self, name: char, register: Register) -> Option<Register> {
+        self.0.insert(name, register)
+    }
+}
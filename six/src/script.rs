@@ -0,0 +1,115 @@
+use rlua::{FromLuaMulti, UserData, UserDataMethods};
+
+use crate::buffer::Buffer;
+use crate::command;
+use crate::cursor::{Cells, Head, Metric, Tail};
+use crate::state::Context;
+use crate::Cursor;
+
+/// A handle to the buffer, exposed to scripts as the `buffer` global.
+///
+/// Positions are zero-based `row, col` pairs, as in `Cursor`.
+struct Handle<'a>(&'a mut Buffer);
+
+/// Returns the position next to `cursor` over the metric with the specified name.
+///
+/// The metrics are `cell`, `word` (the start of words) and `end` (the end of words).
+fn step(buffer: &Buffer, metric: &str, cursor: Cursor, forward: bool) -> Option<Cursor> {
+    fn go<M: Metric>(buffer: &Buffer, cursor: Cursor, forward: bool) -> Option<Cursor> {
+        if forward {
+            M::after(cursor, buffer)
+        } else {
+            M::before(cursor, buffer)
+        }
+    }
+
+    match metric {
+        "cell" => go::<Cells>(buffer, cursor, forward),
+        "word" => go::<Head>(buffer, cursor, forward),
+        "end" => go::<Tail>(buffer, cursor, forward),
+        _ => None,
+    }
+}
+
+impl UserData for Handle<'_> {
+    fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_method("text", |_, this, ()| Ok(this.0.to_string()));
+
+        methods.add_method("cursor", |_, this, ()| {
+            let cursor = this.0.cursor();
+            Ok((cursor.row(), cursor.col()))
+        });
+
+        methods.add_method_mut("set_cursor", |_, this, (row, col): (usize, usize)| {
+            let cursor = this.0.clamp(Cursor::new(row, col));
+            this.0.set_cursor(cursor);
+
+            Ok(())
+        });
+
+        methods.add_method("next", |_, this, (metric, row, col): (String, usize, usize)| {
+            let cursor = step(this.0, &metric, Cursor::new(row, col), true);
+            Ok((cursor.map(Cursor::row), cursor.map(Cursor::col)))
+        });
+
+        methods.add_method("prev", |_, this, (metric, row, col): (String, usize, usize)| {
+            let cursor = step(this.0, &metric, Cursor::new(row, col), false);
+            Ok((cursor.map(Cursor::row), cursor.map(Cursor::col)))
+        });
+
+        methods.add_method_mut("insert", |_, this, text: String| {
+            let cursor = this.0.cursor();
+            let cursor = this.0.edit(&text, cursor..cursor);
+
+            this.0.set_cursor(cursor);
+
+            Ok(())
+        });
+    }
+}
+
+/// Runs a Lua program with access to the buffer, returning its results.
+fn run<R>(context: &mut Context, program: &str) -> rlua::Result<R>
+where
+    R: for<'lua> FromLuaMulti<'lua>,
+{
+    let Context { interpreter, buffer, .. } = context;
+
+    interpreter.context(|lua| {
+        lua.scope(|scope| {
+            lua.globals().set("buffer", scope.create_nonstatic_userdata(Handle(buffer))?)?;
+            lua.load(program).eval()
+        })
+    })
+}
+
+/// Runs a Lua program with access to the buffer.
+///
+/// # Errors
+///
+/// Fails if the program can't be parsed or raises an error.
+pub fn eval(context: &mut Context, program: &str) -> command::Result {
+    run::<()>(context, program).map_err(|err| err.to_string())
+}
+
+/// Runs a Lua program that returns a position, and moves the cursor there.
+///
+/// The cursor stays in place if the program returns nothing. Scripts may walk the buffer with
+/// `buffer:next(metric, row, col)` and `buffer:prev(metric, row, col)`, which allows defining custom
+/// motions.
+///
+/// # Errors
+///
+/// Fails if the program can't be parsed, raises an error or returns something other than a
+/// position.
+pub fn jump(context: &mut Context, program: &str) -> command::Result {
+    let (row, col) =
+        run::<(Option<usize>, Option<usize>)>(context, program).map_err(|err| err.to_string())?;
+
+    if let (Some(row), Some(col)) = (row, col) {
+        let buffer = &mut context.buffer;
+        buffer.set_cursor(Cursor::new(row, col).clamp_to_line(buffer));
+    }
+
+    Ok(())
+}
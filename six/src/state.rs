@@ -1,9 +1,27 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+#[cfg(feature = "io")]
+use std::fs;
+#[cfg(feature = "io")]
+use std::io;
+#[cfg(feature = "io")]
+use std::path::Path;
+use std::sync::Arc;
+
 use rlua::Lua;
 
-use crate::buffer::Buffer;
-use crate::mode::{Mode, Normal};
+use crate::buffer::{Buffer, Edit, Row};
+use crate::command;
+use crate::cursor::Find;
+use crate::event::{self, Event, Key, Modifiers};
+use crate::fold::Folds;
+use crate::format::{CopyIndent, Indenter};
+use crate::goto::{FirstOccurrence, Resolver};
+use crate::history::History;
+use crate::mode::{self, Complete, Completion, Mode, Normal, Selection};
+use crate::options::Options;
+use crate::register::{Clipboard, NoClipboard, Recording, Register, Registers};
 use crate::Cursor;
-use crate::Event;
 
 /// An modal editor.
 #[derive(Derivative)]
@@ -15,44 +33,974 @@ pub struct Editor {
     /// The editor context.
     context: Context,
 
-    /// The scripting engine.
-    #[derivative(Debug = "ignore")]
-    interpreter: Lua,
+    /// The first line of the buffer shown in the view.
+    top: usize,
+
+    /// The keys typed since normal mode was last idle, which make up the command being typed.
+    keys: String,
+
+    /// Whether the command being typed edited the text.
+    edited: bool,
+}
+
+/// A marker shown in the gutter next to a line, such as for search results or changes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Sign(pub char);
+
+/// A change made by a command, repeated by `.`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Change {
+    /// The count of the command, if any.
+    pub count: Option<usize>,
+
+    /// The keys of the command, without its count.
+    pub keys: String,
+}
+
+impl Change {
+    /// Splits the keys of a command into its count and the keys without it, as in `3dw`.
+    ///
+    /// Counts typed after a register or an operator multiply the one typed before them, as in
+    /// Vim, so that `2d3w` has a count of 6 and the keys `dw`, and `"a3dw` a count of 3 and the
+    /// keys `"adw`.
+    #[must_use]
+    pub fn new(keys: &str) -> Self {
+        let (count, rest) = split_count(keys);
+        let (register, rest) = split_register(rest);
+        let (register_count, rest) = split_count(rest);
+        let len = operator_len(rest);
+        let (motion_count, motion) = split_count(&rest[len..]);
+
+        let count = [count, register_count, motion_count]
+            .iter()
+            .flatten()
+            .fold(None, |total: Option<usize>, &count| {
+                Some(total.unwrap_or(1).saturating_mul(count))
+            });
+
+        Self { count, keys: format!("{}{}{}", register, &rest[..len], motion) }
+    }
+}
+
+/// Splits the register given at the start of some keys, as in `"a`, if any, from the keys after it.
+fn split_register(keys: &str) -> (&str, &str) {
+    let len = match keys.strip_prefix('"').and_then(|rest| rest.chars().next()) {
+        Some(name) => 1 + name.len_utf8(),
+        None => 0,
+    };
+
+    keys.split_at(len)
 }
 
+/// Splits the count at the start of some keys, if any, from the keys after it.
+fn split_count(keys: &str) -> (Option<usize>, &str) {
+    let len = match keys.chars().next() {
+        Some('1'..='9') => keys.find(|ch: char| !ch.is_ascii_digit()).unwrap_or(keys.len()),
+        _ => 0,
+    };
+
+    (keys[..len].parse().ok(), &keys[len..])
+}
+
+/// Returns the length of the operator at the start of the keys of a command, as in `d` or `gq`,
+/// or 0 if there is none.
+fn operator_len(keys: &str) -> usize {
+    let operators = ["<lt>", "<gt>", "gc", "gq", "c", "d", "y", "="];
+
+    operators
+        .iter()
+        .find(|operator| keys.starts_with(*operator))
+        .map_or(0, |operator| operator.len())
+}
+
+/// A function that can be called by name, as in `:call name`.
+pub type Function = Arc<dyn Fn(&mut Context) -> command::Result + Send + Sync>;
+
 /// Editor context.
 #[derive(Derivative)]
 #[derivative(Debug, Default)]
 pub struct Context {
     /// The text buffer.
     pub buffer: Buffer,
+
+    /// The registers.
+    pub registers: Registers,
+
+    /// The last character search, repeated by `;` and `,`.
+    pub find: Option<Find>,
+
+    /// The last substitution, repeated by `&`.
+    pub substitution: Option<command::Substitution>,
+
+    /// The last change, repeated by `.`.
+    pub change: Option<Change>,
+
+    /// A message to be shown to the user, such as the description of an error.
+    pub message: Option<String>,
+
+    /// The folds of the buffer.
+    pub folds: Folds,
+
+    /// The signs shown in the gutter, by the line they mark.
+    pub signs: HashMap<usize, Sign>,
+
+    /// The editor-wide options.
+    pub options: Options,
+
+    /// The keys being recorded into a register, as started with `qa`, if any.
+    pub recording: Option<Recording>,
+
+    /// The last selection made in visual mode, restored by `gv`.
+    pub selection: Option<Selection>,
+
+    /// The inputs previously submitted to each prompt, by the name of the prompt.
+    pub histories: HashMap<&'static str, History>,
+
+    /// The abbreviations expanded in insert mode, by the keyword they replace.
+    pub abbreviations: HashMap<String, String>,
+
+    /// Decides the indentation of lines re-indented with `=`.
+    #[derivative(Debug = "ignore", Default(value = "Arc::new(CopyIndent)"))]
+    pub indenter: Arc<dyn Indenter>,
+
+    /// Finds the definitions jumped to by `gd`.
+    #[derivative(Debug = "ignore", Default(value = "Arc::new(FirstOccurrence)"))]
+    pub resolver: Arc<dyn Resolver>,
+
+    /// Holds the text of the `"+` and `"*` registers.
+    #[derivative(Debug = "ignore", Default(value = "Arc::new(NoClipboard)"))]
+    pub clipboard: Arc<dyn Clipboard>,
+
+    /// Completes the keyword before the cursor in insert mode, as in `Ctrl-n`.
+    #[derivative(Debug = "ignore", Default(value = "mode::keywords"))]
+    pub complete: Complete,
+
+    /// The functions that can be called by name.
+    #[derivative(Debug = "ignore")]
+    pub functions: HashMap<String, Function>,
+
+    /// The scripting engine.
+    #[derivative(Debug = "ignore")]
+    pub interpreter: Lua,
+}
+
+impl Context {
+    /// Calls the function registered with the specified name.
+    ///
+    /// # Errors
+    ///
+    /// Fails if there is no such function, or if the function fails itself.
+    pub fn call(&mut self, name: &str) -> command::Result {
+        let function = self
+            .functions
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("Unknown function: {}", name))?;
+
+        function(self)
+    }
+
+    /// Stores text copied into a register, as with `"ay`, which also goes into the unnamed
+    /// register.
+    ///
+    /// Text copied into the clipboard registers goes to the clipboard. Other than the unnamed
+    /// register, only those named by lowercase letters can be written.
+    pub fn store(&mut self, name: char, register: Register) {
+        match name {
+            Registers::CLIPBOARD | Registers::SELECTION => self.clipboard.set(&register.text),
+            'a'..='z' => {
+                self.registers.set(name, register.clone());
+            },
+
+            _ => {},
+        }
+
+        self.registers.set(Registers::UNNAMED, register);
+    }
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Editor {
+    #[must_use]
     pub fn new() -> Self {
-        Self { context: Context::default(), interpreter: Lua::default(), mode: Normal::new() }
+        let mut context = Context::default();
+        context.buffer.set_record_edits(true);
+
+        Self { context, mode: Normal::new(), top: 0, keys: String::new(), edited: false }
+    }
+
+    /// Creates an editor holding the specified text, with the cursor at the origin.
+    #[must_use]
+    pub fn with_text(text: &str) -> Self {
+        let mut editor = Self::new();
+        editor.context.buffer = Buffer::from(text);
+        editor.context.buffer.set_record_edits(true);
+
+        editor
+    }
+
+    /// Creates an editor for the specified file.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the file can't be read.
+    #[cfg(feature = "io")]
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+
+        let mut editor = Self::with_text(&fs::read_to_string(path)?);
+
+        let buffer = &mut editor.context.buffer;
+        buffer.set_file_hash(buffer.content_hash());
+        buffer.set_path(path);
+
+        Ok(editor)
     }
 
     /// Returns a reference to the text buffer.
+    #[must_use]
     pub fn buffer(&self) -> &Buffer {
         &self.context.buffer
     }
 
+    /// Returns an iterator over the lines of the text buffer.
+    ///
+    /// Lines implement `Display`, so they can be rendered without collecting them into strings.
+    #[must_use]
+    pub fn lines(&self) -> impl ExactSizeIterator<Item = &Row> {
+        self.context.buffer.content().iter()
+    }
+
+    /// Returns the number of lines of the text buffer, which is never zero.
+    #[must_use]
+    pub fn line_count(&self) -> usize {
+        self.context.buffer.content().len()
+    }
+
+    /// Returns a line of the text buffer, if it exists.
+    ///
+    /// Like the lines returned by `lines`, it implements `Display`.
+    #[must_use]
+    pub fn line(&self, row: usize) -> Option<&Row> {
+        self.context.buffer.line(row)
+    }
+
+    /// Sets the function that decides which characters are part of keywords, as in Vim's
+    /// `iskeyword`.
+    pub fn set_keyword(&mut self, keyword: fn(char) -> bool) {
+        self.context.buffer.set_keyword(keyword);
+    }
+
+    /// Sets what decides the indentation of lines re-indented with `=`.
+    pub fn set_indenter(&mut self, indenter: impl Indenter + 'static) {
+        self.context.indenter = Arc::new(indenter);
+    }
+
+    /// Sets what finds the definitions jumped to by `gd`.
+    pub fn set_resolver(&mut self, resolver: impl Resolver + 'static) {
+        self.context.resolver = Arc::new(resolver);
+    }
+
+    /// Sets what holds the text of the `"+` and `"*` registers, such as the system clipboard.
+    pub fn set_clipboard(&mut self, clipboard: impl Clipboard + 'static) {
+        self.context.clipboard = Arc::new(clipboard);
+    }
+
+    /// Sets what completes the keyword before the cursor in insert mode, as in `Ctrl-n`.
+    pub fn set_completion(&mut self, complete: Complete) {
+        self.context.complete = complete;
+    }
+
+    /// Registers a function to be called by name, replacing any previous one with the same name.
+    pub fn define(
+        &mut self,
+        name: impl Into<String>,
+        function: impl Fn(&mut Context) -> command::Result + Send + Sync + 'static,
+    ) {
+        self.context.functions.insert(name.into(), Arc::new(function));
+    }
+
     /// Returns the name of the active mode.
+    #[must_use]
     pub fn mode(&self) -> &str {
         self.mode.name()
     }
 
+    /// Returns the text typed in the active mode, if it queries the user for any.
+    #[must_use]
+    pub fn input(&self) -> Option<String> {
+        self.mode.input()
+    }
+
+    /// Returns the completions being cycled through in the active mode, if any, as shown in a
+    /// popup.
+    #[must_use]
+    pub fn completion(&self) -> Option<&Completion> {
+        self.mode.completion()
+    }
+
+    /// Returns the register that typed keys are being recorded into, as started with `qa`, if any.
+    #[must_use]
+    pub fn recording(&self) -> Option<char> {
+        self.context.recording.as_ref().map(|recording| recording.register)
+    }
+
+    /// Returns the message to be shown to the user, if any.
+    #[must_use]
+    pub fn message(&self) -> Option<&str> {
+        self.context.message.as_deref()
+    }
+
     /// Returns the cursor position.
+    #[must_use]
     pub fn cursor(&self) -> Cursor {
         self.context.buffer.cursor()
     }
 
+    /// Returns the first line of the buffer shown in the view.
+    #[must_use]
+    pub fn top(&self) -> usize {
+        self.top
+    }
+
+    /// Scrolls the view by a number of lines, down if positive and up if negative, as with a mouse
+    /// wheel.
+    ///
+    /// The last line of the buffer always stays in view. The cursor is only moved if it would leave
+    /// the `height` lines of the view, onto the closest line still in it, which keeps `scrolloff`
+    /// lines from the edges of the view unless they are past the ends of the buffer.
+    pub fn scroll(&mut self, lines: isize, height: usize) {
+        let height = height.max(1);
+        let margin = self.scroll_margin(height);
+
+        let buffer = &mut self.context.buffer;
+        let last = buffer.content().len() - 1;
+
+        let distance = usize::try_from(lines.saturating_abs()).unwrap_or_default();
+
+        self.top = if lines < 0 {
+            self.top.saturating_sub(distance)
+        } else {
+            self.top.saturating_add(distance).min(last)
+        };
+
+        let bottom = self.top + height - 1;
+
+        let first = if self.top == 0 { 0 } else { self.top + margin };
+        let last = if bottom >= last { bottom } else { bottom - margin };
+
+        let cursor = buffer.cursor();
+        let row = cursor.row().max(first).min(last);
+
+        if row != cursor.row() {
+            buffer.set_cursor(Cursor::new(row, cursor.col()).clamp_to_line(buffer));
+        }
+
+        self.check_invariants();
+    }
+
+    /// Scrolls the view as little as needed to show the cursor within its `height` lines, along
+    /// with `scrolloff` lines around it unless they are past the ends of the buffer.
+    pub fn follow_cursor(&mut self, height: usize) {
+        let height = height.max(1);
+        let margin = self.scroll_margin(height);
+
+        let row = self.cursor().row();
+        let last = self.context.buffer.content().len() - 1;
+
+        if row < self.top + margin {
+            self.top = row.saturating_sub(margin);
+        } else if row + margin >= self.top + height {
+            let bottom = (row + margin).min(last);
+            self.top = self.top.max((bottom + 1).saturating_sub(height));
+        }
+    }
+
+    /// Returns the number of lines kept around the cursor in a view of `height` lines, as set by
+    /// `scrolloff`, which leaves at least the line of the cursor.
+    fn scroll_margin(&self, height: usize) -> usize {
+        self.context.options.scroll_off.min((height - 1) / 2)
+    }
+
+    /// Returns the editor-wide options.
+    #[must_use]
+    pub fn options(&self) -> &Options {
+        &self.context.options
+    }
+
+    /// Returns the folds of the buffer.
+    #[must_use]
+    pub fn folds(&self) -> &Folds {
+        &self.context.folds
+    }
+
+    /// Returns the signs shown in the gutter, by the line they mark.
+    #[must_use]
+    pub fn signs(&self) -> &HashMap<usize, Sign> {
+        &self.context.signs
+    }
+
+    /// Places a sign next to a line, replacing any previous one.
+    pub fn set_sign(&mut self, row: usize, sign: Sign) {
+        self.context.signs.insert(row, sign);
+    }
+
+    /// Removes the sign next to a line, returning it if there was one.
+    pub fn remove_sign(&mut self, row: usize) -> Option<Sign> {
+        self.context.signs.remove(&row)
+    }
+
+    /// Moves the cursor to the character displayed at a position of the text area, as when
+    /// clicking on it.
+    ///
+    /// The position is given in screen rows and columns, counted from the first line of the
+    /// buffer.
+    pub fn click(&mut self, row: usize, col: usize) {
+        let buffer = &mut self.context.buffer;
+        buffer.set_cursor(buffer.cursor_at(row, col));
+
+        self.check_invariants();
+    }
+
+    /// Starts grouping changes, so that the ones made until [`Self::end_undo_group`] is called are
+    /// undone at once, as for a batch of edits made by a formatter.
+    ///
+    /// Groups started within another one are joined with it.
+    pub fn begin_undo_group(&mut self) {
+        self.context.buffer.begin_undo_group();
+    }
+
+    /// Stops grouping changes, as started by [`Self::begin_undo_group`].
+    pub fn end_undo_group(&mut self) {
+        self.context.buffer.end_undo_group();
+    }
+
+    /// Advances the state by handling key presses written in a compact notation, such as
+    /// `ifoo<Esc>dd`.
+    ///
+    /// See `event::parse` for the syntax.
+    pub fn feed(&mut self, keys: &str) {
+        self.advance(&event::parse(keys));
+    }
+
     /// Advances the state by handling events.
+    ///
+    /// Each key press clears the message left before it, while idle events keep it on screen.
+    /// Unsupported events are ignored, while `Ctrl-c` is handled just like `Esc`.
     pub fn advance(&mut self, events: &[Event]) {
-        self.mode =
-            events.iter().fold(std::mem::replace(&mut self.mode, Normal::new()), |mode, &event| {
-                mode.advance(&mut self.context, event)
-            });
+        self.context.buffer.take_refused();
+
+        let mode = std::mem::replace(&mut self.mode, Normal::new());
+        let events = events.iter().filter(|event| event.is_supported()).map(|&event| match event {
+            Event::Key(Key::Char('c'), Modifiers::CTRL) => Event::Key(Key::Esc, Modifiers::NONE),
+            event => event,
+        });
+
+        self.mode = events.fold(mode, |mode, event| {
+            if let Event::Key(..) = event {
+                self.context.message = None;
+                self.keys.push_str(&event.to_string());
+
+                if let Some(recording) = &mut self.context.recording {
+                    recording.keys.push_str(&event.to_string());
+                }
+            }
+
+            let mode = mode.advance(&mut self.context, event);
+
+            if self.context.buffer.take_refused() {
+                self.context.message = Some("Cannot make changes, 'modifiable' is off".to_owned());
+            }
+
+            for edit in self.context.buffer.take_edits() {
+                self.adjust(&edit);
+                self.edited = true;
+            }
+
+            if mode.is_idle() {
+                self.finish_command();
+            }
+
+            mode
+        });
+
+        self.check_invariants();
     }
-}
\ No newline at end of file
+
+    /// Remembers the command just typed for `.` if it changed the text, and saves an undo state.
+    ///
+    /// Moving through the undo history, repeating with `.` and running command lines are not
+    /// remembered.
+    fn finish_command(&mut self) {
+        let change = Change::new(&self.keys);
+
+        if self.edited
+            && self.context.buffer.undo_pending()
+            && change.keys != "."
+            && !change.keys.starts_with(':')
+        {
+            self.context.change = Some(change);
+        }
+
+        self.keys.clear();
+        self.edited = false;
+
+        self.context.buffer.save_undo_state();
+    }
+
+    /// Moves the positions stored outside of the buffer along with the text after a change to it.
+    ///
+    /// Signs that end up on the same line give way to the ones that were not within the replaced
+    /// text, and then to the ones from the lines above.
+    fn adjust(&mut self, edit: &Edit) {
+        self.context.folds.adjust(edit);
+
+        if let Some(selection) = &mut self.context.selection {
+            selection.adjust(edit);
+        }
+
+        let replaced = |row: usize| (edit.start..edit.end).contains(&Cursor::new(row, 0));
+
+        let mut signs = self.context.signs.drain().collect::<Vec<_>>();
+        signs.sort_by_key(|&(row, _)| (replaced(row), row));
+
+        for (row, sign) in signs {
+            let row = edit.adjust_cursor(Cursor::new(row, 0)).row();
+            self.context.signs.entry(row).or_insert(sign);
+        }
+    }
+
+    /// Asserts that the editor state is consistent, which catches motions and edits that leave it
+    /// broken.
+    ///
+    /// The checks are only made in debug builds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer has no rows, if a row holds a line break, if the cursor is out of
+    /// bounds, or if the cursor or the end of a row doesn't survive a round trip through its byte
+    /// offset.
+    pub fn check_invariants(&self) {
+        #[cfg(debug_assertions)]
+        {
+            let buffer = &self.context.buffer;
+            let content = buffer.content();
+
+            assert!(!content.is_empty(), "the buffer has no rows");
+
+            for (idx, row) in content.iter().enumerate() {
+                assert!(
+                    (0..row.len()).all(|col| row.get(col) != Some('\n')),
+                    "row {} holds a line break",
+                    idx
+                );
+            }
+
+            let cursor = buffer.cursor();
+            assert!(cursor.row() < content.len(), "the cursor is past the last row: {:?}", cursor);
+            assert!(
+                cursor.col() <= content[cursor.row()].len(),
+                "the cursor is past the end of its row: {:?}",
+                cursor
+            );
+
+            let ends = content.iter().enumerate().map(|(row, line)| Cursor::new(row, line.len()));
+
+            for cursor in std::iter::once(cursor).chain(ends) {
+                assert_eq!(
+                    buffer.cursor_to_byte(cursor).and_then(|offset| buffer.byte_to_cursor(offset)),
+                    Some(cursor),
+                    "the byte offset of {:?} doesn't lead back to it",
+                    cursor
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_text() {
+        let editor = Editor::with_text("foo\nbar baz\n");
+
+        let rows = editor.buffer().content().iter().map(ToString::to_string).collect::<Vec<_>>();
+        assert_eq!(rows, vec!["foo", "bar baz", ""]);
+        assert_eq!(editor.cursor(), Cursor::origin());
+        assert_eq!(editor.mode(), "Normal");
+    }
+
+    #[test]
+    fn test_send() {
+        fn is_send<T: Send>() {}
+        is_send::<Editor>();
+    }
+
+    #[test]
+    fn test_unsupported_keys() {
+        let mut editor = Editor::with_text("foo");
+
+        editor.feed("i");
+        editor.advance(&[Event::Key(Key::Char('\0'), Modifiers::NONE)]);
+        editor.feed("<Esc>");
+
+        assert_eq!(editor.buffer().to_string(), "foo");
+        assert_eq!(editor.cursor(), Cursor::origin());
+    }
+
+    /// A clipboard whose text is shared by its clones.
+    #[derive(Clone, Default)]
+    struct SharedClipboard(Arc<std::sync::Mutex<Option<String>>>);
+
+    impl Clipboard for SharedClipboard {
+        fn get(&self) -> Option<String> {
+            self.0.lock().ok()?.clone()
+        }
+
+        fn set(&self, text: &str) {
+            if let Ok(mut clipboard) = self.0.lock() {
+                *clipboard = Some(text.to_owned());
+            }
+        }
+    }
+
+    #[test]
+    fn test_clipboard() {
+        let clipboard = SharedClipboard::default();
+
+        let mut editor = Editor::with_text("foo bar\nbaz");
+        editor.set_clipboard(clipboard.clone());
+
+        editor.feed("\"+yy");
+        assert_eq!(clipboard.get().as_deref(), Some("foo bar\n"));
+
+        editor.feed("wve\"*y");
+        assert_eq!(clipboard.get().as_deref(), Some("bar"));
+
+        clipboard.set("qux\n");
+        editor.feed("j\"+p");
+        assert_eq!(editor.buffer().to_string(), "foo bar\nbaz\nqux");
+
+        clipboard.set("x");
+        editor.feed("\"+P");
+        assert_eq!(editor.buffer().to_string(), "foo bar\nbaz\nxqux");
+    }
+
+    #[test]
+    fn test_clipboard_missing() {
+        let mut editor = Editor::with_text("foo bar");
+
+        editor.feed("\"+dwP\"+p");
+        assert_eq!(editor.buffer().to_string(), "foo bar");
+    }
+
+    #[test]
+    fn test_record() {
+        let mut editor = Editor::with_text("foo bar");
+
+        editor.feed("qa");
+        assert_eq!(editor.recording(), Some('a'));
+
+        editor.feed("wi<lt><C-a><Esc>q");
+        assert_eq!(editor.recording(), None);
+
+        editor.feed("\"ap");
+        assert_eq!(editor.buffer().to_string(), "foo <wi<lt><C-a><Esc>bar");
+
+        editor.feed("qAq");
+        assert_eq!(editor.recording(), None);
+    }
+
+    #[test]
+    fn test_repeat() {
+        let mut editor = Editor::with_text("foo\nbar\nbaz");
+
+        editor.feed(".");
+        assert_eq!(editor.buffer().to_string(), "foo\nbar\nbaz");
+
+        editor.feed("A;<Esc>j.");
+        assert_eq!(editor.buffer().to_string(), "foo;\nbar;\nbaz");
+
+        editor.feed("u");
+        assert_eq!(editor.buffer().to_string(), "foo;\nbar\nbaz");
+
+        editor.feed("jj.");
+        assert_eq!(editor.buffer().to_string(), "foo;\nbar\nbaz;");
+    }
+
+    #[test]
+    fn test_repeat_count() {
+        let mut editor = Editor::with_text("a b c d e f g h i");
+
+        editor.feed("dw");
+        assert_eq!(editor.buffer().to_string(), "b c d e f g h i");
+
+        editor.feed("3.");
+        assert_eq!(editor.buffer().to_string(), "e f g h i");
+
+        editor.feed(".");
+        assert_eq!(editor.buffer().to_string(), "h i");
+
+        editor.feed("u2dwu.");
+        assert_eq!(editor.buffer().to_string(), "g h i");
+
+        let mut editor = Editor::with_text("a b c d e f g h i");
+
+        editor.feed("d3w2.");
+        assert_eq!(editor.buffer().to_string(), "f g h i");
+
+        editor.feed("uu2d2wu.");
+        assert_eq!(editor.buffer().to_string(), "e f g h i");
+
+        let mut editor = Editor::with_text("a b c d e f g h i");
+
+        editor.feed("\"a3dw2.");
+        assert_eq!(editor.buffer().to_string(), "f g h i");
+    }
+
+    #[test]
+    fn test_change_count() {
+        assert_eq!(Change::new("2d3w"), Change { count: Some(6), keys: "dw".to_owned() });
+        assert_eq!(Change::new("\"ad2j"), Change { count: Some(2), keys: "\"adj".to_owned() });
+        assert_eq!(Change::new("\"a3dw"), Change { count: Some(3), keys: "\"adw".to_owned() });
+        assert_eq!(Change::new("2\"a3d2w"), Change { count: Some(12), keys: "\"adw".to_owned() });
+        assert_eq!(Change::new("3<gt>j"), Change { count: Some(3), keys: "<gt>j".to_owned() });
+        assert_eq!(Change::new("gq2j"), Change { count: Some(2), keys: "gqj".to_owned() });
+        assert_eq!(Change::new("d0"), Change { count: None, keys: "d0".to_owned() });
+        assert_eq!(Change::new("r3"), Change { count: None, keys: "r3".to_owned() });
+    }
+
+    #[test]
+    fn test_interrupt() {
+        let mut editor = Editor::with_text("bar baz");
+
+        editor.feed("ifoo<C-c>");
+        assert_eq!(editor.mode(), "Normal");
+        assert_eq!(editor.cursor(), Cursor::new(0, 2));
+
+        editor.feed("d<C-c>w");
+        assert_eq!(editor.buffer().to_string(), "foobar baz");
+        assert_eq!(editor.cursor(), Cursor::new(0, 7));
+
+        editor.feed("v<C-c>:se<C-c>");
+        assert_eq!(editor.mode(), "Normal");
+    }
+
+    #[test]
+    fn test_idle_keeps_message() {
+        let mut editor = Editor::with_text("foo");
+
+        editor.feed(":nothing<CR>");
+        assert_eq!(editor.message(), Some("Not an editor command: nothing"));
+
+        editor.advance(&[Event::Idle(std::time::Duration::from_secs(1))]);
+        assert_eq!(editor.message(), Some("Not an editor command: nothing"));
+
+        editor.feed("0");
+        assert_eq!(editor.message(), None);
+    }
+
+    #[test]
+    fn test_lines() {
+        let editor = Editor::with_text("foo\n\nbär");
+
+        assert_eq!(editor.lines().len(), 3);
+
+        let lines = editor.lines().map(ToString::to_string).collect::<Vec<_>>();
+        assert_eq!(lines, vec!["foo", "", "bär"]);
+
+        let lengths = editor.lines().map(|line| line.chars().count()).collect::<Vec<_>>();
+        assert_eq!(lengths, vec![3, 0, 3]);
+    }
+
+    #[test]
+    fn test_line() {
+        let editor = Editor::with_text("foo\n\nbär\n");
+
+        assert_eq!(editor.line_count(), 4);
+        assert_eq!(Editor::new().line_count(), 1);
+
+        assert_eq!(editor.line(0).map(ToString::to_string).as_deref(), Some("foo"));
+        assert_eq!(editor.line(2).map(ToString::to_string).as_deref(), Some("bär"));
+        assert_eq!(editor.line(3).map(ToString::to_string).as_deref(), Some(""));
+        assert!(editor.line(4).is_none());
+        assert!(editor.line(usize::MAX).is_none());
+    }
+
+    #[test]
+    fn test_click() {
+        let mut editor = Editor::with_text("foo\n\tbar");
+
+        editor.click(1, 9);
+        assert_eq!(editor.cursor(), Cursor::new(1, 2));
+
+        editor.click(0, 80);
+        assert_eq!(editor.cursor(), Cursor::new(0, 2));
+
+        editor.click(24, 0);
+        assert_eq!(editor.cursor(), Cursor::new(1, 0));
+    }
+
+    #[test]
+    fn test_scroll() {
+        let mut editor = Editor::with_text(&"foo\n".repeat(9));
+
+        editor.scroll(3, 4);
+        assert_eq!(editor.top(), 3);
+        assert_eq!(editor.cursor(), Cursor::new(3, 0));
+
+        editor.click(5, 2);
+        editor.scroll(-3, 4);
+        assert_eq!(editor.top(), 0);
+        assert_eq!(editor.cursor(), Cursor::new(3, 2));
+
+        editor.scroll(-3, 4);
+        assert_eq!(editor.top(), 0);
+
+        editor.scroll(30, 4);
+        assert_eq!(editor.top(), 9);
+        assert_eq!(editor.cursor(), Cursor::new(9, 0));
+    }
+
+    #[test]
+    fn test_follow_cursor() {
+        let mut editor = Editor::with_text(&"foo\n".repeat(9));
+
+        editor.click(6, 0);
+        editor.follow_cursor(4);
+        assert_eq!(editor.top(), 3);
+
+        editor.click(1, 0);
+        editor.follow_cursor(4);
+        assert_eq!(editor.top(), 1);
+
+        editor.follow_cursor(4);
+        assert_eq!(editor.top(), 1);
+    }
+
+    #[test]
+    fn test_not_modifiable() {
+        let mut editor = Editor::with_text("bar\nbaz");
+        editor.feed(":set noma<CR>");
+
+        for keys in &["i", "u", "J", "gqq", "Vj>", ":s/a/0/<CR>", ":d<CR>", "g-", "yiwwviwp"] {
+            editor.feed("<Esc>");
+            editor.feed(keys);
+
+            assert_eq!(editor.buffer().to_string(), "bar\nbaz");
+            assert_eq!(editor.message(), Some("Cannot make changes, 'modifiable' is off"));
+        }
+
+        editor.feed("<Esc>j");
+        assert_eq!(editor.message(), None);
+    }
+
+    #[test]
+    fn test_scroll_off() {
+        let mut editor = Editor::with_text(&"foo\n".repeat(19));
+        editor.feed(":set so=2<CR>");
+
+        editor.click(6, 0);
+        editor.follow_cursor(6);
+        assert_eq!(editor.top(), 3);
+
+        editor.click(4, 0);
+        editor.follow_cursor(6);
+        assert_eq!(editor.top(), 2);
+
+        editor.feed("G");
+        editor.follow_cursor(6);
+        assert_eq!(editor.top(), 14);
+
+        editor.feed("gg");
+        editor.follow_cursor(6);
+        assert_eq!(editor.top(), 0);
+
+        editor.scroll(5, 6);
+        assert_eq!(editor.cursor(), Cursor::new(7, 0));
+
+        editor.scroll(4, 6);
+        editor.feed("G");
+        editor.scroll(-1, 6);
+        assert_eq!(editor.cursor(), Cursor::new(11, 0));
+
+        editor.scroll(30, 6);
+        editor.feed("G");
+        editor.scroll(0, 6);
+        assert_eq!(editor.cursor(), Cursor::new(19, 0));
+    }
+
+    #[test]
+    fn test_adjust_positions() {
+        let mut editor = Editor::with_text("foo\nbar\nbaz\nqux");
+
+        editor.feed("jzfj");
+        editor.set_sign(3, Sign('>'));
+
+        editor.feed("ggInew<CR><Esc>");
+        assert_eq!(editor.folds().closed(2), Some((2, 3)));
+        assert_eq!(editor.signs().get(&4), Some(&Sign('>')));
+
+        editor.feed("ggdd");
+        assert_eq!(editor.folds().closed(1), Some((1, 2)));
+        assert_eq!(editor.signs().get(&3), Some(&Sign('>')));
+    }
+
+    #[test]
+    fn test_adjust_signs() {
+        let mut editor = Editor::with_text("foo\nbar\nbaz\nqux");
+
+        (0..4).zip("abcd".chars()).for_each(|(row, ch)| editor.set_sign(row, Sign(ch)));
+
+        editor.feed("dd");
+        assert_eq!(editor.signs().get(&0), Some(&Sign('b')));
+
+        editor.feed("J");
+        assert_eq!(editor.signs().get(&0), Some(&Sign('b')));
+        assert_eq!(editor.signs().get(&1), Some(&Sign('d')));
+    }
+
+    #[test]
+    fn test_check_invariants() {
+        let mut editor = Editor::with_text("foo\nbar");
+        editor.context.buffer.set_cursor(Cursor::new(1, 3));
+
+        editor.check_invariants();
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "the cursor is past the end of its row")]
+    fn test_check_invariants_cursor_out_of_bounds() {
+        let mut editor = Editor::with_text("foo\nbar");
+        editor.context.buffer.set_cursor(Cursor::new(0, 4));
+
+        editor.check_invariants();
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "the cursor is past the last row")]
+    fn test_check_invariants_cursor_past_last_row() {
+        let mut editor = Editor::with_text("foo");
+        editor.context.buffer.set_cursor(Cursor::new(1, 0));
+
+        editor.check_invariants();
+    }
+
+    #[test]
+    fn test_with_text_edit() {
+        let mut editor = Editor::with_text("foo\nbar");
+
+        editor.feed("ix<Esc>");
+        assert_eq!(editor.buffer().to_string(), "xfoo\nbar");
+        assert_eq!(editor.cursor(), Cursor::new(0, 0));
+    }
+}
@@ -0,0 +1,351 @@
+//! The undo history of a buffer, kept as a tree of states so that changes undone are not lost by
+//! making new ones, as with Vim's undo tree.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::Cursor;
+
+/// The number of undo states kept by default, as with Vim's `undolevels`.
+pub const LEVELS: usize = 1000;
+
+/// A change made to the text, as the text removed from a position and the text put there instead.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Change {
+    /// The start of the changed text.
+    pub start: Cursor,
+
+    /// The text that was removed.
+    pub removed: String,
+
+    /// The text that was inserted in its place.
+    pub inserted: String,
+}
+
+impl Change {
+    /// Returns the change that reverts this one.
+    #[must_use]
+    pub fn invert(&self) -> Self {
+        Self { start: self.start, removed: self.inserted.clone(), inserted: self.removed.clone() }
+    }
+
+    /// Returns the position right after the removed text.
+    #[must_use]
+    pub fn end(&self) -> Cursor {
+        match self.removed.rfind('\n') {
+            Some(idx) => Cursor::new(
+                self.start.row() + self.removed.matches('\n').count(),
+                self.removed[idx + 1..].chars().count(),
+            ),
+
+            None => Cursor::new(self.start.row(), self.start.col() + self.removed.chars().count()),
+        }
+    }
+}
+
+/// A state of the text in the undo history.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct State {
+    /// The changes made to the text of the parent to reach this state, in order.
+    changes: Vec<Change>,
+
+    /// The state the text was changed from, or `None` for the oldest state kept.
+    parent: Option<usize>,
+
+    /// The state redo moves to, which is the child undone last, or otherwise the newest one.
+    next: Option<usize>,
+}
+
+/// The undo history of a buffer.
+///
+/// States are numbered in the order they were saved, starting from the original text at 0, and
+/// only hold the changes from their parent. Undo and redo move along a branch of the tree, while
+/// going earlier or later in time moves through the numbers, across branches.
+///
+/// Once there are too many states, the oldest ones are forgotten, along with the branches that
+/// started from them.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Undo {
+    /// The states kept, by their numbers.
+    states: BTreeMap<usize, State>,
+
+    /// The number of the state the text is in.
+    current: usize,
+
+    /// The number of states kept, other than the oldest one.
+    levels: usize,
+}
+
+impl Default for Undo {
+    fn default() -> Self {
+        Self::new(LEVELS)
+    }
+}
+
+impl Undo {
+    /// Creates a history that keeps up to `levels` states other than the oldest one.
+    #[must_use]
+    pub fn new(levels: usize) -> Self {
+        let root = State { changes: Vec::new(), parent: None, next: None };
+        Self { states: std::iter::once((0, root)).collect(), current: 0, levels: levels.max(1) }
+    }
+
+    /// Returns the number of the state the text is in.
+    #[must_use]
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    /// Saves a new state reached from the current one by some changes, which becomes current.
+    ///
+    /// The states undone from the current one are kept in their own branches.
+    pub fn save(&mut self, changes: Vec<Change>) {
+        let idx = self.states.keys().next_back().map_or(0, |idx| idx + 1);
+
+        self.states.insert(idx, State { changes, parent: Some(self.current), next: None });
+        self.state(self.current).next = Some(idx);
+        self.current = idx;
+
+        while self.states.len() > self.levels + 1 {
+            self.forget_oldest();
+        }
+    }
+
+    /// Moves to the state the current one was changed from, as in `u`, returning the changes that
+    /// revert the text to it, or `None` at the oldest state.
+    pub fn undo(&mut self) -> Option<Vec<Change>> {
+        let current = self.current;
+        let parent = self.states[&current].parent?;
+
+        self.state(parent).next = Some(current);
+        self.current = parent;
+
+        Some(self.states[&current].changes.iter().rev().map(Change::invert).collect())
+    }
+
+    /// Moves to the state undone last from the current one, or otherwise to its newest child, as in
+    /// `Ctrl-r`, returning the changes that bring the text to it, or `None` if there are no
+    /// children.
+    pub fn redo(&mut self) -> Option<Vec<Change>> {
+        self.current = self.states[&self.current].next?;
+        Some(self.states[&self.current].changes.clone())
+    }
+
+    /// Moves to the state saved right before the current one, as in `g-`, returning the changes
+    /// that bring the text to it, or `None` at the oldest state.
+    pub fn earlier(&mut self) -> Option<Vec<Change>> {
+        let target = *self.states.range(..self.current).next_back()?.0;
+        Some(self.jump(target))
+    }
+
+    /// Moves to the state saved right after the current one, as in `g+`, returning the changes that
+    /// bring the text to it, or `None` at the newest state.
+    pub fn later(&mut self) -> Option<Vec<Change>> {
+        let target = *self.states.range(self.current + 1..).next()?.0;
+        Some(self.jump(target))
+    }
+
+    /// Moves to another state, returning the changes that bring the text to it, which go up the
+    /// tree to the closest state both are reached from and then down to the target.
+    fn jump(&mut self, target: usize) -> Vec<Change> {
+        let ancestors = |mut idx: usize| {
+            let mut path = vec![idx];
+
+            while let Some(parent) = self.states[&idx].parent {
+                path.push(parent);
+                idx = parent;
+            }
+
+            path
+        };
+
+        let up = ancestors(self.current);
+        let down = ancestors(target);
+
+        let common = up.iter().position(|idx| down.contains(idx)).unwrap_or(up.len() - 1);
+        let below = down.iter().position(|&idx| idx == up[common]).unwrap_or(down.len());
+
+        let reverted = up[..common].iter().flat_map(|idx| {
+            self.states[idx].changes.iter().rev().map(Change::invert).collect::<Vec<_>>()
+        });
+
+        let applied = down[..below].iter().rev().flat_map(|idx| self.states[idx].changes.clone());
+        let changes = reverted.chain(applied).collect();
+
+        self.current = target;
+        changes
+    }
+
+    /// Forgets the oldest state, making its child towards the current state the oldest one, and
+    /// forgetting the branches of its other children.
+    fn forget_oldest(&mut self) {
+        let root = match self.states.keys().next() {
+            Some(&root) if root != self.current => root,
+            _ => return,
+        };
+
+        let mut child = self.current;
+
+        while let Some(parent) = self.states[&child].parent.filter(|&parent| parent != root) {
+            child = parent;
+        }
+
+        self.states.remove(&root);
+
+        let mut forgotten = self
+            .states
+            .iter()
+            .filter(|(&idx, state)| idx != child && state.parent == Some(root))
+            .map(|(&idx, _)| idx)
+            .collect::<BTreeSet<_>>();
+
+        // Children are always newer than their parents, so one pass in order finds them all.
+        for (&idx, state) in &self.states {
+            if state.parent.map_or(false, |parent| forgotten.contains(&parent)) {
+                forgotten.insert(idx);
+            }
+        }
+
+        self.states.retain(|idx, _| !forgotten.contains(idx));
+
+        let child = self.state(child);
+        child.changes.clear();
+        child.parent = None;
+    }
+
+    /// Returns a state that is known to be kept.
+    fn state(&mut self, idx: usize) -> &mut State {
+        self.states.get_mut(&idx).expect("state is kept")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Undo;
+    use crate::{Buffer, Cursor, Editor};
+
+    /// Moves through the undo history of a buffer, returning its text if it moved.
+    fn step(buffer: &mut Buffer, step: fn(&mut Buffer) -> bool) -> Option<String> {
+        if step(buffer) {
+            Some(buffer.to_string())
+        } else {
+            None
+        }
+    }
+
+    #[test]
+    fn test_branches() {
+        let mut buffer = Buffer::from("a");
+
+        buffer.edit("b", Cursor::new(0, 1)..);
+        buffer.save_undo_state();
+        assert_eq!(step(&mut buffer, Buffer::undo).as_deref(), Some("a"));
+
+        buffer.edit("c", Cursor::new(0, 1)..);
+        buffer.save_undo_state();
+        assert_eq!(step(&mut buffer, Buffer::undo).as_deref(), Some("a"));
+        assert_eq!(step(&mut buffer, Buffer::undo), None);
+        assert_eq!(step(&mut buffer, Buffer::redo).as_deref(), Some("ac"));
+
+        assert_eq!(step(&mut buffer, Buffer::earlier).as_deref(), Some("ab"));
+        assert_eq!(step(&mut buffer, Buffer::earlier).as_deref(), Some("a"));
+        assert_eq!(step(&mut buffer, Buffer::earlier), None);
+        assert_eq!(step(&mut buffer, Buffer::redo).as_deref(), Some("ac"));
+
+        assert_eq!(step(&mut buffer, Buffer::earlier).as_deref(), Some("ab"));
+        assert_eq!(step(&mut buffer, Buffer::undo).as_deref(), Some("a"));
+        assert_eq!(step(&mut buffer, Buffer::redo).as_deref(), Some("ab"));
+        assert_eq!(step(&mut buffer, Buffer::later).as_deref(), Some("ac"));
+        assert_eq!(step(&mut buffer, Buffer::later), None);
+    }
+
+    #[test]
+    fn test_levels() {
+        let mut undo = Undo::new(2);
+
+        undo.save(Vec::new());
+        assert_eq!(undo.undo(), Some(Vec::new()));
+
+        (0..3).for_each(|_| undo.save(Vec::new()));
+
+        assert_eq!(undo.current(), 4);
+        assert_eq!(undo.states.keys().copied().collect::<Vec<_>>(), vec![2, 3, 4]);
+        assert!(undo.undo().is_some());
+        assert!(undo.undo().is_some());
+        assert!(undo.undo().is_none());
+        assert!(undo.earlier().is_none());
+    }
+
+    #[test]
+    fn test_undo_tree() {
+        let mut editor = Editor::with_text("one");
+
+        editor.feed("A two<Esc>");
+        editor.feed("u");
+        assert_eq!(editor.buffer().to_string(), "one");
+
+        editor.feed("A three<Esc>");
+        assert_eq!(editor.buffer().to_string(), "one three");
+
+        editor.feed("g-");
+        assert_eq!(editor.buffer().to_string(), "one two");
+
+        editor.feed("g-");
+        assert_eq!(editor.buffer().to_string(), "one");
+
+        editor.feed("2g+");
+        assert_eq!(editor.buffer().to_string(), "one three");
+
+        editor.feed("u<C-r>");
+        assert_eq!(editor.buffer().to_string(), "one three");
+
+        editor.feed("g+");
+        assert_eq!(editor.message(), Some("Already at newest change"));
+    }
+
+    #[test]
+    fn test_undo_commands() {
+        let mut editor = Editor::with_text("foo\nbar\nbaz");
+
+        editor.feed("jddp");
+        assert_eq!(editor.buffer().to_string(), "foo\nbaz\nbar");
+
+        editor.feed("u");
+        assert_eq!(editor.buffer().to_string(), "foo\nbaz");
+
+        editor.feed("u");
+        assert_eq!(editor.buffer().to_string(), "foo\nbar\nbaz");
+        assert_eq!(editor.cursor().row(), 1);
+
+        editor.feed("u");
+        assert_eq!(editor.message(), Some("Already at oldest change"));
+
+        editor.feed("2<C-r>");
+        assert_eq!(editor.buffer().to_string(), "foo\nbaz\nbar");
+    }
+
+    #[test]
+    fn test_undo_group() {
+        let mut editor = Editor::with_text("foo\nbar");
+
+        editor.begin_undo_group();
+        editor.feed("A!<Esc>");
+
+        editor.begin_undo_group();
+        editor.feed("jdd");
+        editor.end_undo_group();
+
+        editor.feed("P");
+        editor.end_undo_group();
+
+        assert_eq!(editor.buffer().to_string(), "bar\nfoo!");
+
+        editor.feed("u");
+        assert_eq!(editor.buffer().to_string(), "foo\nbar");
+
+        editor.feed("<C-r>");
+        assert_eq!(editor.buffer().to_string(), "bar\nfoo!");
+
+        editor.feed("A?<Esc>u");
+        assert_eq!(editor.buffer().to_string(), "bar\nfoo!");
+    }
+}